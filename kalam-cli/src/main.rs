@@ -0,0 +1,141 @@
+//! `kalam render <board.json> <out.png|out.svg> [--scale N] [--watermark TEXT]`
+//!
+//! Renders a saved board without opening a window. The output is cropped to the
+//! items plus a small margin and has a transparent background. A watermark is
+//! placed as a text box below the bottom-right corner of the items.
+
+use std::path::Path;
+use std::process::ExitCode;
+
+use ab_glyph::FontRef;
+use kalam_core::{board, CanvasItem, TextBackground, TextBox};
+
+mod raster;
+mod svg;
+
+const USAGE: &str =
+    "usage: kalam render <board.json> <out.png|out.svg> [--scale N] [--watermark TEXT]";
+
+/// Empty space kept around the items, in board units.
+const MARGIN: f32 = 16.0;
+
+/// Half-transparent white, premultiplied like item colors.
+const WATERMARK_RGBA: [u8; 4] = [128, 128, 128, 128];
+
+/// The part of the board being rendered.
+pub struct Frame {
+    pub origin: [f32; 2],
+    pub size: [f32; 2],
+    pub scale: f32,
+}
+
+impl Frame {
+    fn around(items: &[CanvasItem], font: &FontRef, scale: f32) -> Self {
+        let bounds = bounds(items, font);
+        Self {
+            origin: [bounds[0][0] - MARGIN, bounds[0][1] - MARGIN],
+            size: [
+                bounds[1][0] - bounds[0][0] + 2.0 * MARGIN,
+                bounds[1][1] - bounds[0][1] + 2.0 * MARGIN,
+            ],
+            scale,
+        }
+    }
+}
+
+fn bounds(items: &[CanvasItem], font: &FontRef) -> [[f32; 2]; 2] {
+    items
+        .iter()
+        .filter_map(|item| item.bounds(item.text_box().map(|t| raster::text_size(font, t))))
+        .reduce(|a, b| {
+            [
+                [a[0][0].min(b[0][0]), a[0][1].min(b[0][1])],
+                [a[1][0].max(b[1][0]), a[1][1].max(b[1][1])],
+            ]
+        })
+        .unwrap_or([[0.0, 0.0], [0.0, 0.0]])
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("render") => render(&args[1..]),
+        _ => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn render(args: &[String]) -> Result<(), String> {
+    let mut paths = Vec::new();
+    let mut scale = 1.0;
+    let mut watermark = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--scale" {
+            scale = args
+                .next()
+                .and_then(|s| s.parse::<f32>().ok())
+                .filter(|s| *s > 0.0)
+                .ok_or("--scale needs a positive number")?;
+        } else if arg == "--watermark" {
+            watermark = Some(args.next().ok_or("--watermark needs a text")?);
+        } else {
+            paths.push(arg);
+        }
+    }
+    let [input, output] = paths[..] else {
+        return Err(USAGE.to_string());
+    };
+
+    let json = std::fs::read_to_string(input).map_err(|e| format!("{input}: {e}"))?;
+    let mut items = board::from_json(&json).map_err(|e| format!("{input}: {e}"))?;
+
+    let fonts = epaint::text::FontDefinitions::default();
+    let font_data = fonts
+        .font_data
+        .get("Ubuntu-Light")
+        .ok_or("the default font is missing")?;
+    let font = FontRef::try_from_slice(&font_data.font).map_err(|e| e.to_string())?;
+    if let Some(text) = watermark.filter(|text| !text.trim().is_empty()) {
+        let [_, max] = bounds(&items, &font);
+        let mut mark = TextBox {
+            pos: max,
+            text: text.trim().to_string(),
+            rgba: WATERMARK_RGBA,
+            wrap_width: None,
+            background: TextBackground::default(),
+        };
+        mark.pos[0] -= raster::text_size(&font, &mark)[0];
+        mark.pos[1] += MARGIN / 2.0;
+        items.push(CanvasItem::Text(mark));
+    }
+    let frame = Frame::around(&items, &font, scale);
+
+    let extension = Path::new(output)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("png") => raster::render_png(&items, &font, &frame, Path::new(output)),
+        Some("svg") => std::fs::write(output, svg::render_svg(&items, &font, &frame))
+            .map_err(|e| format!("{output}: {e}")),
+        Some("pdf") => Err("PDF output is not supported yet; render to SVG and convert it".into()),
+        _ => Err(format!("{output}: expected a .png or .svg file name")),
+    }
+}
+
+/// Splits premultiplied `rgba` (as stored in items) into straight color and alpha.
+pub fn unpremultiply(rgba: [u8; 4]) -> ([u8; 3], u8) {
+    let [r, g, b, a] = rgba;
+    if a == 0 {
+        return ([0, 0, 0], 0);
+    }
+    let channel = |c: u8| ((c as u32 * 255 + a as u32 / 2) / a as u32).min(255) as u8;
+    ([channel(r), channel(g), channel(b)], a)
+}
@@ -0,0 +1,377 @@
+use std::path::Path;
+
+use ab_glyph::{Font, FontRef, PxScale, PxScaleFont, ScaleFont};
+use kalam_core::{geometry, CanvasItem, Marker, TextBox};
+use rayon::prelude::*;
+use tiny_skia::{
+    FillRule, LineCap, LineJoin, Paint, PathBuilder, Pixmap, PremultipliedColorU8, Stroke,
+    Transform,
+};
+
+use crate::{unpremultiply, Frame};
+
+const FONT_SIZE: f32 = 18.0;
+const LINE_SPACING: f32 = 22.0;
+const MARKER_FONT_SIZE: f32 = 16.0;
+/// Rows of output pixels rendered per parallel task.
+const BAND_HEIGHT: u32 = 256;
+
+pub fn render_png(
+    items: &[CanvasItem],
+    font: &FontRef,
+    frame: &Frame,
+    output: &Path,
+) -> Result<(), String> {
+    let width = (frame.size[0] * frame.scale).ceil().max(1.0) as u32;
+    let height = (frame.size[1] * frame.scale).ceil().max(1.0) as u32;
+    let mut pixmap =
+        Pixmap::new(width, height).ok_or_else(|| format!("cannot render {width}x{height}"))?;
+
+    // Bands are rendered in parallel, each with just the items that reach into it,
+    // then copied into the final image in order.
+    let bands: Vec<(u32, Pixmap)> = (0..height)
+        .step_by(BAND_HEIGHT as usize)
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .filter_map(|top| {
+            let band_height = BAND_HEIGHT.min(height - top);
+            let band = render_band(items, font, frame, width, top, band_height)?;
+            Some((top, band))
+        })
+        .collect();
+    let row_bytes = width as usize * 4;
+    for (top, band) in bands {
+        let start = top as usize * row_bytes;
+        pixmap.data_mut()[start..start + band.data().len()].copy_from_slice(band.data());
+    }
+
+    pixmap
+        .save_png(output)
+        .map_err(|e| format!("{}: {e}", output.display()))
+}
+
+/// Renders the horizontal strip of the output starting `top` pixels down.
+fn render_band(
+    items: &[CanvasItem],
+    font: &FontRef,
+    frame: &Frame,
+    width: u32,
+    top: u32,
+    height: u32,
+) -> Option<Pixmap> {
+    let mut pixmap = Pixmap::new(width, height)?;
+    let transform = Transform::from_row(
+        frame.scale,
+        0.0,
+        0.0,
+        frame.scale,
+        -frame.origin[0] * frame.scale,
+        -frame.origin[1] * frame.scale - top as f32,
+    );
+    let band_min = frame.origin[1] + top as f32 / frame.scale;
+    let band_max = frame.origin[1] + (top + height) as f32 / frame.scale;
+    for item in items {
+        let Some([min, max]) = item.bounds(item.text_box().map(|t| text_size(font, t))) else {
+            continue;
+        };
+        if max[1] < band_min || min[1] > band_max {
+            continue;
+        }
+        match item {
+            CanvasItem::Stroke(path) if !path.widths.is_empty() => {
+                let mut builder = PathBuilder::new();
+                let parts = geometry::variable_width_outline(&path.points, &path.point_widths());
+                for part in parts {
+                    for (idx, p) in part.iter().enumerate() {
+                        if idx == 0 {
+                            builder.move_to(p[0], p[1]);
+                        } else {
+                            builder.line_to(p[0], p[1]);
+                        }
+                    }
+                    builder.close();
+                }
+                if let Some(outline) = builder.finish() {
+                    pixmap.fill_path(
+                        &outline,
+                        &paint(path.rgba),
+                        FillRule::Winding,
+                        transform,
+                        None,
+                    );
+                }
+            }
+            CanvasItem::Stroke(path) => stroke_points(
+                &mut pixmap,
+                &path.points,
+                false,
+                path.thickness,
+                path.rgba,
+                transform,
+            ),
+            CanvasItem::Polygon(poly) => {
+                if let Some(fill) = poly.fill {
+                    fill_points(&mut pixmap, &poly.points, fill, transform);
+                }
+                stroke_points(
+                    &mut pixmap,
+                    &poly.points,
+                    true,
+                    poly.thickness,
+                    poly.rgba,
+                    transform,
+                )
+            }
+            CanvasItem::Text(t) => text(&mut pixmap, font, t, frame, top, transform),
+            CanvasItem::Ellipse(ellipse) => {
+                let outline = ellipse.outline();
+                if let Some(fill) = ellipse.fill {
+                    fill_points(&mut pixmap, &outline, fill, transform);
+                }
+                stroke_points(
+                    &mut pixmap,
+                    &outline,
+                    true,
+                    ellipse.thickness,
+                    ellipse.rgba,
+                    transform,
+                )
+            }
+            CanvasItem::Line(line) => stroke_points(
+                &mut pixmap,
+                &[line.start, line.end],
+                false,
+                line.thickness,
+                line.rgba,
+                transform,
+            ),
+            CanvasItem::Marker(m) => marker(&mut pixmap, font, m, frame, top, transform),
+            CanvasItem::Stamp(stamp) => {
+                for (points, closed) in stamp.lines() {
+                    stroke_points(
+                        &mut pixmap,
+                        &points,
+                        closed,
+                        stamp.thickness(),
+                        stamp.rgba,
+                        transform,
+                    );
+                }
+            }
+            CanvasItem::Callout(callout) => {
+                if let Some(tail) = callout.tail(text_size(font, &callout.text)) {
+                    let mut builder = PathBuilder::new();
+                    builder.move_to(tail[0][0], tail[0][1]);
+                    builder.line_to(tail[1][0], tail[1][1]);
+                    builder.line_to(tail[2][0], tail[2][1]);
+                    builder.close();
+                    if let Some(path) = builder.finish() {
+                        let rgba = callout.text.background.rgba();
+                        pixmap.fill_path(&path, &paint(rgba), FillRule::Winding, transform, None);
+                    }
+                }
+                text(&mut pixmap, font, &callout.text, frame, top, transform);
+            }
+        }
+    }
+    Some(pixmap)
+}
+
+fn paint(rgba: [u8; 4]) -> Paint<'static> {
+    let ([r, g, b], a) = unpremultiply(rgba);
+    let mut paint = Paint::default();
+    paint.set_color_rgba8(r, g, b, a);
+    paint.anti_alias = true;
+    paint
+}
+
+/// Fills the closed outline through `points` by the even-odd rule.
+fn fill_points(pixmap: &mut Pixmap, points: &[[f32; 2]], rgba: [u8; 4], transform: Transform) {
+    let mut builder = PathBuilder::new();
+    for (idx, p) in points.iter().enumerate() {
+        if idx == 0 {
+            builder.move_to(p[0], p[1]);
+        } else {
+            builder.line_to(p[0], p[1]);
+        }
+    }
+    builder.close();
+    if let Some(path) = builder.finish() {
+        pixmap.fill_path(&path, &paint(rgba), FillRule::EvenOdd, transform, None);
+    }
+}
+
+fn stroke_points(
+    pixmap: &mut Pixmap,
+    points: &[[f32; 2]],
+    closed: bool,
+    thickness: f32,
+    rgba: [u8; 4],
+    transform: Transform,
+) {
+    let mut builder = PathBuilder::new();
+    for (idx, p) in points.iter().enumerate() {
+        if idx == 0 {
+            builder.move_to(p[0], p[1]);
+        } else {
+            builder.line_to(p[0], p[1]);
+        }
+    }
+    if closed {
+        builder.close();
+    }
+    let Some(path) = builder.finish() else {
+        return;
+    };
+    let stroke = Stroke {
+        width: thickness,
+        line_cap: LineCap::Round,
+        line_join: LineJoin::Round,
+        ..Default::default()
+    };
+    pixmap.stroke_path(&path, &paint(rgba), &stroke, transform, None);
+}
+
+fn text(
+    pixmap: &mut Pixmap,
+    font: &FontRef,
+    t: &TextBox,
+    frame: &Frame,
+    top: u32,
+    transform: Transform,
+) {
+    let [width, height] = text_size(font, t);
+    let style = t.background;
+    if let Some(backdrop) = rounded_rect(t.pos, [width, height], style.corner_radius) {
+        pixmap.fill_path(
+            &backdrop,
+            &paint(style.rgba()),
+            FillRule::Winding,
+            transform,
+            None,
+        );
+    }
+
+    // Glyphs are placed in pixel space so they are rasterized at the output scale.
+    let scale = frame.scale;
+    let lines = text_lines(font, t);
+    let block = (lines.len().max(1) - 1) as f32 * LINE_SPACING + FONT_SIZE;
+    let text_top = t.pos[1] + (height - block) / 2.0;
+    let font = font.as_scaled(PxScale::from(FONT_SIZE * scale));
+    for (idx, line) in lines.iter().enumerate() {
+        let baseline = (text_top + idx as f32 * LINE_SPACING - frame.origin[1]) * scale
+            - top as f32
+            + font.ascent() / font.height() * FONT_SIZE * scale;
+        let x = (t.pos[0] + style.padding[0] - frame.origin[0]) * scale;
+        glyph_run(pixmap, &font, line, [x, baseline], t.rgba);
+    }
+}
+
+fn marker(
+    pixmap: &mut Pixmap,
+    font: &FontRef,
+    m: &Marker,
+    frame: &Frame,
+    top: u32,
+    transform: Transform,
+) {
+    if let Some(badge) = PathBuilder::from_circle(m.pos[0], m.pos[1], Marker::RADIUS) {
+        pixmap.fill_path(&badge, &paint(m.rgba), FillRule::Winding, transform, None);
+    }
+    let scale = frame.scale;
+    let font = font.as_scaled(PxScale::from(MARKER_FONT_SIZE * scale));
+    let label = m.number.to_string();
+    let width: f32 = label
+        .chars()
+        .map(|c| font.h_advance(font.glyph_id(c)))
+        .sum();
+    let x = (m.pos[0] - frame.origin[0]) * scale - width / 2.0;
+    // Digits stand about 0.7 em tall, so this centres them on the badge.
+    let baseline = (m.pos[1] - frame.origin[1]) * scale - top as f32 + 0.35 * font.scale().y;
+    glyph_run(pixmap, &font, &label, [x, baseline], m.label_rgba());
+}
+
+/// The lines of a text box, wrapped by their width in the overlay's font.
+pub fn text_lines(font: &FontRef, t: &TextBox) -> Vec<String> {
+    let font = font.as_scaled(PxScale::from(FONT_SIZE));
+    t.lines(|line| line.chars().map(|c| font.h_advance(font.glyph_id(c))).sum())
+}
+
+/// Size of a text box's backdrop, with its text laid out in the overlay's font.
+pub fn text_size(font: &FontRef, t: &TextBox) -> [f32; 2] {
+    let font = font.as_scaled(PxScale::from(FONT_SIZE));
+    t.size_with(
+        |line| line.chars().map(|c| font.h_advance(font.glyph_id(c))).sum(),
+        LINE_SPACING,
+    )
+}
+
+/// Draws one line of text starting at `origin`, a point on the baseline in pixels.
+fn glyph_run(
+    pixmap: &mut Pixmap,
+    font: &PxScaleFont<&FontRef>,
+    line: &str,
+    origin: [f32; 2],
+    rgba: [u8; 4],
+) {
+    let (color, alpha) = unpremultiply(rgba);
+    let [mut x, baseline] = origin;
+    let mut previous = None;
+    for c in line.chars() {
+        let id = font.glyph_id(c);
+        if let Some(previous) = previous {
+            x += font.kern(previous, id);
+        }
+        let glyph = id.with_scale_and_position(font.scale(), ab_glyph::point(x, baseline));
+        x += font.h_advance(id);
+        previous = Some(id);
+        let Some(outline) = font.outline_glyph(glyph) else {
+            continue;
+        };
+        let bounds = outline.px_bounds();
+        outline.draw(|gx, gy, coverage| {
+            let px = bounds.min.x as i32 + gx as i32;
+            let py = bounds.min.y as i32 + gy as i32;
+            blend(pixmap, px, py, color, alpha as f32 / 255.0 * coverage);
+        });
+    }
+}
+
+/// Composites a straight color with the given opacity over one pixel.
+fn blend(pixmap: &mut Pixmap, x: i32, y: i32, color: [u8; 3], opacity: f32) {
+    let (width, height) = (pixmap.width() as i32, pixmap.height() as i32);
+    if x < 0 || y < 0 || x >= width || y >= height || opacity <= 0.0 {
+        return;
+    }
+    let pixel = &mut pixmap.pixels_mut()[(y * width + x) as usize];
+    let opacity = opacity.min(1.0);
+    let mix =
+        |src: u8, dst: u8| (src as f32 * opacity + dst as f32 * (1.0 - opacity)).round() as u8;
+    let blended = PremultipliedColorU8::from_rgba(
+        mix(color[0], pixel.red()),
+        mix(color[1], pixel.green()),
+        mix(color[2], pixel.blue()),
+        mix(255, pixel.alpha()),
+    );
+    if let Some(blended) = blended {
+        *pixel = blended;
+    }
+}
+
+fn rounded_rect(min: [f32; 2], size: [f32; 2], radius: f32) -> Option<tiny_skia::Path> {
+    let [x, y] = min;
+    let [w, h] = size;
+    let r = radius.min(w / 2.0).min(h / 2.0);
+    let mut builder = PathBuilder::new();
+    builder.move_to(x + r, y);
+    builder.line_to(x + w - r, y);
+    builder.quad_to(x + w, y, x + w, y + r);
+    builder.line_to(x + w, y + h - r);
+    builder.quad_to(x + w, y + h, x + w - r, y + h);
+    builder.line_to(x + r, y + h);
+    builder.quad_to(x, y + h, x, y + h - r);
+    builder.line_to(x, y + r);
+    builder.quad_to(x, y, x + r, y);
+    builder.close();
+    builder.finish()
+}
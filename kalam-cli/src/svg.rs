@@ -0,0 +1,181 @@
+use std::fmt::Write;
+
+use ab_glyph::FontRef;
+use kalam_core::{geometry, CanvasItem, Marker, TextBox};
+
+use crate::raster::{text_lines, text_size};
+use crate::{unpremultiply, Frame};
+
+/// `font` is only used to measure text for wrapping; viewers draw it in their own.
+pub fn render_svg(items: &[CanvasItem], font: &FontRef, frame: &Frame) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="{} {} {} {}">"#,
+        frame.size[0] * frame.scale,
+        frame.size[1] * frame.scale,
+        frame.origin[0],
+        frame.origin[1],
+        frame.size[0],
+        frame.size[1],
+    );
+    for item in items {
+        match item {
+            CanvasItem::Stroke(path) if !path.widths.is_empty() => {
+                let outline: String =
+                    geometry::variable_width_outline(&path.points, &path.point_widths())
+                        .iter()
+                        .map(|part| format!("M{}Z", points(part)))
+                        .collect();
+                let _ = writeln!(
+                    out,
+                    r#"  <path d="{outline}" fill-rule="nonzero" {}/>"#,
+                    paint("fill", path.rgba),
+                );
+            }
+            CanvasItem::Stroke(path) => {
+                let _ = writeln!(
+                    out,
+                    r#"  <polyline points="{}" fill="none" {} stroke-width="{}" stroke-linecap="round" stroke-linejoin="round"/>"#,
+                    points(&path.points),
+                    paint("stroke", path.rgba),
+                    path.thickness,
+                );
+            }
+            CanvasItem::Polygon(poly) => {
+                let _ = writeln!(
+                    out,
+                    r#"  <polygon points="{}" {} {} stroke-width="{}" stroke-linejoin="round"/>"#,
+                    points(&poly.points),
+                    fill(poly.fill),
+                    paint("stroke", poly.rgba),
+                    poly.thickness,
+                );
+            }
+            CanvasItem::Text(t) => text(&mut out, font, t),
+            CanvasItem::Ellipse(ellipse) => {
+                let [cx, cy] = ellipse.center;
+                let _ = writeln!(
+                    out,
+                    r#"  <ellipse cx="{cx}" cy="{cy}" rx="{}" ry="{}" {} {} stroke-width="{}" transform="rotate({} {cx} {cy})"/>"#,
+                    ellipse.radii[0],
+                    ellipse.radii[1],
+                    fill(ellipse.fill),
+                    paint("stroke", ellipse.rgba),
+                    ellipse.thickness,
+                    ellipse.rotation.to_degrees(),
+                );
+            }
+            CanvasItem::Line(line) => {
+                let _ = writeln!(
+                    out,
+                    r#"  <line x1="{}" y1="{}" x2="{}" y2="{}" {} stroke-width="{}" stroke-linecap="round"/>"#,
+                    line.start[0],
+                    line.start[1],
+                    line.end[0],
+                    line.end[1],
+                    paint("stroke", line.rgba),
+                    line.thickness,
+                );
+            }
+            CanvasItem::Marker(m) => {
+                let [x, y] = m.pos;
+                let _ = writeln!(
+                    out,
+                    r#"  <circle cx="{x}" cy="{y}" r="{}" {}/>"#,
+                    Marker::RADIUS,
+                    paint("fill", m.rgba),
+                );
+                let _ = writeln!(
+                    out,
+                    r#"  <text x="{x}" y="{y}" text-anchor="middle" dominant-baseline="central" font-family="Ubuntu, sans-serif" font-size="16" {}>{}</text>"#,
+                    paint("fill", m.label_rgba()),
+                    m.number,
+                );
+            }
+            CanvasItem::Stamp(stamp) => {
+                for (line, closed) in stamp.lines() {
+                    let _ = writeln!(
+                        out,
+                        r#"  <{} points="{}" fill="none" {} stroke-width="{}" stroke-linecap="round" stroke-linejoin="round"/>"#,
+                        if closed { "polygon" } else { "polyline" },
+                        points(&line),
+                        paint("stroke", stamp.rgba),
+                        stamp.thickness(),
+                    );
+                }
+            }
+            CanvasItem::Callout(callout) => {
+                if let Some(tail) = callout.tail(text_size(font, &callout.text)) {
+                    let _ = writeln!(
+                        out,
+                        r#"  <polygon points="{}" {}/>"#,
+                        points(&tail),
+                        paint("fill", callout.text.background.rgba()),
+                    );
+                }
+                text(&mut out, font, &callout.text);
+            }
+        }
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+fn text(out: &mut String, font: &FontRef, t: &TextBox) {
+    let [width, height] = text_size(font, t);
+    let style = t.background;
+    let _ = writeln!(
+        out,
+        r#"  <rect x="{}" y="{}" width="{width}" height="{height}" rx="{}" {}/>"#,
+        t.pos[0],
+        t.pos[1],
+        style.corner_radius,
+        paint("fill", style.rgba()),
+    );
+    let x = t.pos[0] + style.padding[0];
+    let lines = text_lines(font, t);
+    // Matches the overlay: lines are centred vertically in the box, 22 units apart.
+    let block = (lines.len().max(1) - 1) as f32 * 22.0 + 18.0;
+    let first_baseline = t.pos[1] + (height - block) / 2.0 + 14.0;
+    let _ = write!(
+        out,
+        r#"  <text x="{x}" y="{first_baseline}" font-family="Ubuntu, sans-serif" font-size="18" {}>"#,
+        paint("fill", t.rgba),
+    );
+    for (idx, line) in lines.iter().enumerate() {
+        let dy = if idx == 0 { 0.0 } else { 22.0 };
+        let _ = write!(out, r#"<tspan x="{x}" dy="{dy}">{}</tspan>"#, escape(line));
+    }
+    out.push_str("</text>\n");
+}
+
+fn points(points: &[[f32; 2]]) -> String {
+    points
+        .iter()
+        .map(|p| format!("{},{}", p[0], p[1]))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn paint(attribute: &str, rgba: [u8; 4]) -> String {
+    let ([r, g, b], a) = unpremultiply(rgba);
+    format!(
+        r##"{attribute}="#{r:02x}{g:02x}{b:02x}" {attribute}-opacity="{:.3}""##,
+        a as f32 / 255.0
+    )
+}
+
+/// The fill of a closed shape, by the same even-odd rule as the PNG output.
+fn fill(rgba: Option<[u8; 4]>) -> String {
+    match rgba {
+        Some(rgba) => format!(r#"{} fill-rule="evenodd""#, paint("fill", rgba)),
+        None => r#"fill="none""#.to_string(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,12 @@
+//! Saved boards: a JSON list of items, the same format per-author exports use.
+
+use crate::CanvasItem;
+
+pub fn to_json<'a>(items: impl IntoIterator<Item = &'a CanvasItem>) -> serde_json::Result<String> {
+    let items: Vec<&CanvasItem> = items.into_iter().collect();
+    serde_json::to_string_pretty(&items)
+}
+
+pub fn from_json(json: &str) -> serde_json::Result<Vec<CanvasItem>> {
+    serde_json::from_str(json)
+}
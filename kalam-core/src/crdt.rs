@@ -0,0 +1,478 @@
+//! Replicated item list used by collaborative sessions.
+//!
+//! Every item gets a unique [`ItemId`] (a Lamport clock plus a per-process site id)
+//! and remembers the display name of whoever drew it and, for items that fade out,
+//! the wall-clock time they expire at, so every peer fades them together.
+//! Inserts and removals are idempotent and commute, removals win over a late insert,
+//! and the list is kept ordered by id, so peers that have seen the same set of
+//! operations show identical boards no matter the order those operations arrived in.
+//! Edits update an item in place under a version stamp from the same clock; of two
+//! concurrent edits the one with the higher stamp wins on every peer, and the item
+//! keeps its id and its place in the order. An edit that arrives before its item is
+//! held back until the item does.
+//!
+//! Removals are kept as tombstones for as long as the list lives, since a peer may
+//! still send a removed item: every id ever removed stays in memory and goes out
+//! again with each [`ItemList::state`], a few dozen bytes of JSON apiece. A long
+//! session with a lot of erasing therefore makes catching up a joining peer slower;
+//! tombstones are not journaled, so a restart drops them.
+
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{BuildHasher, Hasher};
+use std::ops::Deref;
+
+use serde::{Deserialize, Serialize};
+
+use crate::CanvasItem;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ItemId {
+    clock: u64,
+    site: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Op {
+    Insert {
+        id: ItemId,
+        author: String,
+        item: CanvasItem,
+        /// Seconds since the Unix epoch at which the item fades out, if it does.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        expires: Option<f64>,
+    },
+    Remove {
+        id: ItemId,
+    },
+    /// Replaces the content of an existing item, unless it already holds a newer version.
+    Update {
+        id: ItemId,
+        version: ItemId,
+        item: CanvasItem,
+    },
+}
+
+struct ItemMeta {
+    id: ItemId,
+    author: String,
+    expires: Option<f64>,
+    /// Stamp of the last edit; the id itself until the item is first edited.
+    version: ItemId,
+}
+
+pub struct ItemList {
+    items: Vec<CanvasItem>,
+    /// Parallel to `items` and always sorted by id, which is what makes the order deterministic.
+    meta: Vec<ItemMeta>,
+    removed: HashSet<ItemId>,
+    /// Edits whose item has not arrived yet, newest version per item.
+    pending: HashMap<ItemId, (ItemId, CanvasItem)>,
+    site: u64,
+    author: String,
+    clock: u64,
+    outbox: Vec<Op>,
+}
+
+impl ItemList {
+    /// Name attached to items drawn locally from now on.
+    pub fn set_author(&mut self, author: &str) {
+        if self.author != author {
+            self.author = author.to_string();
+        }
+    }
+
+    /// Name attached to items drawn locally.
+    pub fn author(&self) -> &str {
+        &self.author
+    }
+
+    /// Appends a locally drawn item and returns its new id.
+    pub fn push(&mut self, item: CanvasItem) -> ItemId {
+        let author = self.author.clone();
+        self.push_as(item, author, None)
+    }
+
+    /// Like [`Self::push`], but keeps `author` instead of the local name, e.g. when an
+    /// edit replaces someone else's item, and fades out at `expires` if given.
+    pub fn push_as(&mut self, item: CanvasItem, author: String, expires: Option<f64>) -> ItemId {
+        // A fresh id is newer than everything seen so far, so it always sorts last.
+        self.clock += 1;
+        let id = ItemId {
+            clock: self.clock,
+            site: self.site,
+        };
+        self.outbox.push(Op::Insert {
+            id,
+            author: author.clone(),
+            item: item.clone(),
+            expires,
+        });
+        self.items.push(item);
+        self.meta.push(ItemMeta {
+            id,
+            author,
+            expires,
+            version: id,
+        });
+        id
+    }
+
+    /// Replaces an item's content while keeping its id, author, and place in the order.
+    /// Returns false if there is no such item.
+    pub fn update(&mut self, id: ItemId, item: CanvasItem) -> bool {
+        let Some(idx) = self.index_of(id) else {
+            return false;
+        };
+        self.clock += 1;
+        let version = ItemId {
+            clock: self.clock,
+            site: self.site,
+        };
+        self.outbox.push(Op::Update {
+            id,
+            version,
+            item: item.clone(),
+        });
+        self.items[idx] = item;
+        self.meta[idx].version = version;
+        true
+    }
+
+    pub fn remove(&mut self, id: ItemId) -> Option<CanvasItem> {
+        let idx = self.index_of(id)?;
+        self.meta.remove(idx);
+        self.mark_removed(id);
+        Some(self.items.remove(idx))
+    }
+
+    pub fn clear(&mut self) {
+        for meta in std::mem::take(&mut self.meta) {
+            self.mark_removed(meta.id);
+        }
+        self.items.clear();
+    }
+
+    pub fn get(&self, id: ItemId) -> Option<(&CanvasItem, &str)> {
+        let idx = self.index_of(id)?;
+        Some((&self.items[idx], self.meta[idx].author.as_str()))
+    }
+
+    /// When the item fades out, in seconds since the Unix epoch; `None` if it stays.
+    pub fn expires(&self, id: ItemId) -> Option<f64> {
+        self.meta[self.index_of(id)?].expires
+    }
+
+    /// Ids of the items that fade out, with their deadlines.
+    pub fn iter_expiring(&self) -> impl Iterator<Item = (ItemId, f64)> + '_ {
+        self.meta
+            .iter()
+            .filter_map(|meta| Some((meta.id, meta.expires?)))
+    }
+
+    /// Id of the item drawn on top of all others.
+    pub fn last_id(&self) -> Option<ItemId> {
+        self.meta.last().map(|meta| meta.id)
+    }
+
+    /// Moves the clock past `stamp`, so ids and edits made here from now on are newer.
+    pub fn witness(&mut self, stamp: ItemId) {
+        self.clock = self.clock.max(stamp.clock);
+    }
+
+    /// Merges an operation from a peer. Returns whether the visible list changed.
+    pub fn apply(&mut self, op: Op) -> bool {
+        match op {
+            Op::Insert {
+                id,
+                author,
+                item,
+                expires,
+            } => {
+                self.clock = self.clock.max(id.clock);
+                if self.removed.contains(&id) {
+                    return false;
+                }
+                match self.meta.binary_search_by_key(&id, |m| m.id) {
+                    Ok(_) => false,
+                    Err(idx) => {
+                        let (version, item) = match self.pending.remove(&id) {
+                            Some((version, edited)) if version > id => (version, edited),
+                            _ => (id, item),
+                        };
+                        self.meta.insert(
+                            idx,
+                            ItemMeta {
+                                id,
+                                author,
+                                expires,
+                                version,
+                            },
+                        );
+                        self.items.insert(idx, item);
+                        true
+                    }
+                }
+            }
+            Op::Remove { id } => {
+                self.clock = self.clock.max(id.clock);
+                self.removed.insert(id);
+                self.pending.remove(&id);
+                match self.meta.binary_search_by_key(&id, |m| m.id) {
+                    Ok(idx) => {
+                        self.meta.remove(idx);
+                        self.items.remove(idx);
+                        true
+                    }
+                    Err(_) => false,
+                }
+            }
+            Op::Update { id, version, item } => {
+                self.clock = self.clock.max(version.clock);
+                match self.index_of(id) {
+                    Some(idx) if self.meta[idx].version < version => {
+                        self.meta[idx].version = version;
+                        self.items[idx] = item;
+                        true
+                    }
+                    Some(_) => false,
+                    None if self.removed.contains(&id) => false,
+                    None => {
+                        let newer = self
+                            .pending
+                            .get(&id)
+                            .map_or(true, |(pending, _)| *pending < version);
+                        if newer {
+                            self.pending.insert(id, (version, item));
+                        }
+                        false
+                    }
+                }
+            }
+        }
+    }
+
+    /// Every operation needed to bring a peer (even one that was offline) up to date.
+    pub fn state(&self) -> Vec<Op> {
+        let inserts = self
+            .meta
+            .iter()
+            .zip(&self.items)
+            .map(|(meta, item)| Op::Insert {
+                id: meta.id,
+                author: meta.author.clone(),
+                item: item.clone(),
+                expires: meta.expires,
+            });
+        // A peer that already has an older copy of an edited item ignores the insert.
+        let updates = self
+            .meta
+            .iter()
+            .zip(&self.items)
+            .filter(|(meta, _)| meta.version != meta.id)
+            .map(|(meta, item)| Op::Update {
+                id: meta.id,
+                version: meta.version,
+                item: item.clone(),
+            });
+        let pending = self.pending.iter().map(|(id, (version, item))| Op::Update {
+            id: *id,
+            version: *version,
+            item: item.clone(),
+        });
+        let removals = self.removed.iter().map(|id| Op::Remove { id: *id });
+        inserts
+            .chain(updates)
+            .chain(pending)
+            .chain(removals)
+            .collect()
+    }
+
+    pub fn iter_authored(&self) -> impl Iterator<Item = (&CanvasItem, &str)> {
+        self.items
+            .iter()
+            .zip(&self.meta)
+            .map(|(item, meta)| (item, meta.author.as_str()))
+    }
+
+    /// Like [`Self::iter_authored`], but also yields each item's id.
+    pub fn iter_with_ids(&self) -> impl Iterator<Item = (ItemId, &CanvasItem, &str)> {
+        self.items
+            .iter()
+            .zip(&self.meta)
+            .map(|(item, meta)| (meta.id, item, meta.author.as_str()))
+    }
+
+    /// Distinct authors with their item counts, sorted by name.
+    pub fn authors(&self) -> Vec<(String, usize)> {
+        let mut counts = BTreeMap::new();
+        for meta in &self.meta {
+            *counts.entry(meta.author.clone()).or_insert(0) += 1;
+        }
+        counts.into_iter().collect()
+    }
+
+    /// Local operations since the last call.
+    pub fn take_outbox(&mut self) -> Vec<Op> {
+        std::mem::take(&mut self.outbox)
+    }
+
+    fn index_of(&self, id: ItemId) -> Option<usize> {
+        self.meta.binary_search_by_key(&id, |m| m.id).ok()
+    }
+
+    fn mark_removed(&mut self, id: ItemId) {
+        self.removed.insert(id);
+        self.outbox.push(Op::Remove { id });
+    }
+}
+
+impl Default for ItemList {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            meta: Vec::new(),
+            removed: HashSet::new(),
+            pending: HashMap::new(),
+            site: RandomState::new().build_hasher().finish(),
+            author: String::new(),
+            clock: 0,
+            outbox: Vec::new(),
+        }
+    }
+}
+
+impl Deref for ItemList {
+    type Target = [CanvasItem];
+
+    fn deref(&self) -> &[CanvasItem] {
+        &self.items
+    }
+}
+
+impl<'a> IntoIterator for &'a ItemList {
+    type Item = &'a CanvasItem;
+    type IntoIter = std::slice::Iter<'a, CanvasItem>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Marker;
+
+    fn marker(number: u32) -> CanvasItem {
+        CanvasItem::Marker(Marker {
+            pos: [0.0, 0.0],
+            number,
+            rgba: [255, 0, 0, 255],
+        })
+    }
+
+    /// The marker numbers in the list, in drawing order.
+    fn numbers(list: &ItemList) -> Vec<u32> {
+        list.iter()
+            .map(|item| match item {
+                CanvasItem::Marker(marker) => marker.number,
+                _ => panic!("only markers are drawn in these tests"),
+            })
+            .collect()
+    }
+
+    fn replay(ops: &[Op]) -> ItemList {
+        let mut list = ItemList::default();
+        for op in ops {
+            list.apply(op.clone());
+        }
+        list
+    }
+
+    #[test]
+    fn applying_twice_changes_nothing() {
+        let mut source = ItemList::default();
+        let id = source.push(marker(1));
+        source.update(id, marker(2));
+        source.push(marker(3));
+        let ops = source.take_outbox();
+
+        let mut list = replay(&ops);
+        for op in ops {
+            assert!(!list.apply(op));
+        }
+        assert_eq!(numbers(&list), [2, 3]);
+    }
+
+    #[test]
+    fn inserts_and_removals_commute() {
+        let mut source = ItemList::default();
+        let first = source.push(marker(1));
+        source.push(marker(2));
+        source.push(marker(3));
+        source.remove(first);
+        let ops = source.take_outbox();
+
+        let mut reversed = ops.clone();
+        reversed.reverse();
+        assert_eq!(numbers(&replay(&ops)), [2, 3]);
+        assert_eq!(numbers(&replay(&reversed)), [2, 3]);
+    }
+
+    #[test]
+    fn a_removal_wins_over_a_late_insert() {
+        let mut source = ItemList::default();
+        let id = source.push(marker(1));
+        let insert = source.take_outbox();
+
+        let mut list = ItemList::default();
+        assert!(!list.apply(Op::Remove { id }));
+        assert!(!list.apply(insert[0].clone()));
+        assert!(numbers(&list).is_empty());
+        // The tombstone travels with the state, so a fresh peer drops the insert too.
+        let mut state = list.state();
+        state.extend(insert);
+        assert!(numbers(&replay(&state)).is_empty());
+    }
+
+    #[test]
+    fn the_newer_of_two_concurrent_edits_wins_everywhere() {
+        let mut alice = ItemList::default();
+        let id = alice.push(marker(1));
+        let mut bob = replay(&alice.take_outbox());
+
+        alice.update(id, marker(2));
+        bob.update(id, marker(3));
+        let from_alice = alice.take_outbox();
+        let from_bob = bob.take_outbox();
+        for op in from_bob {
+            alice.apply(op);
+        }
+        for op in from_alice {
+            bob.apply(op);
+        }
+        // Both edits carry the same clock, so the higher site id breaks the tie.
+        let winner = if alice.site > bob.site { 2 } else { 3 };
+        assert_eq!(numbers(&alice), [winner]);
+        assert_eq!(numbers(&bob), [winner]);
+    }
+
+    #[test]
+    fn an_edit_that_arrives_first_waits_for_its_item() {
+        let mut source = ItemList::default();
+        let id = source.push(marker(1));
+        source.update(id, marker(2));
+        let mut ops = source.take_outbox();
+        ops.reverse();
+
+        let list = replay(&ops);
+        assert_eq!(numbers(&list), [2]);
+        // A held-back edit is part of the state, so it reaches joining peers as well.
+        let mut waiting = ItemList::default();
+        waiting.apply(ops[0].clone());
+        let mut state = waiting.state();
+        state.push(ops[1].clone());
+        assert_eq!(numbers(&replay(&state)), [2]);
+    }
+}
@@ -0,0 +1,612 @@
+//! The board as seen by frontends: every change goes through a [`Command`] (or a
+//! replicated [`Op`] from a peer) and is announced to subscribers as an [`Event`].
+//!
+//! Subscribers get a channel rather than a callback so they can drain events on
+//! their own schedule, e.g. once per frame or from a background thread.
+//!
+//! Local commands are also kept as undo steps: each records the items it took off
+//! the board, the ids of those it put on, and the earlier content of those it
+//! edited in place, so undoing one swaps them back. A step
+//! is one command unless several are grouped between [`Document::begin_step`] and
+//! [`Document::end_step`]. Peer operations are not undoable here.
+
+use std::ops::Deref;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::crdt::{ItemId, ItemList, Op};
+use crate::CanvasItem;
+
+#[derive(Debug, Clone)]
+pub enum Command {
+    Add(CanvasItem),
+    /// Adds an item that fades out at `expires`, in seconds since the Unix epoch.
+    AddExpiring {
+        item: CanvasItem,
+        expires: f64,
+    },
+    Remove(ItemId),
+    /// Moves an item in place.
+    Transform {
+        id: ItemId,
+        offset: [f32; 2],
+    },
+    /// Swaps an item's content for an edited version, keeping its id, author,
+    /// fade-out time, and place in the drawing order.
+    Replace {
+        id: ItemId,
+        item: CanvasItem,
+    },
+    /// Swaps an item for the pieces left of it, all keeping its author and fade-out
+    /// time. No pieces removes it.
+    Split {
+        id: ItemId,
+        pieces: Vec<CanvasItem>,
+    },
+    Clear,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Added(ItemId),
+    Removed(ItemId),
+    /// The item's content changed; its id did not.
+    Updated(ItemId),
+    Cleared,
+}
+
+/// An item an undo step took off the board, with what it needs to go back on.
+#[derive(Debug, Clone)]
+pub struct Removed {
+    /// The id it had; putting it back gives it a new one.
+    pub id: ItemId,
+    pub item: CanvasItem,
+    pub author: String,
+    pub expires: Option<f64>,
+}
+
+/// What an undo step changed, enough to take it back.
+#[derive(Debug, Clone, Default)]
+pub struct Change {
+    /// Items the step took off the board.
+    pub removed: Vec<Removed>,
+    /// Items the step put on the board.
+    pub added: Vec<ItemId>,
+    /// Items the step changed in place, with their content from before, in the order
+    /// the changes were made.
+    pub edited: Vec<(ItemId, CanvasItem)>,
+}
+
+impl Change {
+    pub fn is_empty(&self) -> bool {
+        self.removed.is_empty() && self.added.is_empty() && self.edited.is_empty()
+    }
+}
+
+/// Undo steps kept unless [`Document::set_history_depth`] says otherwise.
+const DEFAULT_HISTORY_DEPTH: usize = 100;
+
+pub struct Document {
+    items: ItemList,
+    subscribers: Vec<Sender<Event>>,
+    undo: Vec<Change>,
+    redo: Vec<Change>,
+    /// The step being recorded, and how many `begin_step` calls it is nested in.
+    open: Option<(Change, usize)>,
+    history_depth: usize,
+}
+
+impl Default for Document {
+    fn default() -> Self {
+        Self {
+            items: ItemList::default(),
+            subscribers: Vec::new(),
+            undo: Vec::new(),
+            redo: Vec::new(),
+            open: None,
+            history_depth: DEFAULT_HISTORY_DEPTH,
+        }
+    }
+}
+
+impl Document {
+    /// Events for every change from now on.
+    pub fn subscribe(&mut self) -> Receiver<Event> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.push(tx);
+        rx
+    }
+
+    /// Applies a local command as an undo step, or as part of the open one. Returns
+    /// the id of the item it created, if it made exactly one.
+    pub fn execute(&mut self, command: Command) -> Option<ItemId> {
+        self.begin_step();
+        let id = self.run(command, true);
+        self.end_step();
+        id
+    }
+
+    /// Applies a local command that Undo should not take back, such as an item
+    /// expiring on its own.
+    pub fn execute_untracked(&mut self, command: Command) -> Option<ItemId> {
+        self.run(command, false)
+    }
+
+    /// Removes every item for which `remove(id, item, author)` returns true, as one
+    /// undo step.
+    pub fn remove_where(&mut self, mut remove: impl FnMut(ItemId, &CanvasItem, &str) -> bool) {
+        let doomed: Vec<ItemId> = self
+            .items
+            .iter_with_ids()
+            .filter(|(id, item, author)| remove(*id, item, author))
+            .map(|(id, _, _)| id)
+            .collect();
+        self.begin_step();
+        for id in doomed {
+            self.execute(Command::Remove(id));
+        }
+        self.end_step();
+    }
+
+    /// Starts grouping commands into one undo step, until the matching `end_step`.
+    /// Steps nest; only the outermost one is kept.
+    pub fn begin_step(&mut self) {
+        match &mut self.open {
+            Some((_, depth)) => *depth += 1,
+            None => self.open = Some((Change::default(), 1)),
+        }
+    }
+
+    pub fn end_step(&mut self) {
+        let Some((change, depth)) = &mut self.open else {
+            return;
+        };
+        *depth -= 1;
+        if *depth > 0 {
+            return;
+        }
+        let change = std::mem::take(change);
+        self.open = None;
+        if !change.is_empty() {
+            self.undo.push(change);
+            self.redo.clear();
+            self.trim_history();
+        }
+    }
+
+    /// Takes back the last step that still has something to take back. Returns
+    /// whether there was one.
+    pub fn undo(&mut self) -> bool {
+        while let Some(change) = self.undo.pop() {
+            let inverse = self.revert(change);
+            if !inverse.is_empty() {
+                self.redo.push(inverse);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Puts back the last undone step. Returns whether there was one.
+    pub fn redo(&mut self) -> bool {
+        while let Some(change) = self.redo.pop() {
+            let inverse = self.revert(change);
+            if !inverse.is_empty() {
+                self.undo.push(inverse);
+                self.trim_history();
+                return true;
+            }
+        }
+        false
+    }
+
+    /// The step Undo would take back.
+    pub fn undo_step(&self) -> Option<&Change> {
+        self.undo.last()
+    }
+
+    /// The step Redo would put back.
+    pub fn redo_step(&self) -> Option<&Change> {
+        self.redo.last()
+    }
+
+    /// Keeps at most `depth` undo steps, dropping the oldest.
+    pub fn set_history_depth(&mut self, depth: usize) {
+        self.history_depth = depth;
+        self.trim_history();
+    }
+
+    fn trim_history(&mut self) {
+        let excess = self.undo.len().saturating_sub(self.history_depth);
+        self.undo.drain(..excess);
+    }
+
+    /// Swaps back what `change` did and returns the change that swaps it again.
+    /// Items a peer has removed since, and items whose fade-out time has passed, are
+    /// skipped. Put-back items keep their fade-out time but get new ids, which
+    /// this and the other steps are updated to. Edits are undone last to first, so
+    /// an item edited twice in one step ends up as it was before both.
+    fn revert(&mut self, mut change: Change) -> Change {
+        let mut inverse = Change::default();
+        for id in change.added {
+            inverse.removed.extend(self.take_off(id));
+        }
+        let now = unix_time();
+        for removed in change.removed {
+            if removed.expires.is_some_and(|expires| expires <= now) {
+                continue;
+            }
+            let id = self
+                .items
+                .push_as(removed.item, removed.author, removed.expires);
+            self.emit(Event::Added(id));
+            inverse.added.push(id);
+            let edited = change.edited.iter_mut().map(|(id, _)| id);
+            for stale in edited.filter(|stale| **stale == removed.id) {
+                *stale = id;
+            }
+            self.rename(removed.id, id);
+        }
+        for (id, item) in change.edited.into_iter().rev() {
+            if let Some((current, _)) = self.items.get(id) {
+                inverse.edited.push((id, current.clone()));
+                self.items.update(id, item);
+                self.emit(Event::Updated(id));
+            }
+        }
+        inverse
+    }
+
+    /// Points the undo and redo steps that mention `old` at `new` instead.
+    fn rename(&mut self, old: ItemId, new: ItemId) {
+        for step in self.undo.iter_mut().chain(&mut self.redo) {
+            let ids = step
+                .added
+                .iter_mut()
+                .chain(step.removed.iter_mut().map(|removed| &mut removed.id))
+                .chain(step.edited.iter_mut().map(|(id, _)| id));
+            for stale in ids.filter(|stale| **stale == old) {
+                *stale = new;
+            }
+        }
+    }
+
+    /// Removes an item, returning it with what is needed to put it back.
+    fn take_off(&mut self, id: ItemId) -> Option<Removed> {
+        let (item, author) = self.items.get(id)?;
+        let removed = Removed {
+            id,
+            item: item.clone(),
+            author: author.to_string(),
+            expires: self.items.expires(id),
+        };
+        self.items.remove(id);
+        self.emit(Event::Removed(id));
+        Some(removed)
+    }
+
+    /// Applies `command`, noting what it changed in the open step if `track`.
+    fn run(&mut self, command: Command, track: bool) -> Option<ItemId> {
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+        let mut edited = Vec::new();
+        let result = match command {
+            Command::Add(item) => {
+                let id = self.items.push(item);
+                self.emit(Event::Added(id));
+                added.push(id);
+                Some(id)
+            }
+            Command::AddExpiring { item, expires } => {
+                let author = self.items.author().to_string();
+                let id = self.items.push_as(item, author, Some(expires));
+                self.emit(Event::Added(id));
+                added.push(id);
+                Some(id)
+            }
+            Command::Remove(id) => {
+                removed.push(self.take_off(id)?);
+                None
+            }
+            Command::Transform { id, offset } => {
+                let (item, _) = self.items.get(id)?;
+                let mut item = item.clone();
+                item.translate(offset);
+                return self.run(Command::Replace { id, item }, track);
+            }
+            Command::Replace { id, item } => {
+                let (old, _) = self.items.get(id)?;
+                edited.push((id, old.clone()));
+                self.items.update(id, item);
+                self.emit(Event::Updated(id));
+                Some(id)
+            }
+            Command::Split { id, pieces } => {
+                let old = self.take_off(id)?;
+                for piece in pieces {
+                    let piece = self.items.push_as(piece, old.author.clone(), old.expires);
+                    self.emit(Event::Added(piece));
+                    added.push(piece);
+                }
+                removed.push(old);
+                None
+            }
+            Command::Clear => {
+                removed.extend(
+                    self.items
+                        .iter_with_ids()
+                        .map(|(id, item, author)| Removed {
+                            id,
+                            item: item.clone(),
+                            author: author.to_string(),
+                            expires: self.items.expires(id),
+                        }),
+                );
+                self.items.clear();
+                self.emit(Event::Cleared);
+                None
+            }
+        };
+        if let (true, Some((change, _))) = (track, &mut self.open) {
+            change.removed.extend(removed);
+            change.added.extend(added);
+            change.edited.extend(edited);
+        }
+        result
+    }
+
+    /// Merges an operation from a peer.
+    pub fn apply(&mut self, op: Op) {
+        let event = match &op {
+            Op::Insert { id, .. } => Event::Added(*id),
+            Op::Remove { id } => Event::Removed(*id),
+            Op::Update { id, .. } => Event::Updated(*id),
+        };
+        if self.items.apply(op) {
+            self.emit(event);
+        }
+    }
+
+    /// Takes back operations a peer made that this board refused, such as edits that
+    /// reached the host while it had the canvas locked. Edits and removals are undone
+    /// for everyone through the outbox: an edited item is stamped anew with the
+    /// content it has here, and a removed one is put back under a new id, since
+    /// removals win over inserts, so it moves to the top. Returns the removals of the
+    /// peer's own new items, which only that peer has to be sent.
+    pub fn reject(&mut self, ops: Vec<Op>) -> Vec<Op> {
+        let mut replies = Vec::new();
+        for op in ops {
+            match op {
+                Op::Insert { id, .. } => {
+                    self.items.witness(id);
+                    if self.items.get(id).is_none() {
+                        replies.push(Op::Remove { id });
+                    }
+                }
+                Op::Update { id, version, .. } => {
+                    self.items.witness(version);
+                    match self.items.get(id) {
+                        Some((item, _)) => {
+                            let item = item.clone();
+                            self.items.update(id, item);
+                            self.emit(Event::Updated(id));
+                        }
+                        None => replies.push(Op::Remove { id }),
+                    }
+                }
+                Op::Remove { id } => {
+                    if let Some(removed) = self.take_off(id) {
+                        let restored =
+                            self.items
+                                .push_as(removed.item, removed.author, removed.expires);
+                        self.emit(Event::Added(restored));
+                        self.rename(id, restored);
+                    }
+                }
+            }
+        }
+        replies
+    }
+
+    pub fn set_author(&mut self, author: &str) {
+        self.items.set_author(author);
+    }
+
+    /// Local operations since the last call, for replication.
+    pub fn take_outbox(&mut self) -> Vec<Op> {
+        self.items.take_outbox()
+    }
+
+    fn emit(&mut self, event: Event) {
+        self.subscribers.retain(|tx| tx.send(event).is_ok());
+    }
+}
+
+/// Wall-clock seconds since the Unix epoch, the clock fade-out times are kept in so
+/// they mean the same on every peer.
+pub fn unix_time() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |elapsed| elapsed.as_secs_f64())
+}
+
+impl Deref for Document {
+    type Target = ItemList;
+
+    fn deref(&self) -> &ItemList {
+        &self.items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Marker;
+
+    fn marker(number: u32) -> CanvasItem {
+        CanvasItem::Marker(Marker {
+            pos: [0.0, 0.0],
+            number,
+            rgba: [255, 0, 0, 255],
+        })
+    }
+
+    /// The marker numbers on the board, in drawing order.
+    fn numbers(doc: &Document) -> Vec<u32> {
+        doc.iter()
+            .map(|item| match item {
+                CanvasItem::Marker(marker) => marker.number,
+                _ => panic!("only markers are drawn in these tests"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn replace_undoes_and_redoes_in_place() {
+        let mut doc = Document::default();
+        let id = doc.execute(Command::Add(marker(1))).unwrap();
+        doc.execute(Command::Add(marker(2)));
+        assert_eq!(
+            doc.execute(Command::Replace {
+                id,
+                item: marker(10),
+            }),
+            Some(id)
+        );
+        assert_eq!(numbers(&doc), [10, 2]);
+
+        assert!(doc.undo());
+        assert_eq!(numbers(&doc), [1, 2]);
+        assert!(doc.get(id).is_some());
+
+        assert!(doc.redo());
+        assert_eq!(numbers(&doc), [10, 2]);
+        assert!(doc.get(id).is_some());
+    }
+
+    #[test]
+    fn undo_skips_steps_a_peer_took_away() {
+        let mut doc = Document::default();
+        doc.execute(Command::Add(marker(1)));
+        let id = doc.execute(Command::Add(marker(2))).unwrap();
+        doc.apply(Op::Remove { id });
+        assert_eq!(numbers(&doc), [1]);
+
+        // The last step only added the item the peer removed, so the one before goes.
+        assert!(doc.undo());
+        assert!(numbers(&doc).is_empty());
+        assert!(!doc.undo());
+    }
+
+    #[test]
+    fn put_back_items_are_remapped_in_every_step() {
+        let mut doc = Document::default();
+        let id = doc.execute(Command::Add(marker(1))).unwrap();
+        doc.execute(Command::Replace {
+            id,
+            item: marker(2),
+        });
+        doc.execute(Command::Remove(id));
+        assert!(numbers(&doc).is_empty());
+
+        // Putting the item back gives it a new id, which the edit and the add before
+        // it have to follow.
+        assert!(doc.undo());
+        assert_eq!(numbers(&doc), [2]);
+        assert!(doc.undo());
+        assert_eq!(numbers(&doc), [1]);
+        assert!(doc.undo());
+        assert!(numbers(&doc).is_empty());
+
+        assert!(doc.redo());
+        assert!(doc.redo());
+        assert_eq!(numbers(&doc), [2]);
+        assert!(doc.redo());
+        assert!(numbers(&doc).is_empty());
+        assert!(!doc.redo());
+    }
+
+    #[test]
+    fn clear_round_trips() {
+        let mut doc = Document::default();
+        doc.execute(Command::Add(marker(1)));
+        doc.execute(Command::Add(marker(2)));
+        doc.execute(Command::Clear);
+        assert!(numbers(&doc).is_empty());
+
+        assert!(doc.undo());
+        assert_eq!(numbers(&doc), [1, 2]);
+        assert!(doc.redo());
+        assert!(numbers(&doc).is_empty());
+        assert!(doc.undo());
+        assert_eq!(numbers(&doc), [1, 2]);
+    }
+
+    #[test]
+    fn nested_steps_make_one_step() {
+        let mut doc = Document::default();
+        doc.begin_step();
+        doc.execute(Command::Add(marker(1)));
+        doc.begin_step();
+        doc.execute(Command::Add(marker(2)));
+        doc.end_step();
+        assert!(doc.undo_step().is_none());
+        doc.execute(Command::Add(marker(3)));
+        doc.end_step();
+
+        assert_eq!(doc.undo_step().map(|step| step.added.len()), Some(3));
+        assert!(doc.undo());
+        assert!(numbers(&doc).is_empty());
+        assert!(!doc.undo());
+    }
+
+    #[test]
+    fn history_depth_drops_the_oldest_steps() {
+        let mut doc = Document::default();
+        for number in 1..=5 {
+            doc.execute(Command::Add(marker(number)));
+        }
+        doc.set_history_depth(2);
+
+        assert!(doc.undo());
+        assert!(doc.undo());
+        assert!(!doc.undo());
+        assert_eq!(numbers(&doc), [1, 2, 3]);
+
+        // Redoing doesn't grow the history past the limit either.
+        assert!(doc.redo());
+        assert!(doc.redo());
+        doc.execute(Command::Add(marker(6)));
+        assert!(doc.undo());
+        assert!(doc.undo());
+        assert!(!doc.undo());
+        assert_eq!(numbers(&doc), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn rejected_ops_are_taken_back_on_the_sender() {
+        let mut host = Document::default();
+        let kept = host.execute(Command::Add(marker(1))).unwrap();
+        let edited = host.execute(Command::Add(marker(2))).unwrap();
+        let mut peer = Document::default();
+        for op in host.take_outbox() {
+            peer.apply(op);
+        }
+
+        peer.execute(Command::Remove(kept));
+        peer.execute(Command::Replace {
+            id: edited,
+            item: marker(20),
+        });
+        peer.execute(Command::Add(marker(3)));
+        assert_eq!(numbers(&peer), [20, 3]);
+
+        let replies = host.reject(peer.take_outbox());
+        for op in host.take_outbox().into_iter().chain(replies) {
+            peer.apply(op);
+        }
+        // The removed marker comes back on top, under a new id.
+        assert_eq!(numbers(&host), [2, 1]);
+        assert_eq!(numbers(&peer), [2, 1]);
+    }
+}
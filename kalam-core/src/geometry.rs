@@ -0,0 +1,361 @@
+//! Plain geometry helpers working on the `[x, y]` points stored in canvas items.
+
+/// Ramer–Douglas–Peucker simplification: drops points that deviate less than
+/// `epsilon` from the line between the points kept around them.
+pub fn simplify_polyline(points: &[[f32; 2]], epsilon: f32) -> Vec<[f32; 2]> {
+    simplify_indices(points, epsilon)
+        .into_iter()
+        .map(|idx| points[idx])
+        .collect()
+}
+
+/// Indices of the points [`simplify_polyline`] keeps, for carrying per-point values
+/// such as widths along.
+pub fn simplify_indices(points: &[[f32; 2]], epsilon: f32) -> Vec<usize> {
+    if points.len() < 3 || epsilon <= 0.0 {
+        return (0..points.len()).collect();
+    }
+
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    // Explicit stack instead of recursion; long strokes have thousands of points.
+    let mut stack = vec![(0, points.len() - 1)];
+    while let Some((first, last)) = stack.pop() {
+        let mut max_dist = 0.0;
+        let mut max_idx = first;
+        for idx in first + 1..last {
+            let dist = distance_to_segment(points[idx], points[first], points[last]);
+            if dist > max_dist {
+                max_dist = dist;
+                max_idx = idx;
+            }
+        }
+        if max_dist > epsilon {
+            keep[max_idx] = true;
+            stack.push((first, max_idx));
+            stack.push((max_idx, last));
+        }
+    }
+
+    keep.into_iter()
+        .enumerate()
+        .filter_map(|(idx, keep)| keep.then_some(idx))
+        .collect()
+}
+
+/// Whether `p` lies inside the closed outline `polygon`, by the even-odd rule.
+pub fn contains_point(polygon: &[[f32; 2]], p: [f32; 2]) -> bool {
+    let mut inside = false;
+    let mut prev = polygon.len().wrapping_sub(1);
+    for idx in 0..polygon.len() {
+        let (a, b) = (polygon[idx], polygon[prev]);
+        if (a[1] > p[1]) != (b[1] > p[1])
+            && p[0] < (b[0] - a[0]) * (p[1] - a[1]) / (b[1] - a[1]) + a[0]
+        {
+            inside = !inside;
+        }
+        prev = idx;
+    }
+    inside
+}
+
+/// Splits the inside of the closed outline `polygon` into triangles by the even-odd
+/// rule, the rule the PNG and SVG exports fill with, so concave and self-crossing
+/// outlines look the same everywhere. The outline is cut into horizontal bands at
+/// every corner and crossing; no two edges cross inside a band, so the edges there
+/// pair up left to right into trapezoids.
+pub fn fill_triangles(polygon: &[[f32; 2]]) -> Vec<[[f32; 2]; 3]> {
+    let mut triangles = Vec::new();
+    if polygon.len() < 3 {
+        return triangles;
+    }
+    // Level edges bound no area of their own.
+    let edges: Vec<([f32; 2], [f32; 2])> = (0..polygon.len())
+        .map(|idx| (polygon[idx], polygon[(idx + 1) % polygon.len()]))
+        .filter(|(a, b)| a[1] != b[1])
+        .collect();
+    let mut cuts: Vec<f32> = polygon.iter().map(|p| p[1]).collect();
+    for (idx, &(a, b)) in edges.iter().enumerate() {
+        cuts.extend(
+            edges[idx + 1..]
+                .iter()
+                .filter_map(|&(c, d)| crossing(a, b, c, d))
+                .map(|p| p[1]),
+        );
+    }
+    cuts.sort_by(f32::total_cmp);
+    cuts.dedup();
+
+    let x_at =
+        |(a, b): ([f32; 2], [f32; 2]), y: f32| a[0] + (b[0] - a[0]) * (y - a[1]) / (b[1] - a[1]);
+    for band in cuts.windows(2) {
+        let (top, bottom) = (band[0], band[1]);
+        let middle = (top + bottom) / 2.0;
+        let mut spanning: Vec<_> = edges
+            .iter()
+            .filter(|(a, b)| (a[1] > middle) != (b[1] > middle))
+            .copied()
+            .collect();
+        spanning.sort_by(|&e, &f| x_at(e, middle).total_cmp(&x_at(f, middle)));
+        for pair in spanning.chunks_exact(2) {
+            let (left, right) = (pair[0], pair[1]);
+            let corners = [
+                [x_at(left, top), top],
+                [x_at(right, top), top],
+                [x_at(right, bottom), bottom],
+                [x_at(left, bottom), bottom],
+            ];
+            triangles.push([corners[0], corners[1], corners[2]]);
+            triangles.push([corners[0], corners[2], corners[3]]);
+        }
+    }
+    triangles
+}
+
+/// Where segment `a`-`b` crosses segment `c`-`d`, if they cross.
+fn crossing(a: [f32; 2], b: [f32; 2], c: [f32; 2], d: [f32; 2]) -> Option<[f32; 2]> {
+    let (r, s) = ([b[0] - a[0], b[1] - a[1]], [d[0] - c[0], d[1] - c[1]]);
+    let denominator = r[0] * s[1] - r[1] * s[0];
+    if denominator == 0.0 {
+        return None;
+    }
+    let (ac0, ac1) = (c[0] - a[0], c[1] - a[1]);
+    let t = (ac0 * s[1] - ac1 * s[0]) / denominator;
+    let u = (ac0 * r[1] - ac1 * r[0]) / denominator;
+    ((0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u))
+        .then(|| [a[0] + r[0] * t, a[1] + r[1] * t])
+}
+
+/// `point` turned about `origin` onto the nearest direction that is a multiple of
+/// `step` radians, at the same distance.
+pub fn snap_angle(origin: [f32; 2], point: [f32; 2], step: f32) -> [f32; 2] {
+    let (dx, dy) = (point[0] - origin[0], point[1] - origin[1]);
+    let length = dx.hypot(dy);
+    if length == 0.0 || step <= 0.0 {
+        return point;
+    }
+    let angle = (dy.atan2(dx) / step).round() * step;
+    [
+        origin[0] + length * angle.cos(),
+        origin[1] + length * angle.sin(),
+    ]
+}
+
+pub fn distance_to_segment(p: [f32; 2], a: [f32; 2], b: [f32; 2]) -> f32 {
+    let ab = [b[0] - a[0], b[1] - a[1]];
+    let ap = [p[0] - a[0], p[1] - a[1]];
+    let len_sq = ab[0] * ab[0] + ab[1] * ab[1];
+    let t = if len_sq > 0.0 {
+        ((ap[0] * ab[0] + ap[1] * ab[1]) / len_sq).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let closest = [a[0] + ab[0] * t, a[1] + ab[1] * t];
+    ((p[0] - closest[0]).powi(2) + (p[1] - closest[1]).powi(2)).sqrt()
+}
+
+/// Spline samples per input segment when smoothing.
+const SPLINE_STEPS: usize = 4;
+
+/// Evens out pointer jitter, then fits a Catmull-Rom spline through the result.
+/// `strength` ranges from 0 (unchanged) to 1 (heavy smoothing).
+pub fn smooth_polyline(points: &[[f32; 2]], strength: f32) -> Vec<[f32; 2]> {
+    if points.len() < 3 || strength <= 0.0 {
+        return points.to_vec();
+    }
+
+    // Pull every interior point toward the midpoint of its neighbours; ends stay put
+    // so the stroke still starts and stops where the pen did.
+    let mut relaxed = points.to_vec();
+    let passes = 1 + (strength * 3.0).round() as usize;
+    for _ in 0..passes {
+        let prev = relaxed.clone();
+        for idx in 1..prev.len() - 1 {
+            let mid = [
+                (prev[idx - 1][0] + prev[idx + 1][0]) * 0.5,
+                (prev[idx - 1][1] + prev[idx + 1][1]) * 0.5,
+            ];
+            relaxed[idx] = lerp(prev[idx], mid, strength.min(1.0));
+        }
+    }
+
+    let mut out = Vec::with_capacity(relaxed.len() * SPLINE_STEPS);
+    for idx in 0..relaxed.len() - 1 {
+        let p0 = relaxed[idx.saturating_sub(1)];
+        let p1 = relaxed[idx];
+        let p2 = relaxed[idx + 1];
+        let p3 = relaxed[(idx + 2).min(relaxed.len() - 1)];
+        for step in 0..SPLINE_STEPS {
+            out.push(catmull_rom(
+                p0,
+                p1,
+                p2,
+                p3,
+                step as f32 / SPLINE_STEPS as f32,
+            ));
+        }
+    }
+    out.push(relaxed[relaxed.len() - 1]);
+    out
+}
+
+/// Per-point values such as widths, resampled to line up with what
+/// [`smooth_polyline`] makes of the same points.
+pub fn smooth_values(values: &[f32], strength: f32) -> Vec<f32> {
+    if values.len() < 3 || strength <= 0.0 {
+        return values.to_vec();
+    }
+    let mut out = Vec::with_capacity(values.len() * SPLINE_STEPS);
+    for pair in values.windows(2) {
+        for step in 0..SPLINE_STEPS {
+            let t = step as f32 / SPLINE_STEPS as f32;
+            out.push(pair[0] + (pair[1] - pair[0]) * t);
+        }
+    }
+    out.push(values[values.len() - 1]);
+    out
+}
+
+/// Points per piece when a stroke is smoothed piecewise.
+pub const SMOOTHING_CHUNK: usize = 64;
+
+/// [`smooth_polyline`] applied to consecutive pieces of [`SMOOTHING_CHUNK`] points
+/// that share their end points. Smoothing keeps end points in place, so the pieces
+/// join up, and a stroke still being drawn can smooth each finished piece once and
+/// look exactly like the stroke it becomes.
+pub fn smooth_polyline_piecewise(points: &[[f32; 2]], strength: f32) -> Vec<[f32; 2]> {
+    piecewise(points, |piece| smooth_polyline(piece, strength))
+}
+
+/// [`smooth_values`] cut into the same pieces as [`smooth_polyline_piecewise`].
+pub fn smooth_values_piecewise(values: &[f32], strength: f32) -> Vec<f32> {
+    piecewise(values, |piece| smooth_values(piece, strength))
+}
+
+fn piecewise<T: Copy>(items: &[T], smooth: impl Fn(&[T]) -> Vec<T>) -> Vec<T> {
+    if items.len() < 2 {
+        return items.to_vec();
+    }
+    let mut out: Vec<T> = Vec::new();
+    let mut start = 0;
+    while start + 1 < items.len() {
+        let end = (start + SMOOTHING_CHUNK).min(items.len() - 1);
+        let piece = smooth(&items[start..=end]);
+        // Neighbouring pieces share their boundary point.
+        let skip = usize::from(!out.is_empty());
+        out.extend_from_slice(&piece[skip..]);
+        start = end;
+    }
+    out
+}
+
+/// Polygons that together cover a line through `points` that is `widths` wide at
+/// each point, with round ends and joins: a circle at every point and a band along
+/// every segment. They all wind the same way, so filling them as one path with the
+/// nonzero rule paints each pixel once, even in translucent colors.
+pub fn variable_width_outline(points: &[[f32; 2]], widths: &[f32]) -> Vec<Vec<[f32; 2]>> {
+    let mut parts = Vec::new();
+    for (&[x, y], &width) in points.iter().zip(widths) {
+        let radius = width / 2.0;
+        let sides = ((radius * 2.0).ceil() as usize).clamp(8, 48);
+        parts.push(
+            (0..sides)
+                .map(|idx| {
+                    let angle = idx as f32 / sides as f32 * std::f32::consts::TAU;
+                    [x + radius * angle.cos(), y + radius * angle.sin()]
+                })
+                .collect(),
+        );
+    }
+    for (pair, width) in points.windows(2).zip(widths.windows(2)) {
+        let ([a, b], [wa, wb]) = ([pair[0], pair[1]], [width[0] / 2.0, width[1] / 2.0]);
+        let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+        let len = dx.hypot(dy);
+        if len <= f32::EPSILON {
+            continue;
+        }
+        let normal = [-dy / len, dx / len];
+        let side = |p: [f32; 2], r: f32| [p[0] + normal[0] * r, p[1] + normal[1] * r];
+        // Wound like the circles above, which go clockwise on screen.
+        parts.push(vec![side(a, -wa), side(b, -wb), side(b, wb), side(a, wa)]);
+    }
+    parts
+}
+
+fn catmull_rom(p0: [f32; 2], p1: [f32; 2], p2: [f32; 2], p3: [f32; 2], t: f32) -> [f32; 2] {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    let axis = |i: usize| {
+        0.5 * (2.0 * p1[i]
+            + (p2[i] - p0[i]) * t
+            + (2.0 * p0[i] - 5.0 * p1[i] + 4.0 * p2[i] - p3[i]) * t2
+            + (3.0 * p1[i] - p0[i] - 3.0 * p2[i] + p3[i]) * t3)
+    };
+    [axis(0), axis(1)]
+}
+
+fn lerp(a: [f32; 2], b: [f32; 2], t: f32) -> [f32; 2] {
+    [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(triangles: &[[[f32; 2]; 3]]) -> f32 {
+        triangles
+            .iter()
+            .map(|[a, b, c]| {
+                ((b[0] - a[0]) * (c[1] - a[1]) - (c[0] - a[0]) * (b[1] - a[1])).abs() / 2.0
+            })
+            .sum()
+    }
+
+    /// Every triangle with any area lies inside the outline by the even-odd rule.
+    fn assert_inside(polygon: &[[f32; 2]], triangles: &[[[f32; 2]; 3]]) {
+        for &[a, b, c] in triangles.iter().filter(|t| area(&[**t]) > 1e-6) {
+            let centroid = [(a[0] + b[0] + c[0]) / 3.0, (a[1] + b[1] + c[1]) / 3.0];
+            assert!(contains_point(polygon, centroid), "{centroid:?} is outside");
+        }
+    }
+
+    #[test]
+    fn concave_polygons_fill_only_their_inside() {
+        // An L: a 4×1 bar along the bottom and a 1×3 bar up the left side.
+        let polygon = [
+            [0.0, 0.0],
+            [4.0, 0.0],
+            [4.0, 1.0],
+            [1.0, 1.0],
+            [1.0, 4.0],
+            [0.0, 4.0],
+        ];
+        let triangles = fill_triangles(&polygon);
+        assert!((area(&triangles) - 7.0).abs() < 1e-4);
+        assert_inside(&polygon, &triangles);
+    }
+
+    #[test]
+    fn self_crossing_outlines_fill_by_the_even_odd_rule() {
+        // A bow-tie: two triangles of area 1 meeting at (1, 1). Its signed area is 0.
+        let polygon = [[0.0, 0.0], [2.0, 2.0], [2.0, 0.0], [0.0, 2.0]];
+        let triangles = fill_triangles(&polygon);
+        assert!((area(&triangles) - 2.0).abs() < 1e-4);
+        assert_inside(&polygon, &triangles);
+
+        // A square traced twice covers every spot twice, so nothing is inside.
+        let twice = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]].repeat(2);
+        assert!(area(&fill_triangles(&twice)) < 1e-4);
+    }
+
+    #[test]
+    fn degenerate_outlines_fill_nothing() {
+        let collinear = [[0.0, 0.0], [1.0, 1.0], [2.0, 2.0], [3.0, 3.0]];
+        assert!(area(&fill_triangles(&collinear)) < 1e-6);
+        let level = [[0.0, 1.0], [2.0, 1.0], [5.0, 1.0]];
+        assert!(fill_triangles(&level).is_empty());
+        assert!(fill_triangles(&[[0.0, 0.0], [1.0, 1.0]]).is_empty());
+    }
+}
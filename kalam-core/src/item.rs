@@ -0,0 +1,904 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{geometry, markup};
+
+/// Shortest stretch of line, in points, that counts as erased. Anything less is float
+/// noise at the edge of the eraser, such as a piece cut off by the same eraser that
+/// ends exactly on its circle.
+const MIN_ERASED_LENGTH: f32 = 0.01;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrokePath {
+    pub points: Vec<[f32; 2]>,
+    pub rgba: [u8; 4],
+    pub thickness: f32,
+    /// Full width at each point, for strokes drawn with pen pressure; empty for an
+    /// even `thickness` throughout.
+    #[serde(default)]
+    pub widths: Vec<f32>,
+}
+
+impl StrokePath {
+    /// Width at each point.
+    pub fn point_widths(&self) -> Vec<f32> {
+        if self.widths.len() == self.points.len() {
+            self.widths.clone()
+        } else {
+            vec![self.thickness; self.points.len()]
+        }
+    }
+
+    /// The parts of the stroke's center line outside the circle at `center`, cut
+    /// where the line crosses it; parts too short to draw are dropped. `None` if
+    /// the circle doesn't cover more than [`MIN_ERASED_LENGTH`] of the line, so an
+    /// eraser resting on a stroke it already cut leaves the pieces alone.
+    pub fn erase_circle(&self, center: [f32; 2], radius: f32) -> Option<Vec<StrokePath>> {
+        let widths = self.point_widths();
+        let at = |idx: usize, t: f32| {
+            let next = (idx + 1).min(self.points.len() - 1);
+            let (a, b) = (self.points[idx], self.points[next]);
+            let point = [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t];
+            (point, widths[idx] + (widths[next] - widths[idx]) * t)
+        };
+        let piece = |points: Vec<([f32; 2], f32)>| StrokePath {
+            widths: if self.widths.is_empty() {
+                Vec::new()
+            } else {
+                points.iter().map(|(_, width)| *width).collect()
+            },
+            points: points.into_iter().map(|(point, _)| point).collect(),
+            ..self.clone()
+        };
+
+        let first = *self.points.first()?;
+        if self.points.len() == 1 {
+            let inside = (first[0] - center[0]).hypot(first[1] - center[1]) < radius;
+            return inside.then(Vec::new);
+        }
+        let mut touched = false;
+        let mut pieces = Vec::new();
+        let mut current = Vec::new();
+        for idx in 0..self.points.len() - 1 {
+            let (a, b) = (self.points[idx], self.points[idx + 1]);
+            // Where a + t·(b − a) lies on the circle: a quadratic in t.
+            let d = [b[0] - a[0], b[1] - a[1]];
+            let f = [a[0] - center[0], a[1] - center[1]];
+            let qa = d[0] * d[0] + d[1] * d[1];
+            let qb = 2.0 * (f[0] * d[0] + f[1] * d[1]);
+            let qc = f[0] * f[0] + f[1] * f[1] - radius * radius;
+            let inside = if qa == 0.0 {
+                let distance = f[0].hypot(f[1]);
+                (distance < radius - MIN_ERASED_LENGTH).then_some((0.0, 1.0))
+            } else {
+                let disc = qb * qb - 4.0 * qa * qc;
+                let (t0, t1) = (
+                    ((-qb - disc.sqrt()) / (2.0 * qa)).max(0.0),
+                    ((-qb + disc.sqrt()) / (2.0 * qa)).min(1.0),
+                );
+                (disc > 0.0 && (t1 - t0) * qa.sqrt() > MIN_ERASED_LENGTH).then_some((t0, t1))
+            };
+            let Some((t0, t1)) = inside else {
+                if current.is_empty() {
+                    current.push(at(idx, 0.0));
+                }
+                current.push(at(idx, 1.0));
+                continue;
+            };
+            touched = true;
+            if t0 > 0.0 {
+                if current.is_empty() {
+                    current.push(at(idx, 0.0));
+                }
+                current.push(at(idx, t0));
+            }
+            if current.len() > 1 {
+                pieces.push(piece(std::mem::take(&mut current)));
+            }
+            current.clear();
+            if t1 < 1.0 {
+                current.push(at(idx, t1));
+                current.push(at(idx, 1.0));
+            }
+        }
+        if current.len() > 1 {
+            pieces.push(piece(current));
+        }
+        touched.then_some(pieces)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolygonShape {
+    pub points: Vec<[f32; 2]>,
+    pub rgba: [u8; 4],
+    pub thickness: f32,
+    /// Color of the inside; `None` leaves it see-through.
+    #[serde(default)]
+    pub fill: Option<[u8; 4]>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EllipseShape {
+    pub center: [f32; 2],
+    /// Horizontal and vertical radius; equal for a circle.
+    pub radii: [f32; 2],
+    pub rgba: [u8; 4],
+    pub thickness: f32,
+    /// Clockwise turn of the radii from the axes, in radians.
+    #[serde(default)]
+    pub rotation: f32,
+    /// Color of the inside; `None` leaves it see-through.
+    #[serde(default)]
+    pub fill: Option<[u8; 4]>,
+}
+
+impl EllipseShape {
+    /// Points around the outline, spaced closely enough to draw it as a polygon.
+    pub fn outline(&self) -> Vec<[f32; 2]> {
+        let [rx, ry] = self.radii;
+        // Ramanujan's approximation of the circumference, one point every few units.
+        let h = ((rx - ry) / (rx + ry).max(f32::EPSILON)).powi(2);
+        let circumference =
+            std::f32::consts::PI * (rx + ry) * (1.0 + 3.0 * h / (10.0 + (4.0 - 3.0 * h).sqrt()));
+        let count = ((circumference / 4.0) as usize).clamp(16, 256);
+        let (sin, cos) = self.rotation.sin_cos();
+        (0..count)
+            .map(|idx| {
+                let angle = idx as f32 / count as f32 * std::f32::consts::TAU;
+                let (x, y) = (rx * angle.cos(), ry * angle.sin());
+                [
+                    self.center[0] + x * cos - y * sin,
+                    self.center[1] + x * sin + y * cos,
+                ]
+            })
+            .collect()
+    }
+
+    /// Half the width and height of the box around the (possibly turned) outline.
+    pub fn half_extents(&self) -> [f32; 2] {
+        let [rx, ry] = self.radii;
+        let (sin, cos) = self.rotation.sin_cos();
+        [(rx * cos).hypot(ry * sin), (rx * sin).hypot(ry * cos)]
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineShape {
+    pub start: [f32; 2],
+    pub end: [f32; 2],
+    pub rgba: [u8; 4],
+    pub thickness: f32,
+}
+
+/// A numbered badge marking one step of a sequence.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Marker {
+    pub pos: [f32; 2],
+    pub number: u32,
+    pub rgba: [u8; 4],
+}
+
+impl Marker {
+    pub const RADIUS: f32 = 14.0;
+
+    /// Black or white, whichever reads better on the badge color.
+    pub fn label_rgba(&self) -> [u8; 4] {
+        let [r, g, b, _] = self.rgba.map(f32::from);
+        if 0.299 * r + 0.587 * g + 0.114 * b > 150.0 {
+            [0, 0, 0, 255]
+        } else {
+            [255, 255, 255, 255]
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StampKind {
+    Check,
+    Cross,
+    Star,
+    Smiley,
+}
+
+impl StampKind {
+    pub const ALL: [StampKind; 4] = [
+        StampKind::Check,
+        StampKind::Cross,
+        StampKind::Star,
+        StampKind::Smiley,
+    ];
+
+    /// How many points [`Self::lines`] has in all, without building them.
+    pub fn point_count(self) -> usize {
+        match self {
+            StampKind::Check => 3,
+            StampKind::Cross => 4,
+            StampKind::Star => 10,
+            StampKind::Smiley => 48,
+        }
+    }
+
+    /// The lines making up the mark in a box from -1 to 1 around its center, each
+    /// with whether it is closed.
+    fn lines(self) -> Vec<(Vec<[f32; 2]>, bool)> {
+        use std::f32::consts::{FRAC_PI_2, PI, TAU};
+        let arc = |center: [f32; 2], radius: f32, from: f32, to: f32, count: usize| {
+            (0..count)
+                .map(|idx| {
+                    let angle = from + (to - from) * idx as f32 / (count - 1) as f32;
+                    [
+                        center[0] + radius * angle.cos(),
+                        center[1] + radius * angle.sin(),
+                    ]
+                })
+                .collect::<Vec<_>>()
+        };
+        match self {
+            StampKind::Check => vec![(vec![[-0.8, 0.0], [-0.25, 0.6], [0.85, -0.7]], false)],
+            StampKind::Cross => vec![
+                (vec![[-0.7, -0.7], [0.7, 0.7]], false),
+                (vec![[0.7, -0.7], [-0.7, 0.7]], false),
+            ],
+            StampKind::Star => {
+                let points = (0..10)
+                    .map(|idx| {
+                        let angle = -FRAC_PI_2 + idx as f32 * PI / 5.0;
+                        let radius = if idx % 2 == 0 { 0.95 } else { 0.4 };
+                        [radius * angle.cos(), radius * angle.sin()]
+                    })
+                    .collect();
+                vec![(points, true)]
+            }
+            StampKind::Smiley => {
+                let mut face = arc([0.0, 0.0], 0.85, 0.0, TAU, 33);
+                face.pop();
+                vec![
+                    (face, true),
+                    (vec![[-0.3, -0.3], [-0.3, -0.2]], false),
+                    (vec![[0.3, -0.3], [0.3, -0.2]], false),
+                    (arc([0.0, 0.0], 0.5, 0.35, PI - 0.35, 12), false),
+                ]
+            }
+        }
+    }
+}
+
+/// A ready-made mark, such as a check mark, placed with one click.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stamp {
+    /// Center of the mark.
+    pub pos: [f32; 2],
+    pub kind: StampKind,
+    /// Width and height of the mark.
+    pub size: f32,
+    pub rgba: [u8; 4],
+}
+
+impl Stamp {
+    pub fn thickness(&self) -> f32 {
+        self.size * 0.1
+    }
+
+    /// The lines to draw at the stamp's place and size, each with whether it is closed.
+    pub fn lines(&self) -> Vec<(Vec<[f32; 2]>, bool)> {
+        let half = self.size / 2.0;
+        let mut lines = self.kind.lines();
+        for p in lines.iter_mut().flat_map(|(points, _)| points) {
+            *p = [self.pos[0] + p[0] * half, self.pos[1] + p[1] * half];
+        }
+        lines
+    }
+}
+
+/// The rounded box drawn behind a text box's text.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(from = "StoredBackground")]
+pub struct TextBackground {
+    /// Straight color, kept apart from the opacity so that turning the opacity down
+    /// to zero and back up again keeps the color.
+    pub rgb: [u8; 3],
+    /// From 0, which leaves the text bare, to 1.
+    pub opacity: f32,
+    /// Space between the box's edges and the text, across and down.
+    pub padding: [f32; 2],
+    pub corner_radius: f32,
+}
+
+impl Default for TextBackground {
+    fn default() -> Self {
+        Self {
+            rgb: [18, 18, 18],
+            opacity: 0.55,
+            padding: [7.0, 4.0],
+            corner_radius: 6.0,
+        }
+    }
+}
+
+impl TextBackground {
+    /// The fill, premultiplied like item colors.
+    pub fn rgba(&self) -> [u8; 4] {
+        let alpha = (self.opacity.clamp(0.0, 1.0) * 255.0).round();
+        let [r, g, b] = self
+            .rgb
+            .map(|channel| (channel as f32 * alpha / 255.0).round() as u8);
+        [r, g, b, alpha as u8]
+    }
+}
+
+/// A background as saved. Before the color and opacity were kept apart, boards and
+/// settings held one premultiplied `rgba`, which is split up on loading.
+#[derive(Deserialize)]
+#[serde(default)]
+struct StoredBackground {
+    rgb: [u8; 3],
+    opacity: f32,
+    rgba: Option<[u8; 4]>,
+    padding: [f32; 2],
+    corner_radius: f32,
+}
+
+impl Default for StoredBackground {
+    fn default() -> Self {
+        let background = TextBackground::default();
+        Self {
+            rgb: background.rgb,
+            opacity: background.opacity,
+            rgba: None,
+            padding: background.padding,
+            corner_radius: background.corner_radius,
+        }
+    }
+}
+
+impl From<StoredBackground> for TextBackground {
+    fn from(stored: StoredBackground) -> Self {
+        let (rgb, opacity) = match stored.rgba {
+            // A bare background kept no color to recover.
+            Some([.., 0]) => (stored.rgb, 0.0),
+            Some([r, g, b, a]) => {
+                let straight = |c: u8| ((c as u32 * 255 + a as u32 / 2) / a as u32).min(255) as u8;
+                ([straight(r), straight(g), straight(b)], a as f32 / 255.0)
+            }
+            None => (stored.rgb, stored.opacity),
+        };
+        Self {
+            rgb,
+            opacity,
+            padding: stored.padding,
+            corner_radius: stored.corner_radius,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextBox {
+    pub pos: [f32; 2],
+    pub text: String,
+    pub rgba: [u8; 4],
+    /// Lines wrap between words before they run wider than this; without it they
+    /// only break at newlines.
+    #[serde(default)]
+    pub wrap_width: Option<f32>,
+    #[serde(default)]
+    pub background: TextBackground,
+}
+
+impl TextBox {
+    /// Rough width of a character, for estimates without a font.
+    const CHAR_WIDTH: f32 = 9.0;
+    const LINE_HEIGHT: f32 = 22.0;
+
+    /// Size of the backdrop estimated from character counts, for callers that have
+    /// not laid the text out in a font.
+    pub fn estimated_size(&self) -> [f32; 2] {
+        self.size_with(
+            |line| line.chars().count() as f32 * Self::CHAR_WIDTH,
+            Self::LINE_HEIGHT,
+        )
+    }
+
+    /// Size of the backdrop around the text's [`lines`](Self::lines), with `measure`
+    /// giving the width of a line and every line `line_height` tall.
+    pub fn size_with(&self, measure: impl Fn(&str) -> f32, line_height: f32) -> [f32; 2] {
+        let lines = self.lines(&measure);
+        let longest = lines.iter().map(|line| measure(line)).fold(0.0, f32::max);
+        let [across, down] = self.background.padding;
+        [
+            longest + 2.0 * across,
+            lines.len().max(1) as f32 * line_height + 2.0 * down,
+        ]
+    }
+
+    /// The lines the text is shown in, without its emphasis markers: it breaks at
+    /// newlines and, with a wrap width, between words wherever `measure` finds a line
+    /// would run wider. Like the overlay's text layout, a word wider than that on its
+    /// own is broken between characters.
+    pub fn lines(&self, measure: impl Fn(&str) -> f32) -> Vec<String> {
+        let text = markup::plain(&self.text);
+        let Some(wrap_width) = self.wrap_width else {
+            return text.lines().map(str::to_string).collect();
+        };
+        let fits = |line: &str| measure(line.trim_end()) <= wrap_width;
+        let mut lines = Vec::new();
+        for paragraph in text.lines() {
+            let mut line = String::new();
+            for word in paragraph.split_inclusive(' ') {
+                let longer = format!("{line}{word}");
+                if fits(&longer) {
+                    line = longer;
+                    continue;
+                }
+                if !line.is_empty() {
+                    lines.push(line.trim_end().to_string());
+                }
+                line = word.to_string();
+                if fits(&line) {
+                    continue;
+                }
+                // The word alone is too wide: break it between characters.
+                let word = std::mem::take(&mut line);
+                let mut chars = word.chars();
+                line.extend(chars.next());
+                for c in chars {
+                    line.push(c);
+                    if !fits(&line) {
+                        line.pop();
+                        lines.push(std::mem::take(&mut line));
+                        line.push(c);
+                    }
+                }
+            }
+            lines.push(line.trim_end().to_string());
+        }
+        lines
+    }
+}
+
+/// A text box with a tail pointing at something.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Callout {
+    /// The bubble, laid out and drawn like a text box.
+    pub text: TextBox,
+    /// Where the tail points.
+    pub tip: [f32; 2],
+}
+
+impl Callout {
+    /// Width of the tail where it leaves the bubble.
+    const TAIL_WIDTH: f32 = 20.0;
+
+    /// The tail's corners: two on the side of the bubble facing the tip, then the
+    /// tip, for a bubble `size` large. `None` while the tip is inside the bubble.
+    pub fn tail(&self, size: [f32; 2]) -> Option<[[f32; 2]; 3]> {
+        let [x, y] = self.text.pos;
+        let [width, height] = size;
+        let tip = self.tip;
+        if (x..=x + width).contains(&tip[0]) && (y..=y + height).contains(&tip[1]) {
+            return None;
+        }
+        let center = [x + width / 2.0, y + height / 2.0];
+        let dir = [tip[0] - center[0], tip[1] - center[1]];
+        let radius = self.text.background.corner_radius;
+        // Slides the base along a side, clear of its rounded corners.
+        let place = |at: f32, min: f32, len: f32| {
+            let half = (Self::TAIL_WIDTH / 2.0).min(len / 2.0);
+            let (low, high) = (min + radius + half, min + len - radius - half);
+            let at = if low <= high {
+                at.clamp(low, high)
+            } else {
+                min + len / 2.0
+            };
+            (at - half, at + half)
+        };
+        // The side the line from the center to the tip leaves through.
+        if dir[0].abs() * height > dir[1].abs() * width {
+            let side = if dir[0] > 0.0 { x + width } else { x };
+            let at = center[1] + dir[1] * (side - center[0]) / dir[0];
+            let (a, b) = place(at, y, height);
+            Some([[side, a], [side, b], tip])
+        } else {
+            let side = if dir[1] > 0.0 { y + height } else { y };
+            let at = center[0] + dir[0] * (side - center[1]) / dir[1];
+            let (a, b) = place(at, x, width);
+            Some([[a, side], [b, side], tip])
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CanvasItem {
+    Stroke(StrokePath),
+    Polygon(PolygonShape),
+    Text(TextBox),
+    Ellipse(EllipseShape),
+    Line(LineShape),
+    Marker(Marker),
+    Stamp(Stamp),
+    Callout(Callout),
+}
+
+impl CanvasItem {
+    /// The text of a text box or callout.
+    pub fn text_box(&self) -> Option<&TextBox> {
+        match self {
+            CanvasItem::Text(t) => Some(t),
+            CanvasItem::Callout(callout) => Some(&callout.text),
+            _ => None,
+        }
+    }
+
+    pub fn text_box_mut(&mut self) -> Option<&mut TextBox> {
+        match self {
+            CanvasItem::Text(t) => Some(t),
+            CanvasItem::Callout(callout) => Some(&mut callout.text),
+            _ => None,
+        }
+    }
+
+    pub fn point_count(&self) -> usize {
+        match self {
+            CanvasItem::Stroke(path) => path.points.len(),
+            CanvasItem::Polygon(poly) => poly.points.len(),
+            CanvasItem::Text(_) | CanvasItem::Ellipse(_) | CanvasItem::Marker(_) => 1,
+            CanvasItem::Stamp(stamp) => stamp.kind.point_count(),
+            CanvasItem::Line(_) | CanvasItem::Callout(_) => 2,
+        }
+    }
+
+    pub fn translate(&mut self, offset: [f32; 2]) {
+        let shift = |p: &mut [f32; 2]| {
+            p[0] += offset[0];
+            p[1] += offset[1];
+        };
+        match self {
+            CanvasItem::Stroke(path) => path.points.iter_mut().for_each(shift),
+            CanvasItem::Polygon(poly) => poly.points.iter_mut().for_each(shift),
+            CanvasItem::Text(t) => shift(&mut t.pos),
+            CanvasItem::Ellipse(ellipse) => shift(&mut ellipse.center),
+            CanvasItem::Line(line) => {
+                shift(&mut line.start);
+                shift(&mut line.end);
+            }
+            CanvasItem::Marker(marker) => shift(&mut marker.pos),
+            CanvasItem::Stamp(stamp) => shift(&mut stamp.pos),
+            CanvasItem::Callout(callout) => {
+                shift(&mut callout.text.pos);
+                shift(&mut callout.tip);
+            }
+        }
+    }
+
+    /// Scales the item by `factor` around `origin`, then turns it clockwise by `angle`
+    /// radians around the same point. Line widths stay as they are, and text boxes,
+    /// callout bubbles, and markers stay upright, with only their centers moving.
+    /// `text_size` is as for [`Self::bounds`].
+    pub fn scale_rotate(
+        &mut self,
+        origin: [f32; 2],
+        factor: f32,
+        angle: f32,
+        text_size: Option<[f32; 2]>,
+    ) {
+        let (sin, cos) = angle.sin_cos();
+        let map = |p: &mut [f32; 2]| {
+            let (x, y) = ((p[0] - origin[0]) * factor, (p[1] - origin[1]) * factor);
+            *p = [origin[0] + x * cos - y * sin, origin[1] + x * sin + y * cos];
+        };
+        let map_text = |t: &mut TextBox| {
+            let [width, height] = text_size.unwrap_or_else(|| t.estimated_size());
+            let mut center = [t.pos[0] + width / 2.0, t.pos[1] + height / 2.0];
+            map(&mut center);
+            t.pos = [center[0] - width / 2.0, center[1] - height / 2.0];
+        };
+        match self {
+            CanvasItem::Stroke(path) => path.points.iter_mut().for_each(map),
+            CanvasItem::Polygon(poly) => poly.points.iter_mut().for_each(map),
+            CanvasItem::Text(t) => map_text(t),
+            CanvasItem::Ellipse(ellipse) => {
+                map(&mut ellipse.center);
+                ellipse.radii = ellipse.radii.map(|r| r * factor);
+                ellipse.rotation += angle;
+            }
+            CanvasItem::Line(line) => {
+                map(&mut line.start);
+                map(&mut line.end);
+            }
+            CanvasItem::Marker(marker) => map(&mut marker.pos),
+            CanvasItem::Stamp(stamp) => {
+                map(&mut stamp.pos);
+                stamp.size *= factor;
+            }
+            CanvasItem::Callout(callout) => {
+                map_text(&mut callout.text);
+                map(&mut callout.tip);
+            }
+        }
+    }
+
+    pub fn recolor(&mut self, rgba: [u8; 4]) {
+        match self {
+            CanvasItem::Stroke(path) => path.rgba = rgba,
+            CanvasItem::Polygon(poly) => poly.rgba = rgba,
+            CanvasItem::Text(t) => t.rgba = rgba,
+            CanvasItem::Ellipse(ellipse) => ellipse.rgba = rgba,
+            CanvasItem::Line(line) => line.rgba = rgba,
+            CanvasItem::Marker(marker) => marker.rgba = rgba,
+            CanvasItem::Stamp(stamp) => stamp.rgba = rgba,
+            CanvasItem::Callout(callout) => callout.text.rgba = rgba,
+        }
+    }
+
+    /// A copy with its outline simplified by `epsilon`, or `None` if that would not
+    /// drop any points.
+    pub fn simplified(&self, epsilon: f32) -> Option<CanvasItem> {
+        let mut item = self.clone();
+        let points = match &mut item {
+            CanvasItem::Stroke(path) => {
+                let keep = geometry::simplify_indices(&path.points, epsilon);
+                if keep.len() >= path.points.len() {
+                    return None;
+                }
+                if path.widths.len() == path.points.len() {
+                    path.widths = keep.iter().map(|idx| path.widths[*idx]).collect();
+                }
+                path.points = keep.iter().map(|idx| path.points[*idx]).collect();
+                return Some(item);
+            }
+            CanvasItem::Polygon(poly) => &mut poly.points,
+            CanvasItem::Text(_)
+            | CanvasItem::Ellipse(_)
+            | CanvasItem::Line(_)
+            | CanvasItem::Marker(_)
+            | CanvasItem::Stamp(_)
+            | CanvasItem::Callout(_) => return None,
+        };
+        let simplified = geometry::simplify_polyline(points, epsilon);
+        if simplified.len() >= points.len() {
+            return None;
+        }
+        *points = simplified;
+        Some(item)
+    }
+
+    /// Axis-aligned `[min, max]` corners of everything the item paints,
+    /// or `None` if it has no points. `text_size` is the backdrop size of the item's
+    /// text box as the caller laid it out; without it, the size is estimated.
+    pub fn bounds(&self, text_size: Option<[f32; 2]>) -> Option<[[f32; 2]; 2]> {
+        let (points, pad) = match self {
+            CanvasItem::Stroke(path) => (&path.points, path.thickness * 0.5),
+            CanvasItem::Polygon(poly) => (&poly.points, poly.thickness * 0.5),
+            CanvasItem::Text(t) => {
+                let size = text_size.unwrap_or_else(|| t.estimated_size());
+                return Some([t.pos, [t.pos[0] + size[0], t.pos[1] + size[1]]]);
+            }
+            CanvasItem::Ellipse(ellipse) => {
+                let [cx, cy] = ellipse.center;
+                let [rx, ry] = ellipse.half_extents().map(|r| r + ellipse.thickness * 0.5);
+                return Some([[cx - rx, cy - ry], [cx + rx, cy + ry]]);
+            }
+            CanvasItem::Line(line) => {
+                let pad = line.thickness * 0.5;
+                let (min_x, max_x) = (
+                    line.start[0].min(line.end[0]),
+                    line.start[0].max(line.end[0]),
+                );
+                let (min_y, max_y) = (
+                    line.start[1].min(line.end[1]),
+                    line.start[1].max(line.end[1]),
+                );
+                return Some([[min_x - pad, min_y - pad], [max_x + pad, max_y + pad]]);
+            }
+            CanvasItem::Marker(marker) => {
+                let [x, y] = marker.pos;
+                let r = Marker::RADIUS;
+                return Some([[x - r, y - r], [x + r, y + r]]);
+            }
+            CanvasItem::Stamp(stamp) => {
+                let [x, y] = stamp.pos;
+                let r = (stamp.size + stamp.thickness()) / 2.0;
+                return Some([[x - r, y - r], [x + r, y + r]]);
+            }
+            CanvasItem::Callout(callout) => {
+                let [[min_x, min_y], [max_x, max_y]] =
+                    CanvasItem::Text(callout.text.clone()).bounds(text_size)?;
+                let [x, y] = callout.tip;
+                return Some([[min_x.min(x), min_y.min(y)], [max_x.max(x), max_y.max(y)]]);
+            }
+        };
+        let first = *points.first()?;
+        let (min, max) = points.iter().fold((first, first), |(min, max), p| {
+            (
+                [min[0].min(p[0]), min[1].min(p[1])],
+                [max[0].max(p[0]), max[1].max(p[1])],
+            )
+        });
+        Some([[min[0] - pad, min[1] - pad], [max[0] + pad, max[1] + pad]])
+    }
+
+    /// Whether a click at `pos` lands on the item: anywhere on a text box or callout
+    /// bubble, or within `tolerance` of anything else. `text_size` is as for
+    /// [`Self::bounds`].
+    pub fn hit(&self, pos: [f32; 2], tolerance: f32, text_size: Option<[f32; 2]>) -> bool {
+        let in_box = |t: &TextBox| {
+            let [width, height] = text_size.unwrap_or_else(|| t.estimated_size());
+            (t.pos[0] - tolerance..=t.pos[0] + width + tolerance).contains(&pos[0])
+                && (t.pos[1] - tolerance..=t.pos[1] + height + tolerance).contains(&pos[1])
+        };
+        match self {
+            CanvasItem::Text(t) => in_box(t),
+            CanvasItem::Callout(callout) => {
+                in_box(&callout.text) || self.is_near(pos, tolerance, text_size)
+            }
+            _ => self.is_near(pos, tolerance, text_size),
+        }
+    }
+
+    /// Whether the item's outline (a text box's anchor, a callout's anchor or tail)
+    /// passes within `radius` of `center`, or a filled shape covers it. `text_size`
+    /// is as for [`Self::bounds`].
+    pub fn is_near(&self, center: [f32; 2], radius: f32, text_size: Option<[f32; 2]>) -> bool {
+        let near_polyline = |points: &[[f32; 2]], closed: bool| match points {
+            [] => false,
+            [p] => geometry::distance_to_segment(center, *p, *p) <= radius,
+            _ => {
+                let closing = closed.then(|| [points[points.len() - 1], points[0]]);
+                points
+                    .windows(2)
+                    .map(|pair| [pair[0], pair[1]])
+                    .chain(closing)
+                    .any(|[a, b]| geometry::distance_to_segment(center, a, b) <= radius)
+            }
+        };
+        match self {
+            CanvasItem::Stroke(path) => near_polyline(&path.points, false),
+            CanvasItem::Polygon(poly) => {
+                near_polyline(&poly.points, true)
+                    || (poly.fill.is_some() && geometry::contains_point(&poly.points, center))
+            }
+            CanvasItem::Text(t) => (t.pos[0] - center[0]).hypot(t.pos[1] - center[1]) <= radius,
+            CanvasItem::Ellipse(ellipse) => {
+                let outline = ellipse.outline();
+                near_polyline(&outline, true)
+                    || (ellipse.fill.is_some() && geometry::contains_point(&outline, center))
+            }
+            CanvasItem::Line(line) => near_polyline(&[line.start, line.end], false),
+            CanvasItem::Marker(marker) => {
+                let [x, y] = marker.pos;
+                (x - center[0]).hypot(y - center[1]) <= radius + Marker::RADIUS
+            }
+            CanvasItem::Stamp(stamp) => stamp
+                .lines()
+                .iter()
+                .any(|(points, closed)| near_polyline(points, *closed)),
+            CanvasItem::Callout(callout) => {
+                let anchor = callout.text.pos;
+                (anchor[0] - center[0]).hypot(anchor[1] - center[1]) <= radius
+                    || match callout
+                        .tail(text_size.unwrap_or_else(|| callout.text.estimated_size()))
+                    {
+                        Some(tail) => near_polyline(&tail, true),
+                        None => near_polyline(&[callout.tip], false),
+                    }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A stroke along the x axis from 0 to 10, through a point at 5.
+    fn line() -> StrokePath {
+        StrokePath {
+            points: vec![[0.0, 0.0], [5.0, 0.0], [10.0, 0.0]],
+            rgba: [255, 0, 0, 255],
+            thickness: 2.0,
+            widths: Vec::new(),
+        }
+    }
+
+    fn assert_near(actual: [f32; 2], expected: [f32; 2]) {
+        let off = (actual[0] - expected[0]).hypot(actual[1] - expected[1]);
+        assert!(off < 1e-4, "{actual:?} is not {expected:?}");
+    }
+
+    #[test]
+    fn an_eraser_off_the_stroke_leaves_it_alone() {
+        assert!(line().erase_circle([5.0, 3.0], 2.0).is_none());
+        // Just grazing the line is float noise, not an erased stretch.
+        assert!(line().erase_circle([5.0, 2.0], 2.0).is_none());
+    }
+
+    #[test]
+    fn erasing_the_middle_splits_the_stroke() {
+        let pieces = line().erase_circle([5.0, 0.0], 1.0).unwrap();
+        assert_eq!(pieces.len(), 2);
+        assert_near(pieces[0].points[0], [0.0, 0.0]);
+        assert_near(*pieces[0].points.last().unwrap(), [4.0, 0.0]);
+        assert_near(pieces[1].points[0], [6.0, 0.0]);
+        assert_near(*pieces[1].points.last().unwrap(), [10.0, 0.0]);
+        assert!(pieces.iter().all(|piece| piece.widths.is_empty()));
+    }
+
+    #[test]
+    fn erasing_an_end_shortens_the_stroke() {
+        let pieces = line().erase_circle([0.0, 0.0], 1.0).unwrap();
+        assert_eq!(pieces.len(), 1);
+        assert_near(pieces[0].points[0], [1.0, 0.0]);
+        assert_near(*pieces[0].points.last().unwrap(), [10.0, 0.0]);
+
+        let pieces = line().erase_circle([10.0, 0.0], 1.0).unwrap();
+        assert_eq!(pieces.len(), 1);
+        assert_near(pieces[0].points[0], [0.0, 0.0]);
+        assert_near(*pieces[0].points.last().unwrap(), [9.0, 0.0]);
+    }
+
+    #[test]
+    fn erasing_the_whole_stroke_leaves_no_pieces() {
+        assert!(line().erase_circle([5.0, 0.0], 20.0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn a_resting_eraser_does_not_cut_the_pieces_again() {
+        let pieces = line().erase_circle([5.0, 0.0], 1.0).unwrap();
+        for piece in pieces {
+            assert!(piece.erase_circle([5.0, 0.0], 1.0).is_none());
+        }
+    }
+
+    #[test]
+    fn pressure_widths_are_cut_with_the_points() {
+        let stroke = StrokePath {
+            widths: vec![2.0, 4.0, 6.0],
+            ..line()
+        };
+        let pieces = stroke.erase_circle([5.0, 0.0], 1.0).unwrap();
+        assert_eq!(pieces[0].widths.len(), pieces[0].points.len());
+        assert!((pieces[0].widths.last().unwrap() - 3.6).abs() < 1e-4);
+        assert!((pieces[1].widths[0] - 4.4).abs() < 1e-4);
+    }
+
+    /// One unit per character, so widths read as character counts.
+    fn wrapped(text: &str, wrap_width: Option<f32>) -> Vec<String> {
+        let text = TextBox {
+            pos: [0.0, 0.0],
+            text: text.to_string(),
+            rgba: [255, 255, 255, 255],
+            wrap_width,
+            background: TextBackground::default(),
+        };
+        text.lines(|line| line.chars().count() as f32)
+    }
+
+    #[test]
+    fn a_line_exactly_as_wide_as_the_wrap_width_stays_whole() {
+        assert_eq!(wrapped("aaaaa bbbb cc", Some(10.0)), ["aaaaa bbbb", "cc"]);
+        assert_eq!(wrapped("aaaaa bbbbb", Some(10.0)), ["aaaaa", "bbbbb"]);
+    }
+
+    #[test]
+    fn a_word_wider_than_the_wrap_width_breaks_between_characters() {
+        assert_eq!(
+            wrapped("hi abcdefghijkl", Some(5.0)),
+            ["hi", "abcde", "fghij", "kl"]
+        );
+    }
+
+    #[test]
+    fn lines_break_at_newlines_and_leave_out_markers() {
+        assert_eq!(wrapped("one\ntwo three", None), ["one", "two three"]);
+        // "**bold** text" shows as "bold text", which fits in 9.
+        assert_eq!(wrapped("**bold** text", Some(9.0)), ["bold text"]);
+        assert_eq!(wrapped("a\n\nb", Some(9.0)), ["a", "", "b"]);
+    }
+
+    #[test]
+    fn stamp_point_counts_match_their_lines() {
+        for kind in StampKind::ALL {
+            let points: usize = kind.lines().iter().map(|(points, _)| points.len()).sum();
+            assert_eq!(kind.point_count(), points, "{kind:?}");
+        }
+    }
+}
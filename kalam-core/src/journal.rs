@@ -0,0 +1,309 @@
+//! Append-only autosave journal.
+//!
+//! Every document change is appended as one JSON line, so saving costs as much as
+//! the change rather than the whole board. Replaying the file rebuilds the board
+//! up to the last change before OpenPen crashed. Changes are handed to the operating
+//! system right away but only synced to the disk itself every [`SYNC_INTERVAL`], so
+//! a power loss can take the last second of them with it. The journal is rewritten
+//! with just the live items once dead entries (removed items, clears) start to
+//! dominate it.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::crdt::{ItemId, ItemList, Op};
+use crate::document::Event;
+use crate::CanvasItem;
+
+/// Entries the journal may hold before it is considered for compaction.
+const COMPACT_MIN_ENTRIES: usize = 1_000;
+/// How long recorded changes may wait before they are synced to the disk.
+pub const SYNC_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum Entry {
+    Insert {
+        id: ItemId,
+        author: String,
+        item: CanvasItem,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        expires: Option<f64>,
+    },
+    Remove {
+        id: ItemId,
+    },
+    Update {
+        id: ItemId,
+        item: CanvasItem,
+    },
+    Clear,
+}
+
+pub struct Journal {
+    path: PathBuf,
+    file: BufWriter<File>,
+    entries: usize,
+    /// When the file was last synced, and whether changes were recorded since.
+    synced: Instant,
+    dirty: bool,
+}
+
+impl Journal {
+    /// Opens (or creates) the journal at `path` and returns the operations that
+    /// restore the items it recorded.
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<(Self, Vec<Op>)> {
+        let path = path.into();
+        let mut live = BTreeMap::new();
+        let mut entries = 0;
+        if let Ok(file) = File::open(&path) {
+            for line in BufReader::new(file).lines() {
+                // A crash can leave a torn last line; everything before it still counts.
+                let Ok(entry) = serde_json::from_str::<Entry>(&line?) else {
+                    break;
+                };
+                entries += 1;
+                match entry {
+                    Entry::Insert {
+                        id,
+                        author,
+                        item,
+                        expires,
+                    } => {
+                        live.insert(id, (author, item, expires));
+                    }
+                    Entry::Remove { id } => {
+                        live.remove(&id);
+                    }
+                    Entry::Update { id, item } => {
+                        if let Some((_, live_item, _)) = live.get_mut(&id) {
+                            *live_item = item;
+                        }
+                    }
+                    Entry::Clear => live.clear(),
+                }
+            }
+        }
+        let recovered = live
+            .into_iter()
+            .map(|(id, (author, item, expires))| Op::Insert {
+                id,
+                author,
+                item,
+                expires,
+            })
+            .collect();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let journal = Self {
+            path,
+            file: BufWriter::new(file),
+            entries,
+            synced: Instant::now(),
+            dirty: false,
+        };
+        Ok((journal, recovered))
+    }
+
+    /// Appends the given document events, looking up inserted items in `items`, and
+    /// syncs the file if changes have been waiting for [`SYNC_INTERVAL`]. Call it
+    /// without events to sync the last ones once nothing else changes.
+    pub fn record(&mut self, events: &[Event], items: &ItemList) -> io::Result<()> {
+        if events.is_empty() {
+            return self.sync_if_due();
+        }
+        for event in events {
+            let entry = match *event {
+                Event::Added(id) => {
+                    // Already gone again; the matching removal follows in this batch.
+                    let Some((item, author)) = items.get(id) else {
+                        continue;
+                    };
+                    Entry::Insert {
+                        id,
+                        author: author.to_string(),
+                        item: item.clone(),
+                        expires: items.expires(id),
+                    }
+                }
+                Event::Removed(id) => Entry::Remove { id },
+                Event::Updated(id) => {
+                    let Some((item, _)) = items.get(id) else {
+                        continue;
+                    };
+                    Entry::Update {
+                        id,
+                        item: item.clone(),
+                    }
+                }
+                Event::Cleared => Entry::Clear,
+            };
+            self.append(&entry)?;
+        }
+        self.file.flush()?;
+        self.dirty = true;
+
+        if self.entries >= COMPACT_MIN_ENTRIES && self.entries > items.len() * 2 {
+            self.compact(items)?;
+        }
+        self.sync_if_due()
+    }
+
+    /// Whether everything recorded so far has been synced to the disk.
+    pub fn is_synced(&self) -> bool {
+        !self.dirty
+    }
+
+    /// Rewrites the journal to hold only the current items.
+    pub fn compact(&mut self, items: &ItemList) -> io::Result<()> {
+        let tmp = self.path.with_extension("tmp");
+        let mut out = BufWriter::new(File::create(&tmp)?);
+        for (id, item, author) in items.iter_with_ids() {
+            let entry = Entry::Insert {
+                id,
+                author: author.to_string(),
+                item: item.clone(),
+                expires: items.expires(id),
+            };
+            serde_json::to_writer(&mut out, &entry)?;
+            out.write_all(b"\n")?;
+        }
+        out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&tmp, &self.path)?;
+        self.file = BufWriter::new(OpenOptions::new().append(true).open(&self.path)?);
+        self.entries = items.len();
+        self.synced = Instant::now();
+        self.dirty = false;
+        Ok(())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn sync_if_due(&mut self) -> io::Result<()> {
+        if self.dirty && self.synced.elapsed() >= SYNC_INTERVAL {
+            self.file.get_ref().sync_data()?;
+            self.synced = Instant::now();
+            self.dirty = false;
+        }
+        Ok(())
+    }
+
+    fn append(&mut self, entry: &Entry) -> io::Result<()> {
+        serde_json::to_writer(&mut self.file, entry)?;
+        self.file.write_all(b"\n")?;
+        self.entries += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::{Command, Document};
+    use crate::Marker;
+
+    fn marker(number: u32) -> CanvasItem {
+        CanvasItem::Marker(Marker {
+            pos: [0.0, 0.0],
+            number,
+            rgba: [255, 0, 0, 255],
+        })
+    }
+
+    /// A fresh journal path in the temp directory, unique to this test run.
+    fn journal_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("kalam-journal-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("{name}.jsonl"));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    /// Runs `commands` on a document whose changes go into a new journal at `path`.
+    fn record(path: &Path, commands: impl FnOnce(&mut Document)) -> Document {
+        let (mut journal, recovered) = Journal::open(path).unwrap();
+        assert!(recovered.is_empty());
+        let mut doc = Document::default();
+        let events = doc.subscribe();
+        commands(&mut doc);
+        let events: Vec<Event> = events.try_iter().collect();
+        journal.record(&events, &doc).unwrap();
+        doc
+    }
+
+    /// The board the journal at `path` restores, as marker numbers with fade-out times.
+    fn recover(path: &Path) -> Vec<(u32, Option<f64>)> {
+        let (_, recovered) = Journal::open(path).unwrap();
+        let mut list = ItemList::default();
+        for op in recovered {
+            list.apply(op);
+        }
+        list.iter_with_ids()
+            .map(|(id, item, _)| match item {
+                CanvasItem::Marker(marker) => (marker.number, list.expires(id)),
+                _ => panic!("only markers are drawn in these tests"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn replay_follows_inserts_updates_removals_and_clears() {
+        let path = journal_path("replay");
+        record(&path, |doc| {
+            doc.execute(Command::Add(marker(1)));
+            doc.execute(Command::Add(marker(2)));
+            doc.execute(Command::Clear);
+            let edited = doc.execute(Command::Add(marker(3))).unwrap();
+            doc.execute(Command::Replace {
+                id: edited,
+                item: marker(30),
+            });
+            doc.execute(Command::Add(marker(4)));
+            let removed = doc.execute(Command::Add(marker(5))).unwrap();
+            doc.execute(Command::Remove(removed));
+        });
+        assert_eq!(recover(&path), [(30, None), (4, None)]);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn replay_stops_at_a_torn_last_line() {
+        let path = journal_path("torn");
+        record(&path, |doc| {
+            doc.execute(Command::Add(marker(1)));
+            doc.execute(Command::Add(marker(2)));
+        });
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"Insert":{"id":{"clock":9,"#).unwrap();
+        drop(file);
+
+        assert_eq!(recover(&path), [(1, None), (2, None)]);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn compaction_keeps_the_live_items_and_their_fade_out_times() {
+        let path = journal_path("compact");
+        let doc = record(&path, |doc| {
+            let gone = doc.execute(Command::Add(marker(1))).unwrap();
+            doc.execute(Command::AddExpiring {
+                item: marker(2),
+                expires: 1_234.5,
+            });
+            doc.execute(Command::Add(marker(3)));
+            doc.execute(Command::Remove(gone));
+        });
+        let (mut journal, _) = Journal::open(&path).unwrap();
+        journal.compact(&doc).unwrap();
+
+        let lines = fs::read_to_string(&path).unwrap().lines().count();
+        assert_eq!(lines, 2);
+        assert_eq!(recover(&path), [(2, Some(1_234.5)), (3, None)]);
+        let _ = fs::remove_file(&path);
+    }
+}
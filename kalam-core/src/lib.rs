@@ -0,0 +1,15 @@
+//! OpenPen's document model, kept free of any UI toolkit so headless tools and
+//! other frontends can read, edit, and replicate boards.
+
+pub mod board;
+pub mod crdt;
+pub mod document;
+pub mod geometry;
+mod item;
+pub mod journal;
+pub mod markup;
+
+pub use item::{
+    Callout, CanvasItem, EllipseShape, LineShape, Marker, PolygonShape, Stamp, StampKind,
+    StrokePath, TextBackground, TextBox,
+};
@@ -0,0 +1,125 @@
+//! Lightweight emphasis inside text boxes: `**bold**`, `*italic*`, and `` `code` ``.
+//! A marker only counts when a matching one follows later on its line, so a lone
+//! asterisk stays as typed. Nothing is parsed inside code.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    pub bold: bool,
+    pub italic: bool,
+    pub code: bool,
+}
+
+/// A run of text shown in one style.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub style: Style,
+}
+
+/// Splits `text` into styled runs, with the markers taken out.
+pub fn parse(text: &str) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut style = Style::default();
+    let mut run = String::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let marker = if style.code {
+            rest.starts_with('`').then_some("`")
+        } else {
+            ["**", "*", "`"]
+                .into_iter()
+                .find(|marker| rest.starts_with(marker))
+        };
+        if let Some(marker) = marker {
+            let after = &rest[marker.len()..];
+            let before = style;
+            let flag = match marker {
+                "**" => &mut style.bold,
+                "*" => &mut style.italic,
+                _ => &mut style.code,
+            };
+            // Closes an open run, or opens one that gets closed on the same line.
+            let line_rest = after.split('\n').next().unwrap_or_default();
+            if *flag || line_rest.contains(marker) {
+                *flag = !*flag;
+                if !run.is_empty() {
+                    spans.push(Span {
+                        text: std::mem::take(&mut run),
+                        style: before,
+                    });
+                }
+                rest = after;
+                continue;
+            }
+        }
+        run.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    if !run.is_empty() {
+        spans.push(Span { text: run, style });
+    }
+    spans
+}
+
+/// `text` as shown, without its markers.
+pub fn plain(text: &str) -> String {
+    parse(text).into_iter().map(|span| span.text).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(text: &str, bold: bool, italic: bool, code: bool) -> Span {
+        Span {
+            text: text.to_string(),
+            style: Style { bold, italic, code },
+        }
+    }
+
+    #[test]
+    fn unclosed_markers_stay_as_typed() {
+        assert_eq!(plain("a **b"), "a **b");
+        assert_eq!(plain("2 * 3 = 6"), "2 * 3 = 6");
+        // A marker is only closed on its own line.
+        assert_eq!(plain("**a\nb**"), "**a\nb**");
+        assert_eq!(parse("a **b"), [span("a **b", false, false, false)]);
+    }
+
+    #[test]
+    fn nested_markers_combine_their_styles() {
+        assert_eq!(
+            parse("**bold *both* bold**"),
+            [
+                span("bold ", true, false, false),
+                span("both", true, true, false),
+                span(" bold", true, false, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn adjacent_markers_close_one_run_and_open_the_next() {
+        assert_eq!(
+            parse("**a***b*"),
+            [span("a", true, false, false), span("b", false, true, false)]
+        );
+        assert_eq!(
+            parse("*a*`b`"),
+            [span("a", false, true, false), span("b", false, false, true)]
+        );
+    }
+
+    #[test]
+    fn nothing_is_parsed_inside_code() {
+        assert_eq!(
+            parse("`a*b*c` *d*"),
+            [
+                span("a*b*c", false, false, true),
+                span(" ", false, false, false),
+                span("d", false, true, false),
+            ]
+        );
+        assert_eq!(plain("`**`"), "**");
+    }
+}
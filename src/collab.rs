@@ -0,0 +1,504 @@
+//! Networked sessions: one participant hosts a WebSocket relay, the others join it.
+//!
+//! Every peer runs a small I/O thread per socket. The host forwards each message it
+//! receives to all other peers, so clients only ever talk to the host. Participants
+//! join through a link carrying a random token (`host:port/join/<token>`) and any
+//! other connection is refused, so being on the same network is not enough to edit
+//! the board. A broadcasting host also hands out a viewer link
+//! (`host:port/view/<token>`); viewers receive every update but anything they send
+//! is dropped. While the host has the canvas locked, edits from participants are not
+//! relayed either; the host takes them back instead (see
+//! [`kalam_core::document::Document::reject`]), including edits that were already on
+//! their way when it locked, so the sender's board does not drift from everyone else's.
+
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::{Message, WebSocket};
+
+use crate::i18n::{tr, trf};
+use kalam_core::crdt::Op;
+
+pub const DEFAULT_PORT: u16 = 7879;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(15);
+/// How long connecting and the WebSocket handshake may take before we give up on a peer.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum WireMessage {
+    /// Item list operations; a newly connected peer receives the full state this way too.
+    Ops(Vec<Op>),
+    /// Sent by the host to a newly connected peer; `0` is always the host itself.
+    Welcome { participant: usize },
+    /// Sent by the host whenever drawing rights are revoked or restored.
+    Lock { locked: bool },
+    /// Live pointer position of a participant; `None` once it leaves the overlay.
+    Cursor {
+        participant: usize,
+        name: String,
+        pos: Option<[f32; 2]>,
+    },
+}
+
+pub enum SessionEvent {
+    Received(WireMessage),
+    /// The host told us our participant number.
+    Assigned(usize),
+    /// Host only: edits from `participant` that arrived while the canvas was locked.
+    Rejected {
+        participant: usize,
+        ops: Vec<Op>,
+    },
+    PeerJoined,
+    PeerLeft,
+    Disconnected(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Participant,
+    Viewer,
+}
+
+struct Peer {
+    id: usize,
+    role: Role,
+    outgoing: Sender<String>,
+}
+
+/// State shared between the session handle and its I/O threads.
+#[derive(Default)]
+struct Shared {
+    peers: Mutex<Vec<Peer>>,
+    /// The participant number handed to the last peer that joined; the host is `0`.
+    last_id: AtomicUsize,
+    stop: AtomicBool,
+    locked: AtomicBool,
+}
+
+pub struct Session {
+    label: String,
+    role: Role,
+    hosting: bool,
+    participant: Option<usize>,
+    viewer_link: Option<String>,
+    join_link: Option<String>,
+    shared: Arc<Shared>,
+    events: Receiver<SessionEvent>,
+}
+
+impl Session {
+    /// Starts hosting; participants join via [`Self::join_link`], and with `broadcast` set,
+    /// read-only viewers via [`Self::viewer_link`].
+    pub fn host(port: u16, broadcast: bool, ctx: egui::Context) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+        let port = listener.local_addr()?.port();
+        let addr = format!("{}:{port}", crate::companion::lan_ip());
+        let label = trf("Hosting on {0}", &[&addr]);
+        let viewer_token = broadcast.then(random_token).transpose()?;
+        let join_token = random_token()?;
+        let viewer_link = viewer_token
+            .as_ref()
+            .map(|token| format!("{addr}/view/{token}"));
+        let join_link = Some(format!("{addr}/join/{join_token}"));
+
+        let shared = Arc::new(Shared::default());
+        let (events_tx, events) = mpsc::channel();
+
+        let accept_shared = shared.clone();
+        let tokens = Arc::new(Tokens {
+            join: join_token,
+            view: viewer_token,
+        });
+        thread::spawn(move || {
+            while !accept_shared.stop.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        // Each handshake gets its own thread, so a peer that stalls in it
+                        // cannot hold up anyone else joining.
+                        let (tokens, shared, events, ctx) = (
+                            tokens.clone(),
+                            accept_shared.clone(),
+                            events_tx.clone(),
+                            ctx.clone(),
+                        );
+                        thread::spawn(move || admit_peer(stream, &tokens, shared, events, ctx));
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        thread::sleep(Duration::from_millis(50));
+                    }
+                    Err(e) => {
+                        let _ = events_tx.send(SessionEvent::Disconnected(e.to_string()));
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            label,
+            role: Role::Participant,
+            hosting: true,
+            participant: Some(0),
+            viewer_link,
+            join_link,
+            shared,
+            events,
+        })
+    }
+
+    /// Joins a host through the participant link `host:port/join/<token>` or the viewer
+    /// link `host:port/view/<token>` it handed out; the port may be left out. The
+    /// connection is made on its own thread; poll the returned [`PendingJoin`] for it.
+    pub fn join(addr: &str, ctx: egui::Context) -> PendingJoin {
+        let addr = addr.trim().to_string();
+        let (tx, result) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = tx.send(Self::connect(&addr, ctx.clone()));
+            ctx.request_repaint();
+        });
+        PendingJoin { result }
+    }
+
+    fn connect(addr: &str, ctx: egui::Context) -> Result<Self, String> {
+        let addr = addr.strip_prefix("ws://").unwrap_or(addr);
+        let Some((addr, path)) = addr.find('/').map(|idx| addr.split_at(idx)) else {
+            return Err(tr("Join with the participant or viewer link the host shows").into());
+        };
+        let addr = if addr.contains(':') {
+            addr.to_string()
+        } else {
+            format!("{addr}:{DEFAULT_PORT}")
+        };
+        let role = if path.starts_with("/view/") {
+            Role::Viewer
+        } else {
+            Role::Participant
+        };
+
+        let mut last_error = None;
+        let stream = addr
+            .to_socket_addrs()
+            .map_err(|e| e.to_string())?
+            .find_map(|resolved| {
+                TcpStream::connect_timeout(&resolved, HANDSHAKE_TIMEOUT)
+                    .map_err(|e| last_error = Some(e.to_string()))
+                    .ok()
+            })
+            .ok_or_else(|| last_error.unwrap_or_else(|| format!("{addr} did not resolve")))?;
+        stream
+            .set_read_timeout(Some(HANDSHAKE_TIMEOUT))
+            .and_then(|()| stream.set_write_timeout(Some(HANDSHAKE_TIMEOUT)))
+            .map_err(|e| e.to_string())?;
+        let (socket, _) =
+            tungstenite::client(format!("ws://{addr}{path}"), stream).map_err(|e| e.to_string())?;
+
+        let shared = Arc::new(Shared::default());
+        let (events_tx, events) = mpsc::channel();
+        // The host is our only peer; from our side it is always a participant.
+        spawn_peer(
+            0,
+            Role::Participant,
+            socket,
+            shared.clone(),
+            events_tx,
+            ctx,
+            false,
+        );
+
+        let label = match role {
+            Role::Participant => trf("Joined {0}", &[&addr]),
+            Role::Viewer => trf("Viewing {0} (read-only)", &[&addr]),
+        };
+        Ok(Self {
+            label,
+            role,
+            hosting: false,
+            participant: None,
+            viewer_link: None,
+            join_link: None,
+            shared,
+            events,
+        })
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn is_viewer(&self) -> bool {
+        self.role == Role::Viewer
+    }
+
+    pub fn is_host(&self) -> bool {
+        self.hosting
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.shared.locked.load(Ordering::Relaxed)
+    }
+
+    /// Whether the local user may currently edit the shared board.
+    pub fn can_draw(&self) -> bool {
+        self.hosting || (self.role == Role::Participant && !self.is_locked())
+    }
+
+    /// Host only: revokes or restores everyone else's drawing rights.
+    pub fn set_locked(&self, locked: bool) {
+        if self.hosting {
+            self.shared.locked.store(locked, Ordering::Relaxed);
+            self.send(&WireMessage::Lock { locked });
+        }
+    }
+
+    pub fn participant(&self) -> Option<usize> {
+        self.participant
+    }
+
+    pub fn viewer_link(&self) -> Option<&str> {
+        self.viewer_link.as_deref()
+    }
+
+    pub fn join_link(&self) -> Option<&str> {
+        self.join_link.as_deref()
+    }
+
+    pub fn peer_count(&self, role: Role) -> usize {
+        self.shared
+            .peers
+            .lock()
+            .map(|peers| peers.iter().filter(|p| p.role == role).count())
+            .unwrap_or(0)
+    }
+
+    pub fn send(&self, message: &WireMessage) {
+        let Ok(text) = serde_json::to_string(message) else {
+            return;
+        };
+        if let Ok(peers) = self.shared.peers.lock() {
+            for peer in peers.iter() {
+                let _ = peer.outgoing.send(text.clone());
+            }
+        }
+    }
+
+    /// Sends `message` to one peer only.
+    pub fn send_to(&self, participant: usize, message: &WireMessage) {
+        let Ok(text) = serde_json::to_string(message) else {
+            return;
+        };
+        if let Ok(peers) = self.shared.peers.lock() {
+            for peer in peers.iter().filter(|peer| peer.id == participant) {
+                let _ = peer.outgoing.send(text.clone());
+            }
+        }
+    }
+
+    /// Drains events received since the last call.
+    pub fn poll(&mut self) -> Vec<SessionEvent> {
+        let mut events: Vec<_> = self.events.try_iter().collect();
+        for event in &mut events {
+            match *event {
+                SessionEvent::Received(WireMessage::Welcome { participant }) => {
+                    self.participant = Some(participant);
+                    *event = SessionEvent::Assigned(participant);
+                }
+                SessionEvent::Received(WireMessage::Lock { locked }) if !self.hosting => {
+                    self.shared.locked.store(locked, Ordering::Relaxed);
+                }
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+/// A [`Session::join`] still connecting in the background.
+pub struct PendingJoin {
+    result: Receiver<Result<Session, String>>,
+}
+
+impl PendingJoin {
+    /// The joined session or why joining failed, once the attempt has finished.
+    pub fn poll(&self) -> Option<Result<Session, String>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err("connection attempt aborted".into())),
+        }
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// A fresh 128-bit session token from the operating system's CSPRNG, as hex.
+pub(crate) fn random_token() -> std::io::Result<String> {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes)?;
+    Ok(bytes.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+/// The tokens a host accepts in the WebSocket request path.
+struct Tokens {
+    join: String,
+    view: Option<String>,
+}
+
+/// Host side: runs the WebSocket handshake for a freshly accepted connection and, if it
+/// presents a valid token, numbers the peer, greets it, and starts its I/O thread.
+fn admit_peer(
+    stream: TcpStream,
+    tokens: &Tokens,
+    shared: Arc<Shared>,
+    events: Sender<SessionEvent>,
+    ctx: egui::Context,
+) {
+    if stream.set_nonblocking(false).is_err()
+        || stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT)).is_err()
+        || stream.set_write_timeout(Some(HANDSHAKE_TIMEOUT)).is_err()
+    {
+        return;
+    }
+    let mut role = Role::Participant;
+    // The callback signature is dictated by tungstenite.
+    #[allow(clippy::result_large_err)]
+    let check_path = |request: &Request, response: Response| {
+        let path = request.uri().path();
+        let matches = |prefix: &str, token: Option<&str>| {
+            path.strip_prefix(prefix)
+                .is_some_and(|given| token == Some(given))
+        };
+        if matches("/view/", tokens.view.as_deref()) {
+            role = Role::Viewer;
+            Ok(response)
+        } else if matches("/join/", Some(&tokens.join)) {
+            Ok(response)
+        } else {
+            let mut denied = ErrorResponse::new(Some("bad session token".into()));
+            *denied.status_mut() = tungstenite::http::StatusCode::FORBIDDEN;
+            Err(denied)
+        }
+    };
+    let Ok(socket) = tungstenite::accept_hdr(stream, check_path) else {
+        return;
+    };
+    if shared.stop.load(Ordering::Relaxed) {
+        return;
+    }
+    let id = shared.last_id.fetch_add(1, Ordering::Relaxed) + 1;
+    let locked = shared.locked.load(Ordering::Relaxed);
+    let outgoing = spawn_peer(id, role, socket, shared, events.clone(), ctx.clone(), true);
+    for greeting in [
+        WireMessage::Welcome { participant: id },
+        WireMessage::Lock { locked },
+    ] {
+        if let Ok(text) = serde_json::to_string(&greeting) {
+            let _ = outgoing.send(text);
+        }
+    }
+    let _ = events.send(SessionEvent::PeerJoined);
+    ctx.request_repaint();
+}
+
+fn spawn_peer(
+    id: usize,
+    role: Role,
+    mut socket: WebSocket<TcpStream>,
+    shared: Arc<Shared>,
+    events: Sender<SessionEvent>,
+    ctx: egui::Context,
+    relay: bool,
+) -> Sender<String> {
+    let (outgoing_tx, outgoing) = mpsc::channel::<String>();
+    if let Ok(mut list) = shared.peers.lock() {
+        list.push(Peer {
+            id,
+            role,
+            outgoing: outgoing_tx.clone(),
+        });
+    }
+
+    thread::spawn(move || {
+        // A short read timeout lets one thread interleave reads with queued writes.
+        let _ = socket.get_mut().set_read_timeout(Some(POLL_INTERVAL));
+        let _ = socket.get_mut().set_write_timeout(None);
+        let reason = loop {
+            if shared.stop.load(Ordering::Relaxed) {
+                let _ = socket.close(None);
+                break None;
+            }
+            if let Some(err) = outgoing
+                .try_iter()
+                .find_map(|text| socket.send(Message::text(text)).err())
+            {
+                break Some(err.to_string());
+            }
+
+            match socket.read() {
+                Ok(Message::Text(_)) if role == Role::Viewer => {}
+                Ok(Message::Text(text)) => {
+                    let Ok(message) = serde_json::from_str::<WireMessage>(&text) else {
+                        continue;
+                    };
+                    if relay {
+                        match message {
+                            WireMessage::Ops(ops) if shared.locked.load(Ordering::Relaxed) => {
+                                let _ = events.send(SessionEvent::Rejected {
+                                    participant: id,
+                                    ops,
+                                });
+                                ctx.request_repaint();
+                                continue;
+                            }
+                            WireMessage::Ops(_) | WireMessage::Cursor { .. } => {}
+                            // Session control messages may only come from the host.
+                            WireMessage::Welcome { .. } | WireMessage::Lock { .. } => continue,
+                        }
+                        if let Ok(list) = shared.peers.lock() {
+                            for peer in list.iter().filter(|peer| peer.id != id) {
+                                let _ = peer.outgoing.send(text.to_string());
+                            }
+                        }
+                    }
+                    let _ = events.send(SessionEvent::Received(message));
+                    ctx.request_repaint();
+                }
+                Ok(Message::Close(_)) => break Some("Peer closed the connection".to_string()),
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(e))
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                    ) => {}
+                Err(e) => break Some(e.to_string()),
+            }
+        };
+
+        if let Ok(mut list) = shared.peers.lock() {
+            list.retain(|peer| peer.id != id);
+        }
+        if let Some(reason) = reason {
+            let _ = events.send(if relay {
+                SessionEvent::PeerLeft
+            } else {
+                SessionEvent::Disconnected(reason)
+            });
+            ctx.request_repaint();
+        }
+    });
+
+    outgoing_tx
+}
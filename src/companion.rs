@@ -0,0 +1,246 @@
+//! Tiny HTTP server that turns a phone or tablet browser into a drawing pad.
+//!
+//! The page posts finished strokes in normalized `0..1` coordinates; the app maps
+//! them onto the overlay when it polls [`CompanionServer::poll`]. Every start picks
+//! a fresh pairing token that is part of the URL; requests without it are refused.
+//!
+//! The same server takes timer and break commands, so scripts and stream decks can
+//! drive them: `POST <url>command/<name>` with a name from [`RemoteCommand::parse`].
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use eframe::egui;
+use serde::Deserialize;
+
+pub const DEFAULT_PORT: u16 = 7878;
+
+const PAGE: &str = include_str!("companion.html");
+const MAX_BODY_BYTES: usize = 1 << 20;
+/// Most bytes read for the request line and headers together.
+const MAX_HEADER_BYTES: u64 = 16 * 1024;
+/// How long a connection may go quiet before it is dropped.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Deserialize)]
+pub struct RemoteStroke {
+    pub points: Vec<[f32; 2]>,
+}
+
+/// Timer and break controls accepted from outside the app.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteCommand {
+    CountdownStart,
+    CountdownPause,
+    CountdownReset,
+    StopwatchStart,
+    StopwatchStop,
+    StopwatchLap,
+    StopwatchReset,
+    BreakStart,
+    BreakEnd,
+}
+
+impl RemoteCommand {
+    /// Parses the last path segment of a command request, e.g. `countdown-start`.
+    pub fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "countdown-start" => Self::CountdownStart,
+            "countdown-pause" => Self::CountdownPause,
+            "countdown-reset" => Self::CountdownReset,
+            "stopwatch-start" => Self::StopwatchStart,
+            "stopwatch-stop" => Self::StopwatchStop,
+            "stopwatch-lap" => Self::StopwatchLap,
+            "stopwatch-reset" => Self::StopwatchReset,
+            "break-start" => Self::BreakStart,
+            "break-end" => Self::BreakEnd,
+            _ => return None,
+        })
+    }
+}
+
+/// Something the companion server received.
+enum Incoming {
+    Stroke(RemoteStroke),
+    Command(RemoteCommand),
+}
+
+pub struct CompanionServer {
+    url: String,
+    stop: Arc<AtomicBool>,
+    incoming: Receiver<Incoming>,
+}
+
+impl CompanionServer {
+    pub fn start(port: u16, ctx: egui::Context) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+        let port = listener.local_addr()?.port();
+        let token = crate::collab::random_token()?;
+        let url = format!("http://{}:{port}/{token}/", lan_ip());
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let (tx, incoming) = mpsc::channel();
+        let thread_stop = stop.clone();
+        thread::spawn(move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        // Each connection gets its own thread, so a slow or idle client
+                        // cannot keep the pad and timer commands waiting.
+                        let (token, tx, ctx) = (token.clone(), tx.clone(), ctx.clone());
+                        thread::spawn(move || handle_connection(stream, &token, &tx, &ctx));
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        thread::sleep(Duration::from_millis(50));
+                    }
+                    Err(_) => break,
+                }
+            }
+        });
+
+        Ok(Self {
+            url,
+            stop,
+            incoming,
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Drains strokes and commands received since the last call.
+    pub fn poll(&self) -> (Vec<RemoteStroke>, Vec<RemoteCommand>) {
+        let mut strokes = Vec::new();
+        let mut commands = Vec::new();
+        for incoming in self.incoming.try_iter() {
+            match incoming {
+                Incoming::Stroke(stroke) => strokes.push(stroke),
+                Incoming::Command(command) => commands.push(command),
+            }
+        }
+        (strokes, commands)
+    }
+}
+
+impl Drop for CompanionServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn handle_connection(
+    stream: TcpStream,
+    token: &str,
+    tx: &Sender<Incoming>,
+    ctx: &egui::Context,
+) -> std::io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut head = (&mut reader).take(MAX_HEADER_BYTES);
+
+    let mut request_line = String::new();
+    head.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if head.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+
+    let mut stream = stream;
+    if head.limit() == 0 {
+        return respond(
+            &mut stream,
+            "431 Request Header Fields Too Large",
+            "text/plain",
+            "headers too large",
+        );
+    }
+    let Some(path) = path
+        .strip_prefix('/')
+        .and_then(|path| path.strip_prefix(token))
+    else {
+        return respond(
+            &mut stream,
+            "403 Forbidden",
+            "text/plain",
+            "bad pairing token",
+        );
+    };
+    match (method.as_str(), path) {
+        ("GET", "/") => respond(&mut stream, "200 OK", "text/html; charset=utf-8", PAGE),
+        ("POST", "/stroke") if content_length > MAX_BODY_BYTES => respond(
+            &mut stream,
+            "413 Payload Too Large",
+            "text/plain",
+            "stroke too large",
+        ),
+        ("POST", "/stroke") => {
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body)?;
+            match serde_json::from_slice::<RemoteStroke>(&body) {
+                Ok(stroke) => {
+                    let _ = tx.send(Incoming::Stroke(stroke));
+                    ctx.request_repaint();
+                    respond(&mut stream, "204 No Content", "text/plain", "")
+                }
+                Err(_) => respond(&mut stream, "400 Bad Request", "text/plain", "bad stroke"),
+            }
+        }
+        ("POST", path) => match path
+            .strip_prefix("/command/")
+            .and_then(RemoteCommand::parse)
+        {
+            Some(command) => {
+                let _ = tx.send(Incoming::Command(command));
+                ctx.request_repaint();
+                respond(&mut stream, "204 No Content", "text/plain", "")
+            }
+            None => respond(&mut stream, "404 Not Found", "text/plain", "not found"),
+        },
+        _ => respond(&mut stream, "404 Not Found", "text/plain", "not found"),
+    }
+}
+
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &str,
+) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+/// Best guess at the address other devices on the network can reach us on.
+pub fn lan_ip() -> String {
+    // Connecting a UDP socket sends nothing; it only asks the OS which interface would route there.
+    UdpSocket::bind("0.0.0.0:0")
+        .and_then(|socket| {
+            socket.connect("192.0.2.1:80")?;
+            socket.local_addr()
+        })
+        .map(|addr: SocketAddr| addr.ip().to_string())
+        .unwrap_or_else(|_| "localhost".to_string())
+}
@@ -0,0 +1,322 @@
+//! User preferences kept between runs, stored as JSON in the platform's config
+//! directory. Missing or unreadable files fall back to the defaults.
+
+use std::io;
+use std::path::PathBuf;
+
+use eframe::egui::{Align2, Pos2, Vec2};
+use serde::{Deserialize, Serialize};
+
+use kalam_core::TextBackground;
+
+use crate::i18n::Locale;
+use crate::keymap::Keymap;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub toolbar: ToolbarPlacement,
+    pub toolbar_orientation: Orientation,
+    /// Collapse the toolbar to its handle while it is not being used.
+    pub toolbar_autohide: bool,
+    /// Icons only, with names in tooltips and no hotkey line.
+    pub toolbar_compact: bool,
+    /// Size multiplier for the toolbar, its menus and other controls.
+    pub ui_scale: f32,
+    /// Mirror the toolbar and open its menus to the left, away from a left drawing hand.
+    pub left_handed: bool,
+    /// Status line with pointer coordinates and board counts in the bottom-left corner.
+    pub show_status: bool,
+    /// The keystroke display also shows keys typed into other apps, read from the
+    /// system. Off by default, since passwords are keys too.
+    pub global_keystrokes: bool,
+    pub keymap: Keymap,
+    pub locale: Locale,
+    /// Minimize the overlay when Escape has nothing left to cancel.
+    pub escape_hides: bool,
+    pub theme: Theme,
+    /// Length of the countdown timer.
+    pub countdown_seconds: u32,
+    pub show_clock: bool,
+    pub clock_corner: Corner,
+    pub clock_24h: bool,
+    pub clock_seconds: bool,
+    pub clock_opacity: f32,
+    pub break_minutes: u32,
+    /// Shown above the break countdown; empty for the default text.
+    pub break_message: String,
+    pub ticker_text: String,
+    /// Top or bottom; the ticker scrolls along it.
+    pub ticker_edge: Edge,
+    /// Scroll speed in points per second.
+    pub ticker_speed: f32,
+    pub ticker_rgba: [u8; 4],
+    /// Attribution text drawn over everything; empty for none.
+    pub watermark: String,
+    pub watermark_corner: Corner,
+    pub watermark_opacity: f32,
+    /// Screen density for showing ruler distances in centimetres; 0 for pixels only.
+    pub ruler_dpi: f32,
+    /// How strongly pen strokes are evened out, from 0 (as drawn) to 1.
+    pub pen_smoothing: f32,
+    /// Largest deviation, in points, that simplifying a finished stroke may introduce.
+    pub stroke_simplification: f32,
+    /// How many steps Undo can go back.
+    pub history_depth: usize,
+    /// Soft limits past which the board warns that it is getting slow.
+    pub item_budget: usize,
+    pub point_budget: usize,
+    /// Simplify the local user's lines when the board goes over `point_budget`.
+    pub auto_simplify: bool,
+    /// The eraser cuts pen strokes apart instead of removing them whole.
+    pub partial_erase: bool,
+    /// Holding the secondary button over the canvas erases. Tablet drivers report the
+    /// stylus eraser end and barrel button that way, but so does a mouse right-click.
+    pub secondary_button_erases: bool,
+    /// Fill new polygons and ellipses with `shape_fill_rgba`.
+    pub shape_fill: bool,
+    /// Premultiplied, like item colors.
+    pub shape_fill_rgba: [u8; 4],
+    /// Draw a grid over the screen and snap polygon points, text boxes, and shape
+    /// corners to it.
+    pub grid: bool,
+    /// Distance between grid lines.
+    pub grid_spacing: f32,
+    /// Width new text boxes wrap at; 0 for no wrapping.
+    pub text_wrap_width: f32,
+    /// Backdrop for new text boxes.
+    pub text_background: TextBackground,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            toolbar: ToolbarPlacement::Docked(Edge::Bottom),
+            toolbar_orientation: Orientation::Auto,
+            toolbar_autohide: false,
+            toolbar_compact: false,
+            ui_scale: 1.0,
+            left_handed: false,
+            show_status: false,
+            global_keystrokes: false,
+            keymap: Keymap::default(),
+            locale: Locale::default(),
+            escape_hides: true,
+            theme: Theme::Dark,
+            countdown_seconds: 5 * 60,
+            show_clock: false,
+            clock_corner: Corner::TopLeft,
+            clock_24h: true,
+            clock_seconds: false,
+            clock_opacity: 0.8,
+            break_minutes: 10,
+            break_message: String::new(),
+            ticker_text: String::new(),
+            ticker_edge: Edge::Bottom,
+            ticker_speed: 120.0,
+            ticker_rgba: [255, 255, 255, 255],
+            watermark: String::new(),
+            watermark_corner: Corner::BottomRight,
+            watermark_opacity: 0.5,
+            ruler_dpi: 0.0,
+            pen_smoothing: 0.0,
+            stroke_simplification: 0.75,
+            history_depth: 100,
+            item_budget: 5_000,
+            point_budget: 250_000,
+            auto_simplify: false,
+            partial_erase: false,
+            secondary_button_erases: false,
+            shape_fill: false,
+            // Yellow at about a third opacity.
+            shape_fill_rgba: [80, 69, 0, 80],
+            grid: false,
+            grid_spacing: 40.0,
+            text_wrap_width: 480.0,
+            text_background: TextBackground::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Orientation {
+    /// Vertical while docked to the left or right edge, horizontal otherwise.
+    #[default]
+    Auto,
+    Horizontal,
+    Vertical,
+}
+
+/// Colors of the toolbar, its menus and the other controls drawn over the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+    /// Black and white with yellow highlights, thicker outlines and a bold focus ring,
+    /// for projectors in bright rooms.
+    HighContrast,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ToolbarPlacement {
+    /// Centred along a screen edge.
+    Docked(Edge),
+    /// Top-left corner at a fixed screen position.
+    Floating([f32; 2]),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Edge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl Edge {
+    /// Anchor and offset that keep something `margin` away from this edge.
+    pub fn anchor(self, margin: f32) -> (Align2, Vec2) {
+        match self {
+            Edge::Top => (Align2::CENTER_TOP, Vec2::new(0.0, margin)),
+            Edge::Bottom => (Align2::CENTER_BOTTOM, Vec2::new(0.0, -margin)),
+            Edge::Left => (Align2::LEFT_CENTER, Vec2::new(margin, 0.0)),
+            Edge::Right => (Align2::RIGHT_CENTER, Vec2::new(-margin, 0.0)),
+        }
+    }
+
+    /// Top-left corner for a box of `size` docked to this edge of a `screen`-sized area.
+    pub fn position(self, screen: Vec2, size: Vec2, margin: f32) -> Pos2 {
+        let (align, offset) = self.anchor(margin);
+        let anchored = align.pos_in_rect(&eframe::egui::Rect::from_min_size(Pos2::ZERO, screen));
+        align.anchor_size(anchored + offset, size).min
+    }
+
+    /// The edge `rect` is within `distance` of, if any, preferring the closest.
+    pub fn near(
+        rect: eframe::egui::Rect,
+        screen: eframe::egui::Rect,
+        distance: f32,
+    ) -> Option<Self> {
+        [
+            (Edge::Top, rect.top() - screen.top()),
+            (Edge::Bottom, screen.bottom() - rect.bottom()),
+            (Edge::Left, rect.left() - screen.left()),
+            (Edge::Right, screen.right() - rect.right()),
+        ]
+        .into_iter()
+        .filter(|(_, gap)| *gap < distance)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(edge, _)| edge)
+    }
+}
+
+impl Orientation {
+    /// English name, for [`crate::i18n::tr`].
+    pub fn name(self) -> &'static str {
+        match self {
+            Orientation::Auto => "Auto",
+            Orientation::Horizontal => "Horizontal",
+            Orientation::Vertical => "Vertical",
+        }
+    }
+}
+
+impl Corner {
+    pub const ALL: [Corner; 4] = [
+        Corner::TopLeft,
+        Corner::TopRight,
+        Corner::BottomLeft,
+        Corner::BottomRight,
+    ];
+
+    /// English name, for [`crate::i18n::tr`].
+    pub fn name(self) -> &'static str {
+        match self {
+            Corner::TopLeft => "Top left",
+            Corner::TopRight => "Top right",
+            Corner::BottomLeft => "Bottom left",
+            Corner::BottomRight => "Bottom right",
+        }
+    }
+
+    /// Anchor and offset that keep something `margin` away from both edges.
+    pub fn anchor(self, margin: f32) -> (Align2, Vec2) {
+        match self {
+            Corner::TopLeft => (Align2::LEFT_TOP, Vec2::new(margin, margin)),
+            Corner::TopRight => (Align2::RIGHT_TOP, Vec2::new(-margin, margin)),
+            Corner::BottomLeft => (Align2::LEFT_BOTTOM, Vec2::new(margin, -margin)),
+            Corner::BottomRight => (Align2::RIGHT_BOTTOM, Vec2::new(-margin, -margin)),
+        }
+    }
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::Dark, Theme::Light, Theme::HighContrast];
+
+    /// English name, for [`crate::i18n::tr`].
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Dark => "Dark",
+            Theme::Light => "Light",
+            Theme::HighContrast => "High contrast",
+        }
+    }
+}
+
+impl Config {
+    /// Whether the toolbar should lay its buttons out in a column.
+    pub fn toolbar_vertical(&self) -> bool {
+        match self.toolbar_orientation {
+            Orientation::Auto => matches!(
+                self.toolbar,
+                ToolbarPlacement::Docked(Edge::Left | Edge::Right)
+            ),
+            Orientation::Horizontal => false,
+            Orientation::Vertical => true,
+        }
+    }
+
+    pub fn load() -> Self {
+        path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory found"))?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// Where files that are not settings live, such as the crash-recovery journals.
+pub fn data_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("LOCALAPPDATA")
+        .or_else(|| std::env::var_os("XDG_DATA_HOME"))
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share"))
+        })?;
+    Some(base.join("openpen"))
+}
+
+fn path() -> Option<PathBuf> {
+    let base = std::env::var_os("APPDATA")
+        .or_else(|| std::env::var_os("XDG_CONFIG_HOME"))
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("openpen").join("config.json"))
+}
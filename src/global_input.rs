@@ -0,0 +1,363 @@
+//! Input as the operating system sees it, for presenter aids that have to keep working
+//! where the overlay receives no input of its own: the pointer in click-through mode,
+//! and, when the user opts in, keys typed into other apps for the keystroke display.
+//!
+//! A background thread polls the OS every [`POLL_INTERVAL`] and wakes the UI when
+//! the pointer moved, a button went down, or a key was pressed. Positions are in
+//! physical pixels from the top-left corner of the desktop; [`GlobalInput::to_overlay`]
+//! maps them onto the overlay. X11 and Windows are supported. Elsewhere, including
+//! Wayland, which does not let one client see input meant for another,
+//! [`GlobalInput::start`] fails and the aids only follow input that reaches the overlay.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use eframe::egui::{self, Key, KeyboardShortcut, Modifiers, Pos2};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Clone, Copy)]
+pub enum GlobalEvent {
+    /// The pointer moved here.
+    Moved([f32; 2]),
+    /// A mouse button went down here.
+    Pressed([f32; 2]),
+    /// A key went down, with the modifiers held at the time. Only sent while
+    /// [`GlobalInput::set_keys`] is on.
+    Key(KeyboardShortcut),
+}
+
+pub struct GlobalInput {
+    events: Receiver<GlobalEvent>,
+    pointer: Option<[f32; 2]>,
+    keys: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+}
+
+impl GlobalInput {
+    /// Starts watching the pointer, or says why this system does not allow it.
+    pub fn start(ctx: egui::Context) -> Result<Self, String> {
+        let os = os::Os::open()?;
+        let keys = Arc::new(AtomicBool::new(false));
+        let stop = Arc::new(AtomicBool::new(false));
+        let (tx, events) = mpsc::channel();
+        let (thread_keys, thread_stop) = (keys.clone(), stop.clone());
+        thread::spawn(move || watch(os, &tx, &thread_keys, &thread_stop, &ctx));
+        Ok(Self {
+            events,
+            pointer: None,
+            keys,
+            stop,
+        })
+    }
+
+    /// Whether to watch the keyboard too. Off until asked for, since it sees what is
+    /// typed into every app, passwords included.
+    pub fn set_keys(&self, on: bool) {
+        self.keys.store(on, Ordering::Relaxed);
+    }
+
+    /// Drains what happened since the last call.
+    pub fn poll(&mut self) -> Vec<GlobalEvent> {
+        let events: Vec<_> = self.events.try_iter().collect();
+        for event in &events {
+            if let GlobalEvent::Moved(pos) | GlobalEvent::Pressed(pos) = *event {
+                self.pointer = Some(pos);
+            }
+        }
+        events
+    }
+
+    /// Where the pointer was at the last [`Self::poll`].
+    pub fn pointer(&self) -> Option<[f32; 2]> {
+        self.pointer
+    }
+
+    /// Maps a desktop position onto the overlay in `ctx`.
+    pub fn to_overlay(ctx: &egui::Context, pos: [f32; 2]) -> Pos2 {
+        let (origin, pixels_per_point) = ctx.input(|i| {
+            let viewport = i.viewport();
+            (
+                viewport.inner_rect.map_or(Pos2::ZERO, |rect| rect.min),
+                viewport.native_pixels_per_point.unwrap_or(1.0),
+            )
+        });
+        Pos2::new(pos[0], pos[1]) / pixels_per_point - origin.to_vec2()
+    }
+}
+
+impl Drop for GlobalInput {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// What a key on the keyboard stands for.
+#[derive(Debug, Clone, Copy)]
+enum OsKey {
+    Modifier(Modifiers),
+    Key(Key),
+}
+
+fn watch(
+    mut os: os::Os,
+    tx: &Sender<GlobalEvent>,
+    keys: &AtomicBool,
+    stop: &AtomicBool,
+    ctx: &egui::Context,
+) {
+    let mut last_pos = None;
+    let mut was_down = false;
+    let mut held: Vec<Key> = Vec::new();
+    while !stop.load(Ordering::Relaxed) {
+        let mut changed = false;
+        if let Some((pos, down)) = os.pointer() {
+            if last_pos != Some(pos) {
+                last_pos = Some(pos);
+                changed |= tx.send(GlobalEvent::Moved(pos)).is_ok();
+            }
+            if down && !was_down {
+                changed |= tx.send(GlobalEvent::Pressed(pos)).is_ok();
+            }
+            was_down = down;
+        }
+        if keys.load(Ordering::Relaxed) {
+            let mut modifiers = Modifiers::NONE;
+            let mut down = Vec::new();
+            for key in os.keys_down() {
+                match key {
+                    OsKey::Modifier(modifier) => modifiers = modifiers | modifier,
+                    OsKey::Key(key) => down.push(key),
+                }
+            }
+            // Keys held since the last poll were already shown; so were auto-repeats.
+            for &key in down.iter().filter(|key| !held.contains(key)) {
+                let shortcut = KeyboardShortcut::new(modifiers, key);
+                changed |= tx.send(GlobalEvent::Key(shortcut)).is_ok();
+            }
+            held = down;
+        } else {
+            held.clear();
+        }
+        if changed {
+            ctx.request_repaint();
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod os {
+    use eframe::egui::{Key, Modifiers};
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{ConnectionExt, KeyButMask, Window};
+    use x11rb::rust_connection::RustConnection;
+
+    use super::OsKey;
+    use crate::i18n::trf;
+
+    pub struct Os {
+        conn: RustConnection,
+        root: Window,
+        /// What each keycode stands for in the layout active at startup.
+        keycodes: Vec<(u8, OsKey)>,
+    }
+
+    impl Os {
+        pub fn open() -> Result<Self, String> {
+            let (conn, screen) = x11rb::connect(None)
+                .map_err(|e| trf("No X11 display to follow the pointer on: {0}", &[&e]))?;
+            let root = conn.setup().roots[screen].root;
+            // Without a keyboard mapping the pointer still works; only keys go unseen.
+            let keycodes = keycodes(&conn).unwrap_or_default();
+            Ok(Self {
+                conn,
+                root,
+                keycodes,
+            })
+        }
+
+        /// The pointer's position and whether a mouse button is down, if the X server
+        /// answers.
+        pub fn pointer(&mut self) -> Option<([f32; 2], bool)> {
+            let reply = self.conn.query_pointer(self.root).ok()?.reply().ok()?;
+            let buttons = KeyButMask::BUTTON1 | KeyButMask::BUTTON2 | KeyButMask::BUTTON3;
+            let down = u16::from(reply.mask) & u16::from(buttons) != 0;
+            Some(([f32::from(reply.root_x), f32::from(reply.root_y)], down))
+        }
+
+        /// The keys held down right now.
+        pub fn keys_down(&mut self) -> Vec<OsKey> {
+            let Some(reply) = self.conn.query_keymap().ok().and_then(|c| c.reply().ok()) else {
+                return Vec::new();
+            };
+            self.keycodes
+                .iter()
+                .filter(|(code, _)| reply.keys[usize::from(code / 8)] & (1 << (code % 8)) != 0)
+                .map(|&(_, key)| key)
+                .collect()
+        }
+    }
+
+    fn keycodes(conn: &RustConnection) -> Option<Vec<(u8, OsKey)>> {
+        let setup = conn.setup();
+        let (first, last) = (setup.min_keycode, setup.max_keycode);
+        let mapping = conn
+            .get_keyboard_mapping(first, last - first + 1)
+            .ok()?
+            .reply()
+            .ok()?;
+        let per_keycode = usize::from(mapping.keysyms_per_keycode).max(1);
+        // The first keysym is the key's unshifted meaning, the one printed on it.
+        let keycodes = (first..=last)
+            .zip(mapping.keysyms.chunks(per_keycode))
+            .filter_map(|(code, keysyms)| Some((code, keysym_key(keysyms[0])?)))
+            .collect();
+        Some(keycodes)
+    }
+
+    fn keysym_key(keysym: u32) -> Option<OsKey> {
+        let key = match keysym {
+            0xffe1 | 0xffe2 => return Some(OsKey::Modifier(Modifiers::SHIFT)),
+            0xffe3 | 0xffe4 => return Some(OsKey::Modifier(Modifiers::CTRL)),
+            0xffe9 | 0xffea => return Some(OsKey::Modifier(Modifiers::ALT)),
+            0x20..=0x7e => Key::from_name(&char::from(keysym as u8).to_string())?,
+            0xff08 => Key::Backspace,
+            0xff09 => Key::Tab,
+            0xff0d | 0xff8d => Key::Enter,
+            0xff1b => Key::Escape,
+            0xff50 => Key::Home,
+            0xff51 => Key::ArrowLeft,
+            0xff52 => Key::ArrowUp,
+            0xff53 => Key::ArrowRight,
+            0xff54 => Key::ArrowDown,
+            0xff55 => Key::PageUp,
+            0xff56 => Key::PageDown,
+            0xff57 => Key::End,
+            0xff63 => Key::Insert,
+            0xffff => Key::Delete,
+            // Keypad digits, then F1 to F12, each in a run.
+            0xffb0..=0xffb9 => Key::from_name(&(keysym - 0xffb0).to_string())?,
+            0xffbe..=0xffc9 => Key::from_name(&format!("F{}", keysym - 0xffbe + 1))?,
+            _ => return None,
+        };
+        Some(OsKey::Key(key))
+    }
+}
+
+#[cfg(windows)]
+mod os {
+    use eframe::egui::{Key, Modifiers};
+    use windows_sys::Win32::Foundation::POINT;
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::GetAsyncKeyState;
+    use windows_sys::Win32::UI::WindowsAndMessaging::GetCursorPos;
+
+    use super::OsKey;
+
+    /// Virtual-key codes of the left, right, and middle mouse buttons.
+    const MOUSE_BUTTONS: [i32; 3] = [0x01, 0x02, 0x04];
+
+    /// Virtual-key codes other than letters, digits, and function keys. The
+    /// punctuation keys are named as on a US layout.
+    const KEYS: [(i32, OsKey); 28] = [
+        (0x10, OsKey::Modifier(Modifiers::SHIFT)),
+        (0x11, OsKey::Modifier(Modifiers::CTRL)),
+        (0x12, OsKey::Modifier(Modifiers::ALT)),
+        (0x08, OsKey::Key(Key::Backspace)),
+        (0x09, OsKey::Key(Key::Tab)),
+        (0x0d, OsKey::Key(Key::Enter)),
+        (0x1b, OsKey::Key(Key::Escape)),
+        (0x20, OsKey::Key(Key::Space)),
+        (0x21, OsKey::Key(Key::PageUp)),
+        (0x22, OsKey::Key(Key::PageDown)),
+        (0x23, OsKey::Key(Key::End)),
+        (0x24, OsKey::Key(Key::Home)),
+        (0x25, OsKey::Key(Key::ArrowLeft)),
+        (0x26, OsKey::Key(Key::ArrowUp)),
+        (0x27, OsKey::Key(Key::ArrowRight)),
+        (0x28, OsKey::Key(Key::ArrowDown)),
+        (0x2d, OsKey::Key(Key::Insert)),
+        (0x2e, OsKey::Key(Key::Delete)),
+        (0xba, OsKey::Key(Key::Semicolon)),
+        (0xbb, OsKey::Key(Key::Equals)),
+        (0xbc, OsKey::Key(Key::Comma)),
+        (0xbd, OsKey::Key(Key::Minus)),
+        (0xbe, OsKey::Key(Key::Period)),
+        (0xbf, OsKey::Key(Key::Slash)),
+        (0xc0, OsKey::Key(Key::Backtick)),
+        (0xdb, OsKey::Key(Key::OpenBracket)),
+        (0xdc, OsKey::Key(Key::Backslash)),
+        (0xdd, OsKey::Key(Key::CloseBracket)),
+    ];
+
+    pub struct Os;
+
+    impl Os {
+        pub fn open() -> Result<Self, String> {
+            Ok(Self)
+        }
+
+        /// The cursor's position and whether a mouse button is down.
+        pub fn pointer(&mut self) -> Option<([f32; 2], bool)> {
+            let mut point = POINT { x: 0, y: 0 };
+            // SAFETY: `point` is a valid POINT for the call to fill in.
+            if unsafe { GetCursorPos(&mut point) } == 0 {
+                return None;
+            }
+            let down = MOUSE_BUTTONS.iter().any(|&vk| is_down(vk));
+            Some(([point.x as f32, point.y as f32], down))
+        }
+
+        /// The keys held down right now.
+        pub fn keys_down(&mut self) -> Vec<OsKey> {
+            // Letters and digits share their virtual-key codes with ASCII.
+            let named = (b'0'..=b'9').chain(b'A'..=b'Z').filter_map(|c| {
+                let key = Key::from_name(&char::from(c).to_string())?;
+                Some((i32::from(c), OsKey::Key(key)))
+            });
+            let function = (1..=12).filter_map(|n| {
+                let key = Key::from_name(&format!("F{n}"))?;
+                Some((0x6f + n, OsKey::Key(key)))
+            });
+            KEYS.into_iter()
+                .chain(named)
+                .chain(function)
+                .filter(|&(vk, _)| is_down(vk))
+                .map(|(_, key)| key)
+                .collect()
+        }
+    }
+
+    fn is_down(vk: i32) -> bool {
+        // A negative state has the high bit set: the key is down right now.
+        // SAFETY: GetAsyncKeyState takes any virtual-key code.
+        unsafe { GetAsyncKeyState(vk) < 0 }
+    }
+}
+
+#[cfg(not(any(windows, all(unix, not(target_os = "macos")))))]
+mod os {
+    use super::OsKey;
+    use crate::i18n::tr;
+
+    pub struct Os;
+
+    impl Os {
+        pub fn open() -> Result<Self, String> {
+            Err(tr("This system does not let OpenPen follow the pointer \
+                   outside its window")
+            .into())
+        }
+
+        pub fn pointer(&mut self) -> Option<([f32; 2], bool)> {
+            None
+        }
+
+        pub fn keys_down(&mut self) -> Vec<OsKey> {
+            Vec::new()
+        }
+    }
+}
@@ -0,0 +1,651 @@
+//! UI translations. Strings are looked up by their English text, which is also what
+//! is shown when a language has no entry for one. Placeholders are numbered (`{0}`,
+//! `{1}`, ...) so translations can reorder them.
+
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Locale {
+    #[serde(rename = "en")]
+    English,
+    #[serde(rename = "de")]
+    German,
+    #[serde(rename = "es")]
+    Spanish,
+}
+
+impl Default for Locale {
+    /// The language of the user's environment, if it is one we ship.
+    fn default() -> Self {
+        let lang = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty()))
+            .unwrap_or_default();
+        match lang.get(..2) {
+            Some("de") => Locale::German,
+            Some("es") => Locale::Spanish,
+            _ => Locale::English,
+        }
+    }
+}
+
+impl Locale {
+    pub const ALL: [Locale; 3] = [Locale::English, Locale::German, Locale::Spanish];
+
+    /// The language's name in that language, for the picker.
+    pub fn name(self) -> &'static str {
+        match self {
+            Locale::English => "English",
+            Locale::German => "Deutsch",
+            Locale::Spanish => "Español",
+        }
+    }
+
+    fn catalog(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Locale::English => &[],
+            Locale::German => GERMAN,
+            Locale::Spanish => SPANISH,
+        }
+    }
+}
+
+static CURRENT: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_locale(locale: Locale) {
+    let idx = Locale::ALL.iter().position(|l| *l == locale).unwrap_or(0);
+    CURRENT.store(idx as u8, Ordering::Relaxed);
+}
+
+fn current() -> Locale {
+    Locale::ALL[CURRENT.load(Ordering::Relaxed) as usize]
+}
+
+/// `english` in the current language.
+pub fn tr(english: &'static str) -> &'static str {
+    current()
+        .catalog()
+        .iter()
+        .find(|(key, _)| *key == english)
+        .map_or(english, |(_, translated)| translated)
+}
+
+/// [`tr`] with `{0}`, `{1}`, ... replaced by `args`.
+pub fn trf(english: &'static str, args: &[&dyn Display]) -> String {
+    let mut text = tr(english).to_string();
+    for (idx, arg) in args.iter().enumerate() {
+        text = text.replace(&format!("{{{idx}}}"), &arg.to_string());
+    }
+    text
+}
+
+const GERMAN: &[(&str, &str)] = &[
+    // Toolbar
+    ("Drag toolbar", "Leiste verschieben"),
+    ("Drop it near a screen edge to dock it there", "Nahe einem Bildschirmrand ablegen, um sie dort anzudocken"),
+    ("Mouse", "Maus"),
+    ("Pen", "Stift"),
+    ("Polygon", "Polygon"),
+    ("Text", "Text"),
+    ("Eraser", "Radierer"),
+    ("Presenter", "Präsentation"),
+    ("Presenter aids", "Präsentationshilfen"),
+    ("Session", "Sitzung"),
+    ("Settings", "Einstellungen"),
+    ("Undo", "Rückgängig"),
+    ("Redo", "Wiederholen"),
+    ("Undo pen stroke", "Linie rückgängig machen"),
+    ("Undo polygon", "Polygon rückgängig machen"),
+    ("Undo text “{0}”", "Text „{0}“ rückgängig machen"),
+    ("Redo pen stroke", "Linie wiederherstellen"),
+    ("Redo polygon", "Polygon wiederherstellen"),
+    ("Redo text “{0}”", "Text „{0}“ wiederherstellen"),
+    ("Undo ellipse", "Ellipse rückgängig machen"),
+    ("Redo ellipse", "Ellipse wiederherstellen"),
+    ("Ellipse", "Ellipse"),
+    ("Undo line", "Linie rückgängig machen"),
+    ("Redo line", "Linie wiederherstellen"),
+    ("Line", "Linie"),
+    ("Rectangular spotlight", "Rechteckiges Spotlight"),
+    ("Marker", "Markierung"),
+    ("Undo marker {0}", "Markierung {0} rückgängig machen"),
+    ("Redo marker {0}", "Markierung {0} wiederherstellen"),
+    ("Next: {0}", "Nächste: {0}"),
+    ("Restart at 1", "Wieder bei 1 beginnen"),
+    ("Stamp", "Stempel"),
+    ("Undo stamp", "Stempel rückgängig machen"),
+    ("Redo stamp", "Stempel wiederherstellen"),
+    ("Undo callout", "Sprechblase rückgängig machen"),
+    ("Redo callout", "Sprechblase wiederherstellen"),
+    ("Undo adding {0} items", "Hinzufügen von {0} Elementen rückgängig machen"),
+    ("Redo adding {0} items", "Hinzufügen von {0} Elementen wiederherstellen"),
+    ("Undo erase", "Radieren rückgängig machen"),
+    ("Redo erase", "Radieren wiederherstellen"),
+    ("Undo edit", "Bearbeitung rückgängig machen"),
+    ("Redo edit", "Bearbeitung wiederherstellen"),
+    ("Callout", "Sprechblase"),
+    (
+        "Drag from what it points at to where the bubble goes",
+        "Vom Ziel der Spitze dorthin ziehen, wo die Blase hin soll",
+    ),
+    ("Check mark", "Häkchen"),
+    ("Cross", "Kreuz"),
+    ("Star", "Stern"),
+    ("Smiley", "Smiley"),
+    ("Stamp {0} px", "Stempel {0} px"),
+    ("Ruler", "Lineal"),
+    ("Select", "Auswählen"),
+    ("Click an item to select it, or drag a box around several; Shift adds to the selection", "Element anklicken oder einen Rahmen um mehrere ziehen; Umschalt erweitert die Auswahl"),
+    (" · {0} selected, {1} × {2}", " · {0} ausgewählt, {1} × {2}"),
+    ("{0} selected", "{0} ausgewählt"),
+    ("Apply current color", "Aktuelle Farbe anwenden"),
+    ("Recolor the selected items", "Ausgewählte Elemente umfärben"),
+    ("Delete the selected items", "Ausgewählte Elemente löschen"),
+    ("Duplicate", "Duplizieren"),
+    ("Align", "Ausrichten"),
+    ("Align left edges", "Linke Kanten ausrichten"),
+    ("Align horizontal centers", "Horizontal zentrieren"),
+    ("Align right edges", "Rechte Kanten ausrichten"),
+    ("Align top edges", "Obere Kanten ausrichten"),
+    ("Align vertical centers", "Vertikal zentrieren"),
+    ("Align bottom edges", "Untere Kanten ausrichten"),
+    ("Distribute horizontally", "Horizontal verteilen"),
+    ("Distribute vertically", "Vertikal verteilen"),
+    ("Duplicate the selected items", "Ausgewählte Elemente duplizieren"),
+    ("Screen DPI:", "Bildschirm-DPI:"),
+    ("Wrap at:", "Umbrechen bei:"),
+    ("Background", "Hintergrund"),
+    ("Background of the selected text boxes", "Hintergrund der ausgewählten Textfelder"),
+    ("Padding:", "Innenabstand:"),
+    ("Corner radius", "Eckenradius"),
+    (
+        "Width new text boxes wrap their lines at; 0 breaks lines only at newlines",
+        "Breite, bei der neue Textfelder ihre Zeilen umbrechen; 0 bricht nur bei Zeilenwechseln um",
+    ),
+    ("Set to show distances in centimetres as well; 0 for pixels only", "Festlegen, um Abstände auch in Zentimetern anzuzeigen; 0 nur für Pixel"),
+    ("{0} px · {1} cm", "{0} px · {1} cm"),
+    ("{0} px", "{0} px"),
+    ("Nothing to undo", "Nichts rückgängig zu machen"),
+    ("Nothing to redo", "Nichts wiederherzustellen"),
+    ("Clear", "Leeren"),
+    ("Save", "Speichern"),
+    ("Save to openpen-board.json", "In openpen-board.json speichern"),
+    ("Keyboard shortcuts: {0}", "Tastenkürzel: {0}"),
+    // Pen and presenter menus
+    ("Smoothing", "Glättung"),
+    ("Predict ahead", "Vorausschauen"),
+    ("Extend the line along the pen's motion to hide input lag", "Verlängert die Linie in Bewegungsrichtung, um Eingabeverzögerung zu verbergen"),
+    ("Cursor highlight", "Zeiger hervorheben"),
+    ("Click ripples", "Klickwellen"),
+    ("Color:", "Farbe:"),
+    ("Size", "Größe"),
+    ("Show keystrokes", "Tastendrücke anzeigen"),
+    ("Include keys typed into other apps", "Auch Tasten aus anderen Apps"),
+    ("Reads the keyboard system-wide, so it also shows passwords typed while it is on", "Liest die Tastatur systemweit und zeigt daher auch Passwörter, die währenddessen eingegeben werden"),
+    ("No X11 display to follow the pointer on: {0}", "Keine X11-Anzeige, auf der dem Zeiger gefolgt werden kann: {0}"),
+    ("This system does not let OpenPen follow the pointer outside its window", "Dieses System erlaubt OpenPen nicht, dem Zeiger außerhalb seines Fensters zu folgen"),
+    // Settings menu
+    ("Language", "Sprache"),
+    ("Click-through overlay in Mouse mode", "Im Mausmodus durchklickbares Overlay"),
+    ("Moves the toolbar into its own window and lets clicks reach the apps below while no drawing tool is active", "Verschiebt die Leiste in ein eigenes Fenster und lässt Klicks zu den Programmen darunter durch, solange kein Zeichenwerkzeug aktiv ist"),
+    ("Toolbar layout", "Leistenausrichtung"),
+    ("Auto", "Automatisch"),
+    ("Horizontal", "Waagerecht"),
+    ("Vertical", "Senkrecht"),
+    ("Auto stacks the tools in a column while docked to the left or right edge", "Automatisch stapelt die Werkzeuge in einer Spalte, solange die Leiste links oder rechts angedockt ist"),
+    ("UI scale", "UI-Skalierung"),
+    ("Status line", "Statuszeile"),
+    ("Pointer coordinates and item and point counts", "Zeigerkoordinaten sowie Anzahl der Elemente und Punkte"),
+    ("Left-handed layout", "Linkshändermodus"),
+    ("Mirror the toolbar and open its menus to the left", "Spiegelt die Leiste und öffnet ihre Menüs nach links"),
+    ("Compact toolbar (icons only)", "Kompakte Leiste (nur Symbole)"),
+    ("Auto-hide toolbar", "Leiste automatisch ausblenden"),
+    ("Collapse the toolbar to its handle when idle; hover it or press T to bring it back", "Klappt die Leiste bei Nichtbenutzung zu ihrem Griff zusammen; mit der Maus darüberfahren oder T drücken, um sie zurückzuholen"),
+    ("Stroke simplification (px)", "Linienvereinfachung (px)"),
+    ("Drops pointer samples that deviate less than this from the simplified stroke; 0 keeps every sample", "Verwirft Zeigerpunkte, die weniger als diesen Wert von der vereinfachten Linie abweichen; 0 behält alle Punkte"),
+    ("Undo steps:", "Rückgängig-Schritte:"),
+    ("How many steps are kept for Undo; the oldest go first", "Wie viele Schritte zum Rückgängigmachen aufbewahrt werden; die ältesten fallen zuerst weg"),
+    ("Grid spacing:", "Rasterabstand:"),
+    ("Soft limits:", "Weiche Grenzen:"),
+    (" items", " Elemente"),
+    (" points", " Punkte"),
+    ("Simplify your lines when over the point limit", "Eigene Linien beim Überschreiten der Punktgrenze vereinfachen"),
+    ("Phone/tablet drawing pad", "Handy/Tablet als Zeichenfläche"),
+    ("Open {0} on a device on this network", "{0} auf einem Gerät in diesem Netzwerk öffnen"),
+    ("Autosave: {0}", "Automatisches Speichern: {0}"),
+    ("Settings file: {0}", "Einstellungsdatei: {0}"),
+    ("Drawing pad failed: {0}", "Zeichenfläche fehlgeschlagen: {0}"),
+    // Tool options
+    ("Thickness", "Stärke"),
+    ("Outline", "Umriss"),
+    ("Fill", "Füllung"),
+    ("Split strokes", "Linien teilen"),
+    ("Erase only the part of a pen stroke under the eraser instead of the whole stroke", "Radiert nur den Teil einer Stiftlinie unter dem Radierer statt der ganzen Linie"),
+    ("Stylus eraser button", "Radiertaste am Stift"),
+    ("Erase while the stylus eraser end, barrel button, or right mouse button is held down", "Radiert, solange das Radierende oder die Seitentaste des Stifts oder die rechte Maustaste gedrückt ist"),
+    ("Fill color", "Füllfarbe"),
+    ("{0} points · Enter closes", "{0} Punkte · Enter schließt"),
+    ("Text:", "Text:"),
+    ("Eraser size", "Radiergröße"),
+    ("Thickness {0} px", "Stärke {0} px"),
+    ("Eraser {0} px", "Radierer {0} px"),
+    // Session menu
+    ("Participants: {0} · Viewers: {1}", "Teilnehmende: {0} · Zuschauende: {1}"),
+    ("🔒 Lock canvas to presenter", "🔒 Zeichenfläche für Vortragende sperren"),
+    ("Temporarily revoke everyone else's drawing rights", "Entzieht allen anderen vorübergehend das Zeichenrecht"),
+    ("🔒 The presenter has locked the canvas", "🔒 Die Zeichenfläche ist gesperrt"),
+    ("Viewer link: {0}", "Zuschauerlink: {0}"),
+    ("Participant link: {0}", "Teilnehmerlink: {0}"),
+    ("Copy", "Kopieren"),
+    ("Leave session", "Sitzung verlassen"),
+    ("Name:", "Name:"),
+    ("Allow read-only viewers (broadcast)", "Zuschauende ohne Schreibrecht erlauben (Übertragung)"),
+    ("Host session", "Sitzung starten"),
+    ("Hosting failed: {0}", "Starten fehlgeschlagen: {0}"),
+    ("participant or viewer link", "Teilnehmer- oder Zuschauerlink"),
+    (
+        "Join with the participant or viewer link the host shows",
+        "Zum Beitreten wird der Teilnehmer- oder Zuschauerlink des Hosts benötigt",
+    ),
+    ("Join", "Beitreten"),
+    ("Joining failed: {0}", "Beitreten fehlgeschlagen: {0}"),
+    ("Session ended: {0}", "Sitzung beendet: {0}"),
+    ("Hosting on {0}", "Sitzung auf {0}"),
+    ("Joined {0}", "Verbunden mit {0}"),
+    ("Viewing {0} (read-only)", "Zuschauen bei {0} (nur lesen)"),
+    ("Annotations by author", "Anmerkungen nach Person"),
+    ("(unknown)", "(unbekannt)"),
+    ("Export", "Exportieren"),
+    ("Delete", "Löschen"),
+    // Files and warnings
+    ("Saved {0} items to {1}", "{0} Elemente in {1} gespeichert"),
+    ("Saving failed: {0}", "Speichern fehlgeschlagen: {0}"),
+    ("The job failed unexpectedly", "Der Vorgang ist unerwartet fehlgeschlagen"),
+    ("Exported {0} items to {1}", "{0} Elemente nach {1} exportiert"),
+    ("Export failed: {0}", "Export fehlgeschlagen: {0}"),
+    ("Board is over budget: {0} items, {1} points", "Die Tafel überschreitet die Grenzen: {0} Elemente, {1} Punkte"),
+    (" (simplified down to {0} points)", " (auf {0} Punkte vereinfacht)"),
+    ("{0} · {1} items · {2} points · UI {3}%", "{0} · {1} Elemente · {2} Punkte · UI {3} %"),
+    // Help overlay and actions
+    ("Keyboard shortcuts", "Tastenkürzel"),
+    ("Paste text as a text box", "Text als Textfeld einfügen"),
+    ("Resize pen or eraser", "Stift oder Radierer anpassen"),
+    ("Scroll wheel", "Mausrad"),
+    ("Keys can be remapped in the \"keymap\" list of the settings file.", "Tasten lassen sich in der Liste „keymap“ der Einstellungsdatei ändern."),
+    ("Switch to the previous tool", "Zum vorherigen Werkzeug wechseln"),
+    ("{0} while held", "{0}, solange gedrückt"),
+    ("Close the polygon", "Polygon schließen"),
+    ("Next palette color", "Nächste Farbe"),
+    ("Previous palette color", "Vorherige Farbe"),
+    ("Palette color {0}", "Farbe {0}"),
+    ("Shrink pen, eraser, or stamp", "Stift, Radierer oder Stempel verkleinern"),
+    ("Grow pen, eraser, or stamp", "Stift, Radierer oder Stempel vergrößern"),
+    ("Show an auto-hidden toolbar", "Ausgeblendete Leiste zeigen"),
+    ("Enlarge the UI", "UI vergrößern"),
+    ("Shrink the UI", "UI verkleinern"),
+    ("Reset the UI scale", "UI-Skalierung zurücksetzen"),
+    ("Show this help", "Diese Hilfe zeigen"),
+    ("Performance HUD", "Leistungsanzeige"),
+    ("Cancel the current stroke or polygon", "Aktuelle Linie oder Polygon verwerfen"),
+    ("Escape minimizes the overlay", "Escape minimiert das Overlay"),
+    ("Once there is no stroke or polygon left to cancel; Ctrl+Q quits", "Sobald keine Linie und kein Polygon mehr zu verwerfen ist; Strg+Q beendet"),
+    ("Quit", "Beenden"),
+    // Screen reader names
+    ("Move toolbar", "Werkzeugleiste verschieben"),
+    ("Tool: {0}", "Werkzeug: {0}"),
+    // Themes
+    ("Theme", "Design"),
+    ("Dark", "Dunkel"),
+    ("Light", "Hell"),
+    ("High contrast", "Hoher Kontrast"),
+    // Toasts
+    ("UI scale {0}%", "UI-Skalierung {0} %"),
+    ("Someone joined the session", "Jemand ist der Sitzung beigetreten"),
+    ("Someone left the session", "Jemand hat die Sitzung verlassen"),
+    // Timers
+    ("Timers", "Timer"),
+    ("Countdown", "Countdown"),
+    ("Start", "Start"),
+    ("Pause", "Pause"),
+    ("Reset", "Zurücksetzen"),
+    ("Show countdown", "Countdown anzeigen"),
+    ("Stopwatch", "Stoppuhr"),
+    ("Stop", "Stopp"),
+    ("Lap", "Runde"),
+    ("Show stopwatch", "Stoppuhr anzeigen"),
+    ("Lap {0}: {1}", "Runde {0}: {1}"),
+    ("Lap {0}: {1} (+{2})", "Runde {0}: {1} (+{2})"),
+    ("Start or stop the stopwatch", "Stoppuhr starten oder anhalten"),
+    ("Mark a stopwatch lap", "Runde auf der Stoppuhr markieren"),
+    ("Break", "Pausenbildschirm"),
+    ("Start break", "Pause beginnen"),
+    ("End break", "Pause beenden"),
+    ("We'll be right back", "Gleich geht es weiter"),
+    ("The break is over", "Die Pause ist vorbei"),
+    ("Start or end a break", "Pause beginnen oder beenden"),
+    ("Ticker", "Laufschrift"),
+    ("Announcement to scroll across the screen", "Ankündigung, die über den Bildschirm läuft"),
+    ("Speed", "Geschwindigkeit"),
+    ("Top", "Oben"),
+    ("Bottom", "Unten"),
+    ("Spotlight", "Spotlight"),
+    ("Spotlight size", "Spotlight-Größe"),
+    ("Spotlight following the pointer", "Spotlight, der dem Zeiger folgt"),
+    ("Shrink the spotlight", "Spotlight verkleinern"),
+    ("Grow the spotlight", "Spotlight vergrößern"),
+    ("Add curtain", "Vorhang hinzufügen"),
+    ("An opaque panel to cover part of the screen", "Eine undurchsichtige Fläche, die einen Teil des Bildschirms verdeckt"),
+    ("Remove curtains", "Vorhänge entfernen"),
+    ("Remove curtain", "Vorhang entfernen"),
+    ("Reveal next", "Nächsten aufdecken"),
+    ("Cover all", "Alle zudecken"),
+    ("Reveal the next curtain", "Nächsten Vorhang aufdecken"),
+    ("Cover the last revealed curtain again", "Zuletzt aufgedeckten Vorhang wieder zuziehen"),
+    ("Hide or show all annotations", "Alle Anmerkungen aus- oder einblenden"),
+    ("Grid", "Raster"),
+    ("Show the grid and snap to it", "Raster anzeigen und daran ausrichten"),
+    ("Annotations hidden", "Anmerkungen ausgeblendet"),
+    ("Annotations shown", "Anmerkungen eingeblendet"),
+    ("Pointer trail", "Zeigerspur"),
+    ("Trail length", "Länge der Spur"),
+    ("Fade out after", "Ausblenden nach"),
+    ("Items drawn with this tool remove themselves after a while", "Mit diesem Werkzeug gezeichnete Elemente verschwinden nach einer Weile von selbst"),
+    ("Watermark", "Wasserzeichen"),
+    ("Attribution shown over everything", "Quellenangabe über allem anderen"),
+    ("Clock", "Uhr"),
+    ("Corner", "Ecke"),
+    ("Top left", "Oben links"),
+    ("Top right", "Oben rechts"),
+    ("Bottom left", "Unten links"),
+    ("Bottom right", "Unten rechts"),
+    ("24-hour time", "24-Stunden-Format"),
+    ("Show seconds", "Sekunden anzeigen"),
+    ("Opacity", "Deckkraft"),
+];
+
+const SPANISH: &[(&str, &str)] = &[
+    // Toolbar
+    ("Drag toolbar", "Mover barra"),
+    ("Drop it near a screen edge to dock it there", "Suéltala cerca de un borde de la pantalla para acoplarla allí"),
+    ("Mouse", "Ratón"),
+    ("Pen", "Lápiz"),
+    ("Polygon", "Polígono"),
+    ("Text", "Texto"),
+    ("Eraser", "Borrador"),
+    ("Presenter", "Presentación"),
+    ("Presenter aids", "Ayudas de presentación"),
+    ("Session", "Sesión"),
+    ("Settings", "Ajustes"),
+    ("Undo", "Deshacer"),
+    ("Redo", "Rehacer"),
+    ("Undo pen stroke", "Deshacer trazo"),
+    ("Undo polygon", "Deshacer polígono"),
+    ("Undo text “{0}”", "Deshacer texto «{0}»"),
+    ("Redo pen stroke", "Rehacer trazo"),
+    ("Redo polygon", "Rehacer polígono"),
+    ("Redo text “{0}”", "Rehacer texto «{0}»"),
+    ("Undo ellipse", "Deshacer elipse"),
+    ("Redo ellipse", "Rehacer elipse"),
+    ("Ellipse", "Elipse"),
+    ("Undo line", "Deshacer línea"),
+    ("Redo line", "Rehacer línea"),
+    ("Line", "Línea"),
+    ("Rectangular spotlight", "Foco rectangular"),
+    ("Marker", "Marcador"),
+    ("Undo marker {0}", "Deshacer marcador {0}"),
+    ("Redo marker {0}", "Rehacer marcador {0}"),
+    ("Next: {0}", "Siguiente: {0}"),
+    ("Restart at 1", "Volver a empezar en 1"),
+    ("Stamp", "Sello"),
+    ("Undo stamp", "Deshacer sello"),
+    ("Redo stamp", "Rehacer sello"),
+    ("Undo callout", "Deshacer bocadillo"),
+    ("Redo callout", "Rehacer bocadillo"),
+    ("Undo adding {0} items", "Deshacer la adición de {0} elementos"),
+    ("Redo adding {0} items", "Rehacer la adición de {0} elementos"),
+    ("Undo erase", "Deshacer borrado"),
+    ("Redo erase", "Rehacer borrado"),
+    ("Undo edit", "Deshacer edición"),
+    ("Redo edit", "Rehacer edición"),
+    ("Callout", "Bocadillo"),
+    (
+        "Drag from what it points at to where the bubble goes",
+        "Arrastra desde lo que señala hasta donde va el bocadillo",
+    ),
+    ("Check mark", "Marca de verificación"),
+    ("Cross", "Cruz"),
+    ("Star", "Estrella"),
+    ("Smiley", "Cara sonriente"),
+    ("Stamp {0} px", "Sello {0} px"),
+    ("Ruler", "Regla"),
+    ("Select", "Seleccionar"),
+    ("Click an item to select it, or drag a box around several; Shift adds to the selection", "Haz clic en un elemento o arrastra un recuadro alrededor de varios; Mayús añade a la selección"),
+    (" · {0} selected, {1} × {2}", " · {0} seleccionados, {1} × {2}"),
+    ("{0} selected", "{0} seleccionados"),
+    ("Apply current color", "Aplicar el color actual"),
+    ("Recolor the selected items", "Cambiar el color de los elementos seleccionados"),
+    ("Delete the selected items", "Eliminar los elementos seleccionados"),
+    ("Duplicate", "Duplicar"),
+    ("Align", "Alinear"),
+    ("Align left edges", "Alinear bordes izquierdos"),
+    ("Align horizontal centers", "Centrar horizontalmente"),
+    ("Align right edges", "Alinear bordes derechos"),
+    ("Align top edges", "Alinear bordes superiores"),
+    ("Align vertical centers", "Centrar verticalmente"),
+    ("Align bottom edges", "Alinear bordes inferiores"),
+    ("Distribute horizontally", "Distribuir horizontalmente"),
+    ("Distribute vertically", "Distribuir verticalmente"),
+    ("Duplicate the selected items", "Duplicar los elementos seleccionados"),
+    ("Screen DPI:", "DPI de la pantalla:"),
+    ("Wrap at:", "Ajustar a:"),
+    ("Background", "Fondo"),
+    ("Background of the selected text boxes", "Fondo de los cuadros de texto seleccionados"),
+    ("Padding:", "Margen interior:"),
+    ("Corner radius", "Radio de las esquinas"),
+    (
+        "Width new text boxes wrap their lines at; 0 breaks lines only at newlines",
+        "Ancho al que los nuevos cuadros de texto ajustan sus líneas; 0 solo corta en saltos de línea",
+    ),
+    ("Set to show distances in centimetres as well; 0 for pixels only", "Indícalo para ver también las distancias en centímetros; 0 solo píxeles"),
+    ("{0} px · {1} cm", "{0} px · {1} cm"),
+    ("{0} px", "{0} px"),
+    ("Nothing to undo", "Nada que deshacer"),
+    ("Nothing to redo", "Nada que rehacer"),
+    ("Clear", "Borrar todo"),
+    ("Save", "Guardar"),
+    ("Save to openpen-board.json", "Guardar en openpen-board.json"),
+    ("Keyboard shortcuts: {0}", "Atajos de teclado: {0}"),
+    // Pen and presenter menus
+    ("Smoothing", "Suavizado"),
+    ("Predict ahead", "Anticipar trazo"),
+    ("Extend the line along the pen's motion to hide input lag", "Prolonga la línea en la dirección del lápiz para ocultar el retardo de entrada"),
+    ("Cursor highlight", "Resaltar puntero"),
+    ("Click ripples", "Ondas al hacer clic"),
+    ("Color:", "Color:"),
+    ("Size", "Tamaño"),
+    ("Show keystrokes", "Mostrar teclas pulsadas"),
+    ("Include keys typed into other apps", "Incluir teclas pulsadas en otras aplicaciones"),
+    ("Reads the keyboard system-wide, so it also shows passwords typed while it is on", "Lee el teclado de todo el sistema, por lo que también muestra las contraseñas escritas mientras está activo"),
+    ("No X11 display to follow the pointer on: {0}", "No hay pantalla X11 en la que seguir el puntero: {0}"),
+    ("This system does not let OpenPen follow the pointer outside its window", "Este sistema no permite a OpenPen seguir el puntero fuera de su ventana"),
+    // Settings menu
+    ("Language", "Idioma"),
+    ("Click-through overlay in Mouse mode", "Superposición transparente a clics en modo ratón"),
+    ("Moves the toolbar into its own window and lets clicks reach the apps below while no drawing tool is active", "Mueve la barra a su propia ventana y deja pasar los clics a las aplicaciones de debajo mientras no haya una herramienta de dibujo activa"),
+    ("Toolbar layout", "Disposición de la barra"),
+    ("Auto", "Automática"),
+    ("Horizontal", "Horizontal"),
+    ("Vertical", "Vertical"),
+    ("Auto stacks the tools in a column while docked to the left or right edge", "Automática apila las herramientas en una columna mientras la barra está acoplada a la izquierda o a la derecha"),
+    ("UI scale", "Escala de la interfaz"),
+    ("Status line", "Línea de estado"),
+    ("Pointer coordinates and item and point counts", "Coordenadas del puntero y número de elementos y puntos"),
+    ("Left-handed layout", "Modo zurdo"),
+    ("Mirror the toolbar and open its menus to the left", "Refleja la barra y abre sus menús hacia la izquierda"),
+    ("Compact toolbar (icons only)", "Barra compacta (solo iconos)"),
+    ("Auto-hide toolbar", "Ocultar la barra automáticamente"),
+    ("Collapse the toolbar to its handle when idle; hover it or press T to bring it back", "Pliega la barra a su asa cuando no se usa; pasa el puntero por encima o pulsa T para recuperarla"),
+    ("Stroke simplification (px)", "Simplificación de trazos (px)"),
+    ("Drops pointer samples that deviate less than this from the simplified stroke; 0 keeps every sample", "Descarta los puntos que se desvían menos que esto del trazo simplificado; 0 conserva todos"),
+    ("Undo steps:", "Pasos para deshacer:"),
+    ("How many steps are kept for Undo; the oldest go first", "Cuántos pasos se guardan para deshacer; los más antiguos se descartan primero"),
+    ("Grid spacing:", "Separación de la cuadrícula:"),
+    ("Soft limits:", "Límites orientativos:"),
+    (" items", " elementos"),
+    (" points", " puntos"),
+    ("Simplify your lines when over the point limit", "Simplificar tus líneas al superar el límite de puntos"),
+    ("Phone/tablet drawing pad", "Móvil/tableta como superficie de dibujo"),
+    ("Open {0} on a device on this network", "Abre {0} en un dispositivo de esta red"),
+    ("Autosave: {0}", "Autoguardado: {0}"),
+    ("Settings file: {0}", "Archivo de ajustes: {0}"),
+    ("Drawing pad failed: {0}", "Error en la superficie de dibujo: {0}"),
+    // Tool options
+    ("Thickness", "Grosor"),
+    ("Outline", "Contorno"),
+    ("Fill", "Relleno"),
+    ("Split strokes", "Dividir trazos"),
+    ("Erase only the part of a pen stroke under the eraser instead of the whole stroke", "Borra solo la parte del trazo bajo el borrador en lugar del trazo entero"),
+    ("Stylus eraser button", "Botón borrador del lápiz"),
+    ("Erase while the stylus eraser end, barrel button, or right mouse button is held down", "Borra mientras se mantiene pulsado el extremo borrador o el botón lateral del lápiz, o el botón derecho del ratón"),
+    ("Fill color", "Color de relleno"),
+    ("{0} points · Enter closes", "{0} puntos · Intro cierra"),
+    ("Text:", "Texto:"),
+    ("Eraser size", "Tamaño del borrador"),
+    ("Thickness {0} px", "Grosor {0} px"),
+    ("Eraser {0} px", "Borrador {0} px"),
+    // Session menu
+    ("Participants: {0} · Viewers: {1}", "Participantes: {0} · Espectadores: {1}"),
+    ("🔒 Lock canvas to presenter", "🔒 Bloquear el lienzo para el presentador"),
+    ("Temporarily revoke everyone else's drawing rights", "Retira temporalmente el permiso de dibujo a los demás"),
+    ("🔒 The presenter has locked the canvas", "🔒 El presentador ha bloqueado el lienzo"),
+    ("Viewer link: {0}", "Enlace para espectadores: {0}"),
+    ("Participant link: {0}", "Enlace para participantes: {0}"),
+    ("Copy", "Copiar"),
+    ("Leave session", "Salir de la sesión"),
+    ("Name:", "Nombre:"),
+    ("Allow read-only viewers (broadcast)", "Permitir espectadores de solo lectura (emisión)"),
+    ("Host session", "Crear sesión"),
+    ("Hosting failed: {0}", "No se pudo crear la sesión: {0}"),
+    ("participant or viewer link", "enlace de participante o de espectador"),
+    (
+        "Join with the participant or viewer link the host shows",
+        "Para unirse hace falta el enlace de participante o de espectador del anfitrión",
+    ),
+    ("Join", "Unirse"),
+    ("Joining failed: {0}", "No se pudo unir: {0}"),
+    ("Session ended: {0}", "Sesión terminada: {0}"),
+    ("Hosting on {0}", "Sesión en {0}"),
+    ("Joined {0}", "Conectado a {0}"),
+    ("Viewing {0} (read-only)", "Viendo {0} (solo lectura)"),
+    ("Annotations by author", "Anotaciones por autor"),
+    ("(unknown)", "(desconocido)"),
+    ("Export", "Exportar"),
+    ("Delete", "Eliminar"),
+    // Files and warnings
+    ("Saved {0} items to {1}", "{0} elementos guardados en {1}"),
+    ("Saving failed: {0}", "Error al guardar: {0}"),
+    ("The job failed unexpectedly", "La tarea falló inesperadamente"),
+    ("Exported {0} items to {1}", "{0} elementos exportados a {1}"),
+    ("Export failed: {0}", "Error al exportar: {0}"),
+    ("Board is over budget: {0} items, {1} points", "La pizarra supera los límites: {0} elementos, {1} puntos"),
+    (" (simplified down to {0} points)", " (simplificada a {0} puntos)"),
+    ("{0} · {1} items · {2} points · UI {3}%", "{0} · {1} elementos · {2} puntos · UI {3} %"),
+    // Help overlay and actions
+    ("Keyboard shortcuts", "Atajos de teclado"),
+    ("Paste text as a text box", "Pegar texto como cuadro de texto"),
+    ("Resize pen or eraser", "Cambiar el tamaño del lápiz o del borrador"),
+    ("Scroll wheel", "Rueda del ratón"),
+    ("Keys can be remapped in the \"keymap\" list of the settings file.", "Las teclas se pueden reasignar en la lista «keymap» del archivo de ajustes."),
+    ("Switch to the previous tool", "Volver a la herramienta anterior"),
+    ("{0} while held", "{0} mientras se mantiene pulsada"),
+    ("Close the polygon", "Cerrar el polígono"),
+    ("Next palette color", "Color siguiente"),
+    ("Previous palette color", "Color anterior"),
+    ("Palette color {0}", "Color {0}"),
+    ("Shrink pen, eraser, or stamp", "Reducir lápiz, borrador o sello"),
+    ("Grow pen, eraser, or stamp", "Agrandar lápiz, borrador o sello"),
+    ("Show an auto-hidden toolbar", "Mostrar la barra oculta"),
+    ("Enlarge the UI", "Agrandar la interfaz"),
+    ("Shrink the UI", "Reducir la interfaz"),
+    ("Reset the UI scale", "Restablecer la escala"),
+    ("Show this help", "Mostrar esta ayuda"),
+    ("Performance HUD", "Indicador de rendimiento"),
+    ("Cancel the current stroke or polygon", "Descartar el trazo o polígono actual"),
+    ("Escape minimizes the overlay", "Escape minimiza la superposición"),
+    ("Once there is no stroke or polygon left to cancel; Ctrl+Q quits", "Cuando no queda trazo ni polígono que descartar; Ctrl+Q sale"),
+    ("Quit", "Salir"),
+    // Screen reader names
+    ("Move toolbar", "Mover la barra de herramientas"),
+    ("Tool: {0}", "Herramienta: {0}"),
+    // Themes
+    ("Theme", "Tema"),
+    ("Dark", "Oscuro"),
+    ("Light", "Claro"),
+    ("High contrast", "Alto contraste"),
+    // Toasts
+    ("UI scale {0}%", "Escala de la interfaz {0} %"),
+    ("Someone joined the session", "Alguien se ha unido a la sesión"),
+    ("Someone left the session", "Alguien ha salido de la sesión"),
+    // Timers
+    ("Timers", "Temporizadores"),
+    ("Countdown", "Cuenta atrás"),
+    ("Start", "Iniciar"),
+    ("Pause", "Pausar"),
+    ("Reset", "Reiniciar"),
+    ("Show countdown", "Mostrar cuenta atrás"),
+    ("Stopwatch", "Cronómetro"),
+    ("Stop", "Detener"),
+    ("Lap", "Vuelta"),
+    ("Show stopwatch", "Mostrar cronómetro"),
+    ("Lap {0}: {1}", "Vuelta {0}: {1}"),
+    ("Lap {0}: {1} (+{2})", "Vuelta {0}: {1} (+{2})"),
+    ("Start or stop the stopwatch", "Iniciar o detener el cronómetro"),
+    ("Mark a stopwatch lap", "Marcar una vuelta del cronómetro"),
+    ("Break", "Descanso"),
+    ("Start break", "Iniciar descanso"),
+    ("End break", "Terminar descanso"),
+    ("We'll be right back", "Volvemos enseguida"),
+    ("The break is over", "El descanso ha terminado"),
+    ("Start or end a break", "Iniciar o terminar un descanso"),
+    ("Ticker", "Rótulo"),
+    ("Announcement to scroll across the screen", "Anuncio que se desplaza por la pantalla"),
+    ("Speed", "Velocidad"),
+    ("Top", "Arriba"),
+    ("Bottom", "Abajo"),
+    ("Spotlight", "Foco"),
+    ("Spotlight size", "Tamaño del foco"),
+    ("Spotlight following the pointer", "Foco que sigue al puntero"),
+    ("Shrink the spotlight", "Reducir el foco"),
+    ("Grow the spotlight", "Agrandar el foco"),
+    ("Add curtain", "Añadir cortina"),
+    ("An opaque panel to cover part of the screen", "Un panel opaco que tapa parte de la pantalla"),
+    ("Remove curtains", "Quitar cortinas"),
+    ("Remove curtain", "Quitar cortina"),
+    ("Reveal next", "Descubrir siguiente"),
+    ("Cover all", "Cubrir todo"),
+    ("Reveal the next curtain", "Descubrir la siguiente cortina"),
+    ("Cover the last revealed curtain again", "Volver a cubrir la última cortina descubierta"),
+    ("Hide or show all annotations", "Ocultar o mostrar todas las anotaciones"),
+    ("Grid", "Cuadrícula"),
+    ("Show the grid and snap to it", "Mostrar la cuadrícula y ajustar a ella"),
+    ("Annotations hidden", "Anotaciones ocultas"),
+    ("Annotations shown", "Anotaciones visibles"),
+    ("Pointer trail", "Estela del puntero"),
+    ("Trail length", "Longitud de la estela"),
+    ("Fade out after", "Desvanecer tras"),
+    ("Items drawn with this tool remove themselves after a while", "Los elementos dibujados con esta herramienta desaparecen solos al cabo de un rato"),
+    ("Watermark", "Marca de agua"),
+    ("Attribution shown over everything", "Atribución mostrada sobre todo lo demás"),
+    ("Clock", "Reloj"),
+    ("Corner", "Esquina"),
+    ("Top left", "Arriba a la izquierda"),
+    ("Top right", "Arriba a la derecha"),
+    ("Bottom left", "Abajo a la izquierda"),
+    ("Bottom right", "Abajo a la derecha"),
+    ("24-hour time", "Formato de 24 horas"),
+    ("Show seconds", "Mostrar segundos"),
+    ("Opacity", "Opacidad"),
+];
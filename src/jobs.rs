@@ -0,0 +1,108 @@
+//! Background jobs for slow file work, so the overlay (which sits above everything
+//! else on screen) never freezes while a save or export runs.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+
+use eframe::egui;
+use kalam_core::{board, CanvasItem};
+
+use crate::i18n::{tr, trf};
+
+/// Bytes written between progress updates.
+const WRITE_CHUNK: usize = 64 * 1024;
+
+pub struct Job {
+    progress: Arc<AtomicU32>,
+    result: Receiver<String>,
+}
+
+/// Handed to the worker to report how far along it is.
+pub struct Progress {
+    value: Arc<AtomicU32>,
+    ctx: egui::Context,
+}
+
+impl Progress {
+    pub fn set(&self, fraction: f32) {
+        self.value
+            .store(fraction.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+        self.ctx.request_repaint();
+    }
+}
+
+impl Job {
+    /// Runs `work` on its own thread; its return value is the status message to show.
+    pub fn spawn(
+        ctx: &egui::Context,
+        work: impl FnOnce(&Progress) -> String + Send + 'static,
+    ) -> Self {
+        let progress = Arc::new(AtomicU32::new(0.0f32.to_bits()));
+        let (tx, result) = mpsc::channel();
+        let reporter = Progress {
+            value: progress.clone(),
+            ctx: ctx.clone(),
+        };
+        thread::spawn(move || {
+            let _ = tx.send(work(&reporter));
+            reporter.ctx.request_repaint();
+        });
+        Self { progress, result }
+    }
+
+    pub fn progress(&self) -> f32 {
+        f32::from_bits(self.progress.load(Ordering::Relaxed))
+    }
+
+    /// The final status message, once the job has finished.
+    pub fn poll(&self) -> Option<String> {
+        match self.result.try_recv() {
+            Ok(status) => Some(status),
+            Err(TryRecvError::Empty) => None,
+            // The worker panicked before it could report back.
+            Err(TryRecvError::Disconnected) => Some(tr("The job failed unexpectedly").to_string()),
+        }
+    }
+}
+
+/// Writes `items` as a board file at `path`, reporting progress per chunk. The
+/// file is written next to `path` first and renamed over it when complete, so a
+/// failed save leaves the previous file intact. `done` and `failed` are the
+/// untranslated status messages; `done` gets the item count and path as `{0}` and
+/// `{1}`, `failed` the error as `{0}`.
+pub fn write_board(
+    ctx: &egui::Context,
+    path: String,
+    items: Vec<CanvasItem>,
+    done: &'static str,
+    failed: &'static str,
+) -> Job {
+    Job::spawn(ctx, move |progress| {
+        let written = board::to_json(&items)
+            .map_err(std::io::Error::from)
+            .and_then(|json| {
+                let tmp = format!("{path}.tmp");
+                let write = || {
+                    let mut file = File::create(&tmp)?;
+                    let bytes = json.as_bytes();
+                    for (idx, chunk) in bytes.chunks(WRITE_CHUNK).enumerate() {
+                        file.write_all(chunk)?;
+                        progress.set(((idx + 1) * WRITE_CHUNK) as f32 / bytes.len() as f32);
+                    }
+                    file.sync_all()?;
+                    fs::rename(&tmp, &path)
+                };
+                write().inspect_err(|_| {
+                    let _ = fs::remove_file(&tmp);
+                })
+            });
+        match written {
+            Ok(()) => trf(done, &[&items.len(), &path]),
+            Err(e) => trf(failed, &[&e]),
+        }
+    })
+}
@@ -0,0 +1,547 @@
+//! Keyboard shortcuts: every action the keyboard can trigger, and the keys bound to
+//! each. The config file only keeps the actions the user remapped, so bindings added
+//! in later versions still reach existing configs, and the F1 help overlay is
+//! generated from the merged result.
+
+use std::fmt;
+
+use eframe::egui::{self, Key, KeyboardShortcut, Modifiers};
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::{tr, trf};
+use crate::Tool;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    Pen,
+    Polygon,
+    Ellipse,
+    Line,
+    Marker,
+    Stamp,
+    Ruler,
+    Text,
+    Callout,
+    Mouse,
+    Select,
+    Eraser,
+    /// Deletes the items picked with the select tool.
+    DeleteSelection,
+    /// Copies the selected items next to themselves.
+    Duplicate,
+    /// Swaps back to the tool used before the current one.
+    LastTool,
+    Undo,
+    Redo,
+    /// Switches to the tool only while the key is held down.
+    Hold(Tool),
+    ClosePolygon,
+    NextColor,
+    PreviousColor,
+    /// Picks the palette color at this index.
+    PickColor(usize),
+    BrushSmaller,
+    BrushLarger,
+    ShowToolbar,
+    UiScaleUp,
+    UiScaleDown,
+    UiScaleReset,
+    /// Starts or stops the stopwatch.
+    Stopwatch,
+    Lap,
+    /// Starts a break, or ends the one in progress.
+    Break,
+    /// Turns the spotlight that follows the pointer on or off.
+    Spotlight,
+    SpotlightSmaller,
+    SpotlightLarger,
+    /// Lifts the next curtain in order.
+    RevealNext,
+    /// Puts back the curtain lifted last.
+    RevealPrevious,
+    /// Hides every annotation until pressed again.
+    HideAll,
+    /// Shows or hides the grid, and with it snapping.
+    Grid,
+    Help,
+    PerfHud,
+    /// Drops whatever is in progress, or hides the overlay if nothing is.
+    Cancel,
+    Quit,
+}
+
+impl Action {
+    pub fn description(self) -> String {
+        let english = match self {
+            Action::Pen => "Pen",
+            Action::Polygon => "Polygon",
+            Action::Ellipse => "Ellipse",
+            Action::Line => "Line",
+            Action::Marker => "Marker",
+            Action::Stamp => "Stamp",
+            Action::Ruler => "Ruler",
+            Action::Text => "Text",
+            Action::Callout => "Callout",
+            Action::Mouse => "Mouse",
+            Action::Select => "Select",
+            Action::Eraser => "Eraser",
+            Action::DeleteSelection => "Delete the selected items",
+            Action::Duplicate => "Duplicate the selected items",
+            Action::LastTool => "Switch to the previous tool",
+            Action::Undo => "Undo",
+            Action::Redo => "Redo",
+            Action::Hold(tool) => return trf("{0} while held", &[&tr(tool.name())]),
+            Action::ClosePolygon => "Close the polygon",
+            Action::NextColor => "Next palette color",
+            Action::PreviousColor => "Previous palette color",
+            Action::PickColor(idx) => return trf("Palette color {0}", &[&(idx + 1)]),
+            Action::BrushSmaller => "Shrink pen, eraser, or stamp",
+            Action::BrushLarger => "Grow pen, eraser, or stamp",
+            Action::ShowToolbar => "Show an auto-hidden toolbar",
+            Action::UiScaleUp => "Enlarge the UI",
+            Action::UiScaleDown => "Shrink the UI",
+            Action::UiScaleReset => "Reset the UI scale",
+            Action::Stopwatch => "Start or stop the stopwatch",
+            Action::Lap => "Mark a stopwatch lap",
+            Action::Break => "Start or end a break",
+            Action::Spotlight => "Spotlight following the pointer",
+            Action::SpotlightSmaller => "Shrink the spotlight",
+            Action::SpotlightLarger => "Grow the spotlight",
+            Action::RevealNext => "Reveal the next curtain",
+            Action::RevealPrevious => "Cover the last revealed curtain again",
+            Action::HideAll => "Hide or show all annotations",
+            Action::Grid => "Show the grid and snap to it",
+            Action::Help => "Show this help",
+            Action::PerfHud => "Performance HUD",
+            Action::Cancel => "Cancel the current stroke or polygon",
+            Action::Quit => "Quit",
+        };
+        tr(english).to_string()
+    }
+
+    /// Whether holding the key down keeps triggering the action.
+    fn repeats(self) -> bool {
+        matches!(
+            self,
+            Action::BrushSmaller
+                | Action::BrushLarger
+                | Action::SpotlightSmaller
+                | Action::SpotlightLarger
+        )
+    }
+}
+
+/// A key together with the modifiers that have to be held for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct Binding {
+    pub key: Key,
+    pub modifiers: Modifiers,
+}
+
+impl Binding {
+    fn new(modifiers: Modifiers, key: Key) -> Self {
+        Self { key, modifiers }
+    }
+
+    /// Human-readable form using the platform's modifier names.
+    pub fn format(self, ctx: &egui::Context) -> String {
+        ctx.format_shortcut(&KeyboardShortcut::new(self.modifiers, self.key))
+    }
+
+    /// Whether the key would type into a focused text field.
+    fn types_text(self) -> bool {
+        !self.modifiers.command
+            && !self.modifiers.alt
+            && !matches!(
+                self.key,
+                Key::Escape
+                    | Key::Enter
+                    | Key::F1
+                    | Key::F2
+                    | Key::F3
+                    | Key::F4
+                    | Key::F5
+                    | Key::F6
+                    | Key::F7
+                    | Key::F8
+                    | Key::F9
+                    | Key::F10
+                    | Key::F11
+                    | Key::F12
+            )
+    }
+
+    /// How many modifiers the binding asks for; more specific bindings win.
+    fn specificity(self) -> u8 {
+        self.modifiers.shift as u8 + self.modifiers.alt as u8 + self.modifiers.command as u8
+    }
+}
+
+/// `Ctrl+Shift+C` style, as stored in the config file.
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.command {
+            f.write_str("Ctrl+")?;
+        }
+        if self.modifiers.alt {
+            f.write_str("Alt+")?;
+        }
+        if self.modifiers.shift {
+            f.write_str("Shift+")?;
+        }
+        f.write_str(self.key.symbol_or_name())
+    }
+}
+
+impl From<Binding> for String {
+    fn from(binding: Binding) -> Self {
+        binding.to_string()
+    }
+}
+
+impl TryFrom<String> for Binding {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        let mut modifiers = Modifiers::NONE;
+        let mut rest = text.as_str();
+        loop {
+            if let Some(after) = rest.strip_prefix("Ctrl+").or(rest.strip_prefix("Cmd+")) {
+                modifiers = modifiers | Modifiers::COMMAND;
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix("Alt+") {
+                modifiers = modifiers | Modifiers::ALT;
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix("Shift+") {
+                modifiers = modifiers | Modifiers::SHIFT;
+                rest = after;
+            } else {
+                break;
+            }
+        }
+        let key = Key::from_name(rest).ok_or_else(|| format!("unknown key in {text:?}"))?;
+        Ok(Self::new(modifiers, key))
+    }
+}
+
+/// The default bindings merged with the user's overrides. Only the overrides are saved.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(into = "Stored", from = "Stored")]
+pub struct Keymap {
+    /// Actions the user remapped; each one's keys replace all of its default keys.
+    overrides: Vec<(Action, Vec<Binding>)>,
+    bindings: Vec<(Action, Binding)>,
+}
+
+/// The `keymap` entry of the config file.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Stored {
+    Overrides(Vec<(Action, Vec<Binding>)>),
+    /// The full binding list older versions saved.
+    Legacy(Vec<(Action, Binding)>),
+}
+
+/// Default bindings of earlier versions, dropped when reading a legacy list.
+const RETIRED_DEFAULTS: [(Action, Key); 1] = [(Action::Quit, Key::Escape)];
+
+impl From<Keymap> for Stored {
+    fn from(keymap: Keymap) -> Self {
+        Stored::Overrides(keymap.overrides)
+    }
+}
+
+impl From<Stored> for Keymap {
+    fn from(stored: Stored) -> Self {
+        match stored {
+            Stored::Overrides(overrides) => Self::with_overrides(overrides),
+            Stored::Legacy(bindings) => {
+                // Only actions that gained keys the defaults never had count as remapped.
+                let defaults = default_bindings();
+                let bindings: Vec<_> = bindings
+                    .into_iter()
+                    .filter(|(action, binding)| {
+                        binding.modifiers != Modifiers::NONE
+                            || !RETIRED_DEFAULTS.contains(&(*action, binding.key))
+                    })
+                    .collect();
+                let mut overrides: Vec<(Action, Vec<Binding>)> = Vec::new();
+                for (action, binding) in &bindings {
+                    if defaults.contains(&(*action, *binding))
+                        || overrides.iter().any(|(known, _)| known == action)
+                    {
+                        continue;
+                    }
+                    let keys = bindings
+                        .iter()
+                        .filter(|(known, _)| known == action)
+                        .map(|(_, binding)| *binding)
+                        .collect();
+                    overrides.push((*action, keys));
+                }
+                Self::with_overrides(overrides)
+            }
+        }
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::with_overrides(Vec::new())
+    }
+}
+
+fn default_bindings() -> Vec<(Action, Binding)> {
+    let none = Modifiers::NONE;
+    let mut bindings = vec![
+        (Action::Pen, Binding::new(none, Key::Num1)),
+        (Action::Polygon, Binding::new(none, Key::Num2)),
+        (Action::Polygon, Binding::new(none, Key::F2)),
+        (Action::Text, Binding::new(none, Key::Num3)),
+        (Action::Text, Binding::new(none, Key::F3)),
+        (Action::Mouse, Binding::new(none, Key::Num4)),
+        (Action::Mouse, Binding::new(none, Key::F4)),
+        (Action::Eraser, Binding::new(none, Key::Num5)),
+        (Action::Eraser, Binding::new(none, Key::F5)),
+        (Action::Ellipse, Binding::new(none, Key::Num6)),
+        (Action::Line, Binding::new(none, Key::Num7)),
+        (Action::Marker, Binding::new(none, Key::Num8)),
+        (Action::Stamp, Binding::new(none, Key::Num9)),
+        (Action::Ruler, Binding::new(none, Key::Num0)),
+        (Action::Callout, Binding::new(none, Key::K)),
+        (Action::Select, Binding::new(none, Key::V)),
+        (Action::DeleteSelection, Binding::new(none, Key::Delete)),
+        (Action::DeleteSelection, Binding::new(none, Key::Backspace)),
+        (Action::Duplicate, Binding::new(Modifiers::COMMAND, Key::D)),
+        (Action::LastTool, Binding::new(none, Key::X)),
+        (Action::Undo, Binding::new(Modifiers::COMMAND, Key::Z)),
+        (
+            Action::Redo,
+            Binding::new(Modifiers::COMMAND | Modifiers::SHIFT, Key::Z),
+        ),
+        (Action::Redo, Binding::new(Modifiers::COMMAND, Key::Y)),
+        (Action::Hold(Tool::Eraser), Binding::new(none, Key::E)),
+        (Action::ClosePolygon, Binding::new(none, Key::Enter)),
+        (Action::NextColor, Binding::new(none, Key::C)),
+        (
+            Action::PreviousColor,
+            Binding::new(Modifiers::SHIFT, Key::C),
+        ),
+    ];
+    let digits = [
+        Key::Num1,
+        Key::Num2,
+        Key::Num3,
+        Key::Num4,
+        Key::Num5,
+        Key::Num6,
+    ];
+    for (idx, key) in digits.into_iter().enumerate() {
+        bindings.push((Action::PickColor(idx), Binding::new(Modifiers::SHIFT, key)));
+    }
+    bindings.extend([
+        (Action::BrushSmaller, Binding::new(none, Key::OpenBracket)),
+        (Action::BrushLarger, Binding::new(none, Key::CloseBracket)),
+        (Action::ShowToolbar, Binding::new(none, Key::T)),
+        (
+            Action::UiScaleUp,
+            Binding::new(Modifiers::COMMAND, Key::Plus),
+        ),
+        (
+            Action::UiScaleUp,
+            Binding::new(Modifiers::COMMAND, Key::Equals),
+        ),
+        (
+            Action::UiScaleDown,
+            Binding::new(Modifiers::COMMAND, Key::Minus),
+        ),
+        (
+            Action::UiScaleReset,
+            Binding::new(Modifiers::COMMAND, Key::Num0),
+        ),
+        (Action::Stopwatch, Binding::new(none, Key::F6)),
+        (Action::Lap, Binding::new(none, Key::F7)),
+        (Action::Break, Binding::new(none, Key::F8)),
+        (Action::Spotlight, Binding::new(none, Key::F9)),
+        (Action::SpotlightSmaller, Binding::new(none, Key::Minus)),
+        (Action::SpotlightLarger, Binding::new(none, Key::Plus)),
+        (Action::SpotlightLarger, Binding::new(none, Key::Equals)),
+        (Action::RevealNext, Binding::new(none, Key::R)),
+        // Presenter remotes send Page Down / Page Up for next and previous, and
+        // B or . for their blank-screen button.
+        (Action::RevealNext, Binding::new(none, Key::PageDown)),
+        (Action::RevealPrevious, Binding::new(none, Key::PageUp)),
+        (Action::HideAll, Binding::new(none, Key::B)),
+        (Action::HideAll, Binding::new(none, Key::Period)),
+        (Action::Grid, Binding::new(none, Key::G)),
+        (Action::Help, Binding::new(none, Key::F1)),
+        (Action::PerfHud, Binding::new(none, Key::F12)),
+        (Action::Cancel, Binding::new(none, Key::Escape)),
+        (Action::Quit, Binding::new(Modifiers::COMMAND, Key::Q)),
+    ]);
+    bindings
+}
+
+impl Keymap {
+    /// The defaults with each overridden action's keys swapped in where the action
+    /// first appears. Default bindings of other actions that use a remapped key are
+    /// dropped, so the key does only what the user asked for.
+    fn with_overrides(overrides: Vec<(Action, Vec<Binding>)>) -> Self {
+        let overridden = |action: &Action| {
+            overrides
+                .iter()
+                .find(|(known, _)| known == action)
+                .map(|(_, keys)| keys)
+        };
+        let taken = |binding: &Binding| overrides.iter().any(|(_, keys)| keys.contains(binding));
+        let mut bindings: Vec<(Action, Binding)> = Vec::new();
+        let mut placed: Vec<Action> = Vec::new();
+        for (action, binding) in default_bindings() {
+            match overridden(&action) {
+                Some(keys) if !placed.contains(&action) => {
+                    placed.push(action);
+                    bindings.extend(keys.iter().map(|key| (action, *key)));
+                }
+                Some(_) => {}
+                None if !taken(&binding) => bindings.push((action, binding)),
+                None => {}
+            }
+        }
+        for (action, keys) in &overrides {
+            if !placed.contains(action) {
+                bindings.extend(keys.iter().map(|key| (*action, *key)));
+            }
+        }
+        Self {
+            overrides,
+            bindings,
+        }
+    }
+
+    /// Actions whose keys were pressed this frame, with the key that did it. Keys
+    /// that would type text are skipped while a text field has focus.
+    pub fn triggered(&self, input: &egui::InputState, typing: bool) -> Vec<(Action, Key)> {
+        input
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                egui::Event::Key {
+                    key,
+                    pressed: true,
+                    repeat,
+                    modifiers,
+                    ..
+                } => self
+                    .bindings
+                    .iter()
+                    .filter(|(action, binding)| {
+                        binding.key == *key
+                            && modifiers.matches_logically(binding.modifiers)
+                            && !(typing && binding.types_text())
+                            && (!repeat || action.repeats())
+                    })
+                    // Shift+C is also a logical match for plain C; the closer binding wins.
+                    .max_by_key(|(_, binding)| binding.specificity())
+                    .map(|(action, binding)| (*action, binding.key)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Every action with all of its bindings, in keymap order.
+    pub fn actions(&self) -> Vec<(Action, Vec<Binding>)> {
+        let mut actions: Vec<(Action, Vec<Binding>)> = Vec::new();
+        for (action, binding) in &self.bindings {
+            match actions.iter_mut().find(|(known, _)| known == action) {
+                Some((_, bindings)) => bindings.push(*binding),
+                None => actions.push((*action, vec![*binding])),
+            }
+        }
+        actions
+    }
+
+    /// The first key bound to `action`, for hints next to buttons.
+    pub fn hint(&self, action: Action, ctx: &egui::Context) -> Option<String> {
+        self.bindings
+            .iter()
+            .find(|(known, _)| *known == action)
+            .map(|(_, binding)| binding.format(ctx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(keymap: &Keymap, action: Action) -> Vec<Binding> {
+        keymap
+            .actions()
+            .into_iter()
+            .find(|(known, _)| *known == action)
+            .map_or(Vec::new(), |(_, bindings)| bindings)
+    }
+
+    #[test]
+    fn only_overrides_are_saved_and_they_load_back() {
+        let undo = Binding::new(Modifiers::COMMAND, Key::U);
+        let keymap = Keymap::with_overrides(vec![(Action::Undo, vec![undo])]);
+        let json = serde_json::to_string(&keymap).unwrap();
+        assert_eq!(json, r#"[["Undo",["Ctrl+U"]]]"#);
+        let loaded: Keymap = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, keymap);
+        assert_eq!(keys(&loaded, Action::Undo), [undo]);
+    }
+
+    #[test]
+    fn a_legacy_list_of_defaults_has_no_overrides() {
+        let mut legacy = default_bindings();
+        legacy.push((Action::Quit, Binding::new(Modifiers::NONE, Key::Escape)));
+        let json = serde_json::to_string(&legacy).unwrap();
+        let keymap: Keymap = serde_json::from_str(&json).unwrap();
+        assert_eq!(keymap, Keymap::default());
+        assert_eq!(serde_json::to_string(&keymap).unwrap(), "[]");
+    }
+
+    #[test]
+    fn a_legacy_list_keeps_the_keys_the_user_added() {
+        let added = Binding::new(Modifiers::NONE, Key::P);
+        let mut legacy = default_bindings();
+        legacy.push((Action::Pen, added));
+        let json = serde_json::to_string(&legacy).unwrap();
+        let keymap: Keymap = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            keys(&keymap, Action::Pen),
+            [Binding::new(Modifiers::NONE, Key::Num1), added]
+        );
+    }
+
+    #[test]
+    fn an_override_takes_its_key_from_the_default_action() {
+        // X is the default key for swapping back to the last tool.
+        let x = Binding::new(Modifiers::NONE, Key::X);
+        let keymap = Keymap::with_overrides(vec![(Action::Pen, vec![x])]);
+        assert_eq!(keys(&keymap, Action::Pen), [x]);
+        assert!(keys(&keymap, Action::LastTool).is_empty());
+        // The rest of the defaults are untouched.
+        assert_eq!(
+            keys(&keymap, Action::Eraser),
+            keys(&Keymap::default(), Action::Eraser)
+        );
+    }
+
+    #[test]
+    fn bindings_parse_from_their_config_form() {
+        let redo = Binding::try_from("Ctrl+Shift+Z".to_string()).unwrap();
+        assert_eq!(
+            redo,
+            Binding::new(Modifiers::COMMAND | Modifiers::SHIFT, Key::Z)
+        );
+        assert_eq!(redo.to_string(), "Ctrl+Shift+Z");
+        assert_eq!(
+            Binding::try_from("Cmd+Alt+F9".to_string()).unwrap(),
+            Binding::new(Modifiers::COMMAND | Modifiers::ALT, Key::F9)
+        );
+        assert!(Binding::try_from("Ctrl+Nope".to_string()).is_err());
+    }
+}
@@ -1,5 +1,33 @@
-use eframe::egui::{self, Align2, Color32, FontId, Pos2, RichText, Shape, Stroke, Vec2};
-use serde::{Deserialize, Serialize};
+use chrono::Timelike;
+use eframe::egui::{self, Color32, FontId, Pos2, RichText, Shape, Stroke, Vec2};
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, TryLockError};
+use std::io;
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+
+use kalam_core::crdt::{ItemId, Op};
+use kalam_core::document::{unix_time, Command, Document, Event};
+use kalam_core::journal::{self, Journal};
+
+use config::{Config, Corner, Edge, Orientation, Theme, ToolbarPlacement};
+use global_input::{GlobalEvent, GlobalInput};
+use i18n::{tr, trf};
+use kalam_core::{
+    board, geometry, Callout, CanvasItem, EllipseShape, LineShape, Marker, PolygonShape, Stamp,
+    StampKind, StrokePath, TextBackground, TextBox,
+};
+use keymap::Action;
+
+mod collab;
+mod companion;
+mod config;
+mod global_input;
+mod i18n;
+mod jobs;
+mod keymap;
+mod render;
+mod timers;
 
 fn main() -> eframe::Result<()> {
     let viewport = egui::ViewportBuilder::default()
@@ -12,266 +40,4180 @@ fn main() -> eframe::Result<()> {
 
     let native_options = eframe::NativeOptions {
         viewport,
+        // Pen strokes are custom meshes without feathering; multisampling smooths their edges.
+        multisampling: 4,
+        ..Default::default()
+    };
+
+    eframe::run_native(
+        "OpenPen",
+        native_options,
+        Box::new(|cc| {
+            // Ctrl+/- scales the controls only; egui's own zoom would magnify the board too.
+            cc.egui_ctx.options_mut(|o| o.zoom_with_keyboard = false);
+            Box::<OpenPenApp>::default()
+        }),
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+enum Tool {
+    Mouse,
+    Select,
+    Pen,
+    Polygon,
+    Ellipse,
+    Line,
+    Marker,
+    Stamp,
+    Ruler,
+    Text,
+    Callout,
+    Eraser,
+}
+
+/// The style for `theme` with text, spacing and hit targets enlarged by `scale`.
+fn scaled_style(scale: f32, theme: Theme) -> egui::Style {
+    let mut style = egui::Style {
+        visuals: theme_visuals(theme),
         ..Default::default()
     };
+    for font in style.text_styles.values_mut() {
+        font.size *= scale;
+    }
+    let spacing = &mut style.spacing;
+    spacing.item_spacing *= scale;
+    spacing.window_margin *= scale;
+    spacing.button_padding *= scale;
+    spacing.menu_margin *= scale;
+    spacing.interact_size *= scale;
+    spacing.slider_width *= scale;
+    spacing.combo_width *= scale;
+    spacing.text_edit_width *= scale;
+    spacing.icon_width *= scale;
+    spacing.icon_width_inner *= scale;
+    spacing.icon_spacing *= scale;
+    spacing.tooltip_width *= scale;
+    spacing.menu_width *= scale;
+    style
+}
+
+fn theme_visuals(theme: Theme) -> egui::Visuals {
+    match theme {
+        Theme::Dark => egui::Visuals::dark(),
+        Theme::Light => egui::Visuals::light(),
+        Theme::HighContrast => {
+            // White and yellow on black are both well above WCAG AAA's 7:1.
+            let highlight = Color32::YELLOW;
+            let mut visuals = egui::Visuals::dark();
+            visuals.panel_fill = Color32::BLACK;
+            visuals.window_fill = Color32::BLACK;
+            visuals.extreme_bg_color = Color32::BLACK;
+            visuals.faint_bg_color = Color32::BLACK;
+            visuals.window_stroke = Stroke::new(2.0, Color32::WHITE);
+            visuals.hyperlink_color = highlight;
+            visuals.selection.bg_fill = highlight;
+            visuals.selection.stroke = Stroke::new(2.0, Color32::BLACK);
+            let widgets = &mut visuals.widgets;
+            for (state, border, text) in [
+                (&mut widgets.noninteractive, Color32::WHITE, Color32::WHITE),
+                (&mut widgets.inactive, Color32::WHITE, Color32::WHITE),
+                (&mut widgets.hovered, highlight, highlight),
+                (&mut widgets.open, highlight, highlight),
+            ] {
+                state.bg_fill = Color32::BLACK;
+                state.weak_bg_fill = Color32::BLACK;
+                state.bg_stroke = Stroke::new(2.0, border);
+                state.fg_stroke = Stroke::new(2.0, text);
+            }
+            // egui draws focused widgets with the active style, so this is the focus ring.
+            widgets.active.bg_fill = highlight;
+            widgets.active.weak_bg_fill = highlight;
+            widgets.active.bg_stroke = Stroke::new(4.0, Color32::WHITE);
+            widgets.active.fg_stroke = Stroke::new(2.0, Color32::BLACK);
+            visuals
+        }
+    }
+}
+
+/// A toolbar menu. Unlike egui's menus it can open to the left of its button, and it
+/// stays open while a popup of its own (combo box, color picker) is in use. `name` is
+/// what screen readers call the button, since compact titles are only an icon.
+fn drop_down<R>(
+    ui: &mut egui::Ui,
+    title: impl Into<egui::WidgetText>,
+    name: &str,
+    open_left: bool,
+    add_contents: impl FnOnce(&mut egui::Ui) -> R,
+) -> egui::Response {
+    let id = ui.next_auto_id();
+    let was_open = ui.data(|d| d.get_temp::<bool>(id)).unwrap_or(false);
+    let button = named(
+        ui.add(egui::Button::new(title).selected(was_open)),
+        egui::WidgetType::Button,
+        Some(was_open),
+        name,
+    );
+    let mut open = was_open != button.clicked();
+    if open {
+        let (corner, pivot) = if open_left {
+            (button.rect.right_bottom(), egui::Align2::RIGHT_TOP)
+        } else {
+            (button.rect.left_bottom(), egui::Align2::LEFT_TOP)
+        };
+        egui::Area::new(id.with("menu"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(corner + Vec2::new(0.0, ui.spacing().menu_spacing))
+            .pivot(pivot)
+            .constrain_to(ui.ctx().screen_rect())
+            .show(ui.ctx(), |ui| {
+                egui::Frame::menu(ui.style()).show(ui, |ui| {
+                    ui.set_max_width(ui.spacing().menu_width);
+                    add_contents(ui);
+                });
+            });
+        let pressed_at = ui.input(|i| {
+            i.pointer
+                .any_pressed()
+                .then(|| i.pointer.interact_pos())
+                .flatten()
+        });
+        // Menus and their popups live on the foreground layer; anything else closes it.
+        let pressed_elsewhere = pressed_at.is_some_and(|pos| {
+            !button.rect.contains(pos)
+                && ui
+                    .ctx()
+                    .layer_id_at(pos)
+                    .is_none_or(|layer| layer.order != egui::Order::Foreground)
+        });
+        let close_requested = ui
+            .data_mut(|d| d.remove_temp::<bool>(egui::Id::new(CLOSE_DROP_DOWN)))
+            .unwrap_or(false);
+        open = !pressed_elsewhere && !close_requested;
+    }
+    ui.data_mut(|d| d.insert_temp(id, open));
+    button
+}
+
+/// The screen rectangle `item` covers; `text_size` is its text box's laid-out size.
+fn item_rect(item: &CanvasItem, text_size: Option<[f32; 2]>) -> Option<egui::Rect> {
+    let [min, max] = item.bounds(text_size)?;
+    Some(egui::Rect::from_min_max(
+        Pos2::new(min[0], min[1]),
+        Pos2::new(max[0], max[1]),
+    ))
+}
+
+/// "Undo …" or "Redo …" naming the kind of `item`; text items quote the start of
+/// their first line.
+fn describe_change(item: &CanvasItem, redo: bool) -> String {
+    const MAX_CHARS: usize = 20;
+    let pick =
+        |undo: &'static str, redo_text: &'static str| tr(if redo { redo_text } else { undo });
+    match item {
+        CanvasItem::Stroke(_) => pick("Undo pen stroke", "Redo pen stroke").to_string(),
+        CanvasItem::Polygon(_) => pick("Undo polygon", "Redo polygon").to_string(),
+        CanvasItem::Ellipse(_) => pick("Undo ellipse", "Redo ellipse").to_string(),
+        CanvasItem::Line(_) => pick("Undo line", "Redo line").to_string(),
+        CanvasItem::Stamp(_) => pick("Undo stamp", "Redo stamp").to_string(),
+        CanvasItem::Callout(_) => pick("Undo callout", "Redo callout").to_string(),
+        CanvasItem::Marker(marker) => trf(
+            if redo {
+                "Redo marker {0}"
+            } else {
+                "Undo marker {0}"
+            },
+            &[&marker.number],
+        ),
+        CanvasItem::Text(text) => {
+            let line = text.text.lines().next().unwrap_or_default().trim();
+            let mut excerpt: String = line.chars().take(MAX_CHARS).collect();
+            if line.chars().count() > MAX_CHARS || text.text.trim().lines().count() > 1 {
+                excerpt.push('…');
+            }
+            trf(pick("Undo text “{0}”", "Redo text “{0}”"), &[&excerpt])
+        }
+    }
+}
+
+/// Replaces the name screen readers announce for a control, for controls whose visible
+/// text is an icon or nothing at all.
+fn named(
+    response: egui::Response,
+    typ: egui::WidgetType,
+    selected: Option<bool>,
+    name: &str,
+) -> egui::Response {
+    response.widget_info(|| match selected {
+        Some(selected) => egui::WidgetInfo::selected(typ, selected, name),
+        None => egui::WidgetInfo::labeled(typ, name),
+    });
+    response
+}
+
+const CLOSE_DROP_DOWN: &str = "close_drop_down";
+
+/// Closes the [`drop_down`] whose contents are being shown.
+fn close_drop_down(ui: &egui::Ui) {
+    ui.data_mut(|d| d.insert_temp(egui::Id::new(CLOSE_DROP_DOWN), true));
+}
+
+impl Tool {
+    /// English name, for [`tr`].
+    fn name(self) -> &'static str {
+        match self {
+            Tool::Mouse => "Mouse",
+            Tool::Select => "Select",
+            Tool::Pen => "Pen",
+            Tool::Polygon => "Polygon",
+            Tool::Ellipse => "Ellipse",
+            Tool::Line => "Line",
+            Tool::Marker => "Marker",
+            Tool::Stamp => "Stamp",
+            Tool::Ruler => "Ruler",
+            Tool::Text => "Text",
+            Tool::Callout => "Callout",
+            Tool::Eraser => "Eraser",
+        }
+    }
+
+    /// The tool that makes items like `item`, whose fade-out setting they follow.
+    fn for_item(item: &CanvasItem) -> Tool {
+        match item {
+            CanvasItem::Stroke(_) => Tool::Pen,
+            CanvasItem::Polygon(_) => Tool::Polygon,
+            CanvasItem::Text(_) => Tool::Text,
+            CanvasItem::Ellipse(_) => Tool::Ellipse,
+            CanvasItem::Line(_) => Tool::Line,
+            CanvasItem::Marker(_) => Tool::Marker,
+            CanvasItem::Stamp(_) => Tool::Stamp,
+            CanvasItem::Callout(_) => Tool::Callout,
+        }
+    }
+
+    fn cursor(self) -> egui::CursorIcon {
+        match self {
+            Tool::Mouse => egui::CursorIcon::Default,
+            Tool::Select => egui::CursorIcon::PointingHand,
+            Tool::Polygon
+            | Tool::Ellipse
+            | Tool::Line
+            | Tool::Marker
+            | Tool::Stamp
+            | Tool::Ruler
+            | Tool::Callout => egui::CursorIcon::Crosshair,
+            Tool::Text => egui::CursorIcon::Text,
+            // Drawn on the canvas instead: a nib for the pen, the erase radius for the eraser.
+            Tool::Pen | Tool::Eraser => egui::CursorIcon::None,
+        }
+    }
+}
+
+/// The tool buttons in toolbar order, with their icons and the actions that pick them.
+const TOOL_BUTTONS: [(Tool, &str, Action); 12] = [
+    (Tool::Mouse, "🖱", Action::Mouse),
+    (Tool::Select, "☝", Action::Select),
+    (Tool::Pen, "✏", Action::Pen),
+    (Tool::Polygon, "⬠", Action::Polygon),
+    (Tool::Ellipse, "⭕", Action::Ellipse),
+    (Tool::Line, "➖", Action::Line),
+    (Tool::Marker, "#", Action::Marker),
+    (Tool::Stamp, "✔", Action::Stamp),
+    (Tool::Ruler, "📏", Action::Ruler),
+    (Tool::Text, "🔤", Action::Text),
+    (Tool::Callout, "💬", Action::Callout),
+    (Tool::Eraser, "🧽", Action::Eraser),
+];
+
+/// Icon and English name of a stamp, for the picker.
+fn stamp_label(kind: StampKind) -> (&'static str, &'static str) {
+    match kind {
+        StampKind::Check => ("✔", "Check mark"),
+        StampKind::Cross => ("✖", "Cross"),
+        StampKind::Star => ("★", "Star"),
+        StampKind::Smiley => ("☺", "Smiley"),
+    }
+}
+
+/// A pen nib with its tip at `tip`, standing in for the system cursor.
+fn paint_nib(painter: &egui::Painter, tip: Pos2, color: Color32) {
+    let nib = vec![
+        tip,
+        tip + Vec2::new(4.0, -12.0),
+        tip + Vec2::new(12.0, -4.0),
+    ];
+    painter.add(Shape::convex_polygon(
+        nib,
+        color,
+        Stroke::new(1.5, Color32::BLACK),
+    ));
+}
+
+fn color_from_rgba(rgba: [u8; 4]) -> Color32 {
+    Color32::from_rgba_premultiplied(rgba[0], rgba[1], rgba[2], rgba[3])
+}
+
+/// Controls for the color, opacity, padding, and corner radius of a text background.
+/// Returns whether `style` changed, and whether the change is finished rather than
+/// part of a drag still going on.
+fn text_background_editor(ui: &mut egui::Ui, style: &mut TextBackground) -> (bool, bool) {
+    let before = *style;
+    let mut finished = false;
+    ui.horizontal(|ui| {
+        ui.label(tr("Color:"));
+        let [r, g, b] = style.rgb;
+        let mut color = Color32::from_rgb(r, g, b);
+        if egui::color_picker::color_edit_button_srgba(
+            ui,
+            &mut color,
+            egui::color_picker::Alpha::Opaque,
+        )
+        .changed()
+        {
+            style.rgb = [color.r(), color.g(), color.b()];
+            finished = true;
+        }
+    });
+    let opacity = ui.add(egui::Slider::new(&mut style.opacity, 0.0..=1.0).text(tr("Opacity")));
+    ui.horizontal(|ui| {
+        ui.label(tr("Padding:"));
+        for value in &mut style.padding {
+            let padding = ui.add(
+                egui::DragValue::new(value)
+                    .clamp_range(0.0..=40.0)
+                    .speed(0.5)
+                    .suffix(" px"),
+            );
+            finished |= padding.drag_stopped() || (padding.changed() && !padding.dragged());
+        }
+    });
+    let radius =
+        ui.add(egui::Slider::new(&mut style.corner_radius, 0.0..=24.0).text(tr("Corner radius")));
+    for slider in [opacity, radius] {
+        finished |= slider.drag_stopped() || (slider.changed() && !slider.dragged());
+    }
+    (*style != before, finished)
+}
+
+struct OpenPenApp {
+    palette: Vec<Color32>,
+    active_color: usize,
+    thickness: f32,
+    tool: Tool,
+    previous_tool: Tool,
+    /// The key holding a temporary tool down (and the window it was pressed in),
+    /// and the tool to return to.
+    held_tool: Option<(egui::ViewportId, egui::Key, ToolsBeforeHold)>,
+    /// The tools to return to once the stylus eraser lifts off.
+    stylus_eraser: Option<ToolsBeforeHold>,
+    /// An eraser drag is under way, with its undo step open.
+    erasing: bool,
+    drawing: Vec<Pos2>,
+    /// Pen pressure at each point of `drawing`, where the device reports it.
+    drawing_pressure: Vec<Option<f32>>,
+    /// Latest pressure reported by a pen still touching the screen.
+    pen_force: Option<f32>,
+    polygon_points: Vec<Pos2>,
+    /// Press point and current pointer of a shape being dragged out.
+    shape_drag: Option<(Pos2, Pos2)>,
+    /// Number the marker tool places next.
+    next_marker: u32,
+    stamp_kind: StampKind,
+    stamp_size: f32,
+    /// Ends of the last distance measured with the ruler.
+    measurement: Option<(Pos2, Pos2)>,
+    /// Selected items, in the order they were picked.
+    selection: Vec<ItemId>,
+    selection_drag: Option<SelectionDrag>,
+    /// Corners of the rubber band being dragged out to select items.
+    band_drag: Option<(Pos2, Pos2)>,
+    /// Background picked for the selected text boxes, applied once the pointer is let go.
+    selection_background: Option<TextBackground>,
+    text_editor: Option<TextEditor>,
+    items: Document,
+    shape_cache: render::ShapeCache,
+    journal: Option<Journal>,
+    /// Held while this instance owns its journal, so no other one opens the same file.
+    journal_lock: Option<File>,
+    journal_events: Receiver<Event>,
+    journal_error: Option<String>,
+    live_stroke: render::LiveStroke,
+    eraser_size: f32,
+    brush_scroll: f32,
+    brush_resized_at: Option<f64>,
+    predict_strokes: bool,
+    text_draft: String,
+    cursor_highlight: bool,
+    click_ripples: bool,
+    highlight_color: Color32,
+    highlight_radius: f32,
+    ripples: Vec<(Pos2, f64)>,
+    pointer_trail: bool,
+    /// How long a trail point stays visible, in seconds.
+    trail_length: f32,
+    trail_color: Color32,
+    trail: Vec<(Pos2, f64)>,
+    /// Seconds after which new items of the kind a tool makes remove themselves, for
+    /// tools set to fade.
+    fade_after: HashMap<Tool, f32>,
+    show_keystrokes: bool,
+    show_perf_hud: bool,
+    show_help: bool,
+    countdown: timers::Countdown,
+    show_countdown: bool,
+    stopwatch: timers::Stopwatch,
+    show_stopwatch: bool,
+    /// Time left on the break, while the break screen hides the board.
+    on_break: Option<timers::Countdown>,
+    show_ticker: bool,
+    spotlight: bool,
+    spotlight_radius: f32,
+    /// Lights a rectangle instead of a circle, `spotlight_radius` high.
+    spotlight_rect: bool,
+    /// In reveal order.
+    curtains: Vec<Curtain>,
+    /// Board items are not drawn, as if blanked by a presenter remote.
+    hide_all: bool,
+    points: PointTally,
+    over_budget: bool,
+    budget_warning: Option<(String, f64)>,
+    keystrokes: Vec<(String, f64)>,
+    idle_passthrough: bool,
+    passthrough_active: bool,
+    /// Follows the pointer outside the overlay for the presenter aids while the
+    /// overlay lets clicks through and so sees no pointer of its own, and keys typed
+    /// into other apps when the keystroke display is set to show them.
+    global_input: Option<GlobalInput>,
+    global_input_error: Option<String>,
+    /// What `global_input` saw this frame.
+    global_events: Vec<GlobalEvent>,
+    toolbar_pos: Option<Pos2>,
+    toolbar_size: Vec2,
+    toolbar_rect: egui::Rect,
+    toolbar_dragging: bool,
+    toolbar_last_used: f64,
+    config: Config,
+    applied_style: Option<(f32, Theme)>,
+    config_error: Option<String>,
+    companion: Option<companion::CompanionServer>,
+    companion_error: Option<String>,
+    session: Option<collab::Session>,
+    session_addr: String,
+    broadcast: bool,
+    display_name: String,
+    remote_cursors: HashMap<usize, RemoteCursor>,
+    last_cursor_sent: (f64, Option<[f32; 2]>),
+    hidden_authors: HashSet<String>,
+    /// Short confirmations and their start times, oldest first.
+    toasts: Vec<(String, f64)>,
+    save_job: Option<jobs::Job>,
+    export_job: Option<jobs::Job>,
+    session_error: Option<String>,
+    /// A join still connecting in the background.
+    pending_join: Option<collab::PendingJoin>,
+}
+
+const RIPPLE_SECONDS: f64 = 0.45;
+/// Width of the pointer trail at its newest end.
+const TRAIL_WIDTH: f32 = 8.0;
+const DEFAULT_FADE_AFTER: f32 = 10.0;
+/// Fading items turn transparent over their last this many seconds.
+const FADE_OUT_SECONDS: f64 = 1.0;
+const KEYSTROKE_SECONDS: f64 = 2.5;
+const MAX_KEYSTROKES: usize = 6;
+const TOOLBAR_MENU_HEADROOM: f32 = 220.0;
+const TOOLBAR_MARGIN: f32 = 18.0;
+/// How close to a screen edge a dropped toolbar has to be to dock there.
+const DOCK_DISTANCE: f32 = 48.0;
+/// How long an auto-hiding toolbar stays open after the pointer leaves it.
+const TOOLBAR_HIDE_SECONDS: f64 = 3.0;
+const UI_SCALE_STEP: f32 = 0.25;
+const UI_SCALE_RANGE: std::ops::RangeInclusive<f32> = 0.75..=3.0;
+const CURSOR_SEND_INTERVAL: f64 = 0.05;
+const CURSOR_HEARTBEAT: f64 = 1.0;
+const BUDGET_WARNING_SECONDS: f64 = 6.0;
+const TOAST_SECONDS: f64 = 3.0;
+/// The countdown turns amber this long before the end (or at a fifth of its length).
+const COUNTDOWN_WARNING_SECONDS: f64 = 60.0;
+const COUNTDOWN_TEXT_SIZE: f32 = 64.0;
+const CLOCK_TEXT_SIZE: f32 = 28.0;
+const STOPWATCH_TEXT_SIZE: f32 = 48.0;
+const BREAK_DIM_ALPHA: u8 = 220;
+const TICKER_TEXT_SIZE: f32 = 24.0;
+const WATERMARK_TEXT_SIZE: f32 = 20.0;
+const SPOTLIGHT_DIM_ALPHA: u8 = 180;
+const CURTAIN_DEFAULT_SIZE: Vec2 = Vec2::new(360.0, 240.0);
+const CURTAIN_GRAY: u8 = 40;
+/// Size of a curtain's resize grip and close button.
+const CURTAIN_GRIP: f32 = 20.0;
+const SPOTLIGHT_STEP: f32 = 20.0;
+const SPOTLIGHT_RADIUS_RANGE: std::ops::RangeInclusive<f32> = 40.0..=600.0;
+/// Width over height of the rectangular spotlight.
+const SPOTLIGHT_RECT_ASPECT: f32 = 1.6;
+const TICKER_PADDING: f32 = 6.0;
+const TICKER_BACKGROUND_ALPHA: u8 = 170;
+const BREAK_MESSAGE_SIZE: f32 = 40.0;
+const BREAK_COUNTDOWN_SIZE: f32 = 120.0;
+const MAX_LAPS_SHOWN: usize = 5;
+const MAX_TOASTS: usize = 4;
+/// Journals that may be in use at once, one per running instance.
+const JOURNAL_SLOTS: usize = 16;
+/// Tolerance of the clean-up pass run when the board goes over its point budget.
+const BUDGET_SIMPLIFY_EPSILON: f32 = 2.0;
+/// How far ahead, in seconds of pen motion, the live stroke is extrapolated.
+const PREDICTION_SECONDS: f32 = 0.025;
+/// Share of the pen thickness drawn at the lightest pressure.
+const MIN_PRESSURE_WIDTH: f32 = 0.2;
+/// Angle, in degrees, that polygon sides turn in while Ctrl is held.
+const POLYGON_ANGLE_STEP: f32 = 15.0;
+const GRID_SPACING_RANGE: std::ops::RangeInclusive<f32> = 8.0..=400.0;
+const MAX_PREDICTION: f32 = 40.0;
+/// Scroll distance that counts as one wheel notch when resizing the brush.
+const SCROLL_NOTCH: f32 = 50.0;
+const BRUSH_INDICATOR_SECONDS: f64 = 1.0;
+/// How far from an item's outline a click still selects it.
+const SELECT_TOLERANCE: f32 = 8.0;
+/// How far down and right a duplicate lands from its original.
+const DUPLICATE_OFFSET: f32 = 16.0;
+const HANDLE_SIZE: f32 = 8.0;
+/// How far above the selection the rotation grip sits.
+const ROTATE_GRIP_OFFSET: f32 = 24.0;
+/// Smallest factor one scaling drag can shrink the selection by.
+const MIN_SCALE: f32 = 0.05;
+const RULER_TICK: f32 = 8.0;
+const RULER_TEXT_SIZE: f32 = 14.0;
+const STAMP_SIZE_RANGE: std::ops::RangeInclusive<f32> = 16.0..=160.0;
+
+/// A change to the selection being dragged out with the select tool.
+#[derive(Clone, Copy)]
+enum SelectionDrag {
+    Move {
+        start: Pos2,
+        end: Pos2,
+    },
+    /// Scales around `pivot` by how much farther from it the pointer has moved.
+    Scale {
+        pivot: Pos2,
+        start: Pos2,
+        end: Pos2,
+    },
+    /// Turns around `pivot` by the angle the pointer has swept around it.
+    Rotate {
+        pivot: Pos2,
+        start: Pos2,
+        end: Pos2,
+    },
+    /// Moves the tip of a callout's tail, leaving its bubble where it is.
+    Tip {
+        start: Pos2,
+        end: Pos2,
+    },
+}
+
+impl SelectionDrag {
+    fn set_end(&mut self, pos: Pos2) {
+        match self {
+            SelectionDrag::Move { end, .. }
+            | SelectionDrag::Scale { end, .. }
+            | SelectionDrag::Rotate { end, .. }
+            | SelectionDrag::Tip { end, .. } => *end = pos,
+        }
+    }
+
+    /// Whether the pointer is still where the drag started.
+    fn is_empty(self) -> bool {
+        match self {
+            SelectionDrag::Move { start, end }
+            | SelectionDrag::Scale { start, end, .. }
+            | SelectionDrag::Rotate { start, end, .. }
+            | SelectionDrag::Tip { start, end } => start == end,
+        }
+    }
+
+    /// Changes `item` the way the drag so far would; `text_size` is its text box's
+    /// laid-out size.
+    fn apply(self, item: &mut CanvasItem, text_size: Option<[f32; 2]>) {
+        match self {
+            SelectionDrag::Move { start, end } => {
+                let offset = end - start;
+                item.translate([offset.x, offset.y]);
+            }
+            SelectionDrag::Scale { pivot, start, end } => {
+                let factor = (end - pivot).length() / (start - pivot).length().max(1.0);
+                item.scale_rotate([pivot.x, pivot.y], factor.max(MIN_SCALE), 0.0, text_size);
+            }
+            SelectionDrag::Rotate { pivot, start, end } => {
+                let angle = (end - pivot).angle() - (start - pivot).angle();
+                item.scale_rotate([pivot.x, pivot.y], 1.0, angle, text_size);
+            }
+            SelectionDrag::Tip { start, end } => {
+                if let CanvasItem::Callout(callout) = item {
+                    callout.tip[0] += end.x - start.x;
+                    callout.tip[1] += end.y - start.y;
+                }
+            }
+        }
+    }
+}
+
+/// A grip around the selection.
+#[derive(Clone, Copy, PartialEq)]
+enum Handle {
+    /// Scales the selection; corners count clockwise from the top left.
+    Corner(usize),
+    Rotate,
+    /// The tip of a lone selected callout's tail.
+    Tip,
+}
+
+/// A way of lining up the selected items, from the Align menu.
+#[derive(Clone, Copy)]
+enum Alignment {
+    Left,
+    Center,
+    Right,
+    Top,
+    Middle,
+    Bottom,
+    /// Spaces the items evenly between the outermost two, which stay put.
+    DistributeHorizontally,
+    DistributeVertically,
+}
+
+impl Alignment {
+    const ALL: [Alignment; 8] = [
+        Alignment::Left,
+        Alignment::Center,
+        Alignment::Right,
+        Alignment::Top,
+        Alignment::Middle,
+        Alignment::Bottom,
+        Alignment::DistributeHorizontally,
+        Alignment::DistributeVertically,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Alignment::Left => "Align left edges",
+            Alignment::Center => "Align horizontal centers",
+            Alignment::Right => "Align right edges",
+            Alignment::Top => "Align top edges",
+            Alignment::Middle => "Align vertical centers",
+            Alignment::Bottom => "Align bottom edges",
+            Alignment::DistributeHorizontally => "Distribute horizontally",
+            Alignment::DistributeVertically => "Distribute vertically",
+        }
+    }
+
+    /// How many items it takes for this to change anything.
+    fn min_items(self) -> usize {
+        match self {
+            Alignment::DistributeHorizontally | Alignment::DistributeVertically => 3,
+            _ => 2,
+        }
+    }
+
+    /// How far to move each item with these bounds, in the same order.
+    fn offsets(self, bounds: &[egui::Rect]) -> Vec<Vec2> {
+        let Some(all) = bounds.iter().copied().reduce(egui::Rect::union) else {
+            return Vec::new();
+        };
+        let along = |f: &dyn Fn(&egui::Rect) -> f32, target: f32, horizontal: bool| {
+            bounds
+                .iter()
+                .map(|rect| {
+                    let delta = target - f(rect);
+                    if horizontal {
+                        Vec2::new(delta, 0.0)
+                    } else {
+                        Vec2::new(0.0, delta)
+                    }
+                })
+                .collect()
+        };
+        match self {
+            Alignment::Left => along(&|r| r.left(), all.left(), true),
+            Alignment::Center => along(&|r| r.center().x, all.center().x, true),
+            Alignment::Right => along(&|r| r.right(), all.right(), true),
+            Alignment::Top => along(&|r| r.top(), all.top(), false),
+            Alignment::Middle => along(&|r| r.center().y, all.center().y, false),
+            Alignment::Bottom => along(&|r| r.bottom(), all.bottom(), false),
+            Alignment::DistributeHorizontally => {
+                let spans: Vec<_> = bounds.iter().map(|r| r.x_range()).collect();
+                distribute(&spans)
+                    .into_iter()
+                    .map(|dx| Vec2::new(dx, 0.0))
+                    .collect()
+            }
+            Alignment::DistributeVertically => {
+                let spans: Vec<_> = bounds.iter().map(|r| r.y_range()).collect();
+                distribute(&spans)
+                    .into_iter()
+                    .map(|dy| Vec2::new(0.0, dy))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Shifts that leave equal gaps between `spans`, kept in the order of their centers,
+/// filling the range they cover together.
+fn distribute(spans: &[egui::Rangef]) -> Vec<f32> {
+    let mut order: Vec<usize> = (0..spans.len()).collect();
+    order.sort_by(|&a, &b| spans[a].center().total_cmp(&spans[b].center()));
+    let min = spans.iter().map(|s| s.min).fold(f32::INFINITY, f32::min);
+    let max = spans
+        .iter()
+        .map(|s| s.max)
+        .fold(f32::NEG_INFINITY, f32::max);
+    let filled: f32 = spans.iter().map(|s| s.span()).sum();
+    let gap = (max - min - filled) / spans.len().saturating_sub(1).max(1) as f32;
+    let mut offsets = vec![0.0; spans.len()];
+    let mut at = min;
+    for i in order {
+        offsets[i] = at - spans[i].min;
+        at += spans[i].span() + gap;
+    }
+    offsets
+}
+
+/// A placed text box reopened for editing in place.
+struct TextEditor {
+    id: ItemId,
+    text: String,
+    /// Focus is handed to the field once, when it first shows.
+    focused: bool,
+}
+
+struct Curtain {
+    rect: egui::Rect,
+    /// Lifted by a reveal step; it comes back with Cover all.
+    revealed: bool,
+}
+
+struct RemoteCursor {
+    name: String,
+    pos: Pos2,
+    seen: f64,
+}
+
+/// The current and previous tool from before a tool was held down, so letting go
+/// restores both and the previous-tool shortcut doesn't land on the held tool.
+#[derive(Debug, Clone, Copy)]
+struct ToolsBeforeHold {
+    tool: Tool,
+    previous: Tool,
+}
+
+/// The board's point count, kept up to date from document events so the budget
+/// check and the readouts don't add up every item each frame.
+struct PointTally {
+    events: Receiver<Event>,
+    counts: HashMap<ItemId, usize>,
+    total: usize,
+}
+
+impl PointTally {
+    fn new(items: &mut Document) -> Self {
+        let counts: HashMap<_, _> = items
+            .iter_with_ids()
+            .map(|(id, item, _)| (id, item.point_count()))
+            .collect();
+        Self {
+            events: items.subscribe(),
+            total: counts.values().sum(),
+            counts,
+        }
+    }
+
+    /// Catches up with the edits made to `items` since the last call.
+    fn refresh(&mut self, items: &Document) {
+        for event in self.events.try_iter() {
+            match event {
+                Event::Added(id) | Event::Updated(id) => {
+                    let count = items.get(id).map_or(0, |(item, _)| item.point_count());
+                    self.total -= self.counts.insert(id, count).unwrap_or(0);
+                    self.total += count;
+                }
+                Event::Removed(id) => self.total -= self.counts.remove(&id).unwrap_or(0),
+                Event::Cleared => {
+                    self.counts.clear();
+                    self.total = 0;
+                }
+            }
+        }
+    }
+
+    /// The count as of the last [`Self::refresh`].
+    fn total(&self) -> usize {
+        self.total
+    }
+}
+
+impl OpenPenApp {
+    /// Switches to `tool` while a key or button is held down.
+    fn hold_tool(&mut self, tool: Tool) -> ToolsBeforeHold {
+        let before = ToolsBeforeHold {
+            tool: self.tool,
+            previous: self.previous_tool,
+        };
+        self.set_tool(tool);
+        before
+    }
+
+    fn release_tool(&mut self, before: ToolsBeforeHold) {
+        self.tool = before.tool;
+        self.previous_tool = before.previous;
+    }
+
+    fn set_tool(&mut self, tool: Tool) {
+        if self.read_only() && tool != Tool::Mouse {
+            return;
+        }
+        if tool != self.tool {
+            self.previous_tool = self.tool;
+            self.tool = tool;
+        }
+    }
+
+    /// Broadcast viewers, and participants while the host has locked the canvas, can look
+    /// but not draw.
+    fn read_only(&self) -> bool {
+        self.session.as_ref().is_some_and(|s| !s.can_draw())
+    }
+
+    /// Builds a stroke in the current pen style, smoothed and then simplified so it
+    /// doesn't keep every pointer sample. Given a width for each point, it varies in
+    /// width along with them; otherwise it is `thickness` wide throughout.
+    fn stroke_from_points(&self, points: Vec<[f32; 2]>, widths: &[f32]) -> StrokePath {
+        let smoothed = geometry::smooth_polyline_piecewise(&points, self.config.pen_smoothing);
+        let keep = geometry::simplify_indices(&smoothed, self.config.stroke_simplification);
+        let widths = if widths.len() == points.len() {
+            let smoothed = geometry::smooth_values_piecewise(widths, self.config.pen_smoothing);
+            keep.iter().map(|idx| smoothed[*idx]).collect()
+        } else {
+            Vec::new()
+        };
+        StrokePath {
+            points: keep.iter().map(|idx| smoothed[*idx]).collect(),
+            rgba: self.palette[self.active_color].to_array(),
+            thickness: self.thickness,
+            widths,
+        }
+    }
+
+    /// Width at each point of the stroke being drawn, following the pen's pressure;
+    /// empty when nothing reported any.
+    fn drawing_widths(&self) -> Vec<f32> {
+        if self.drawing_pressure.iter().all(Option::is_none) {
+            return Vec::new();
+        }
+        let mut last = self.drawing_pressure.iter().flatten().next().copied();
+        self.drawing_pressure
+            .iter()
+            .map(|pressure| {
+                last = pressure.or(last);
+                let pressure = last.unwrap_or(1.0).clamp(0.0, 1.0);
+                self.thickness * (MIN_PRESSURE_WIDTH + (1.0 - MIN_PRESSURE_WIDTH) * pressure)
+            })
+            .collect()
+    }
+
+    /// Adds a new item, set to fade out if its kind's tool is, and returns its id. The
+    /// deadline is part of the item, so peers and the journal fade it at the same time.
+    fn push_item(&mut self, item: CanvasItem) -> Option<ItemId> {
+        if self.read_only() {
+            return None;
+        }
+        let command = match self.fade_after.get(&Tool::for_item(&item)) {
+            Some(seconds) => Command::AddExpiring {
+                item,
+                expires: unix_time() + *seconds as f64,
+            },
+            None => Command::Add(item),
+        };
+        self.items.execute(command)
+    }
+
+    /// Removes items whose time is up, and wakes up for the next one to start fading.
+    fn expire_items(&mut self, ctx: &egui::Context) {
+        let now = unix_time();
+        let expired: Vec<ItemId> = self
+            .items
+            .iter_expiring()
+            .filter(|(_, expires)| *expires <= now)
+            .map(|(id, _)| id)
+            .collect();
+        if !self.read_only() {
+            for id in expired {
+                self.items.execute_untracked(Command::Remove(id));
+            }
+        }
+        let upcoming = self.items.iter_expiring().map(|(_, expires)| expires);
+        if let Some(next) = upcoming.filter(|expires| *expires > now).reduce(f64::min) {
+            let fade_starts = next - FADE_OUT_SECONDS;
+            if fade_starts <= now {
+                ctx.request_repaint();
+            } else {
+                ctx.request_repaint_after(Duration::from_secs_f64(fade_starts - now));
+            }
+        }
+    }
+
+    fn undo(&mut self) {
+        if !self.read_only() && self.items.undo() {
+            self.after_history_step();
+        }
+    }
+
+    fn redo(&mut self) {
+        if !self.read_only() && self.items.redo() {
+            self.after_history_step();
+        }
+    }
+
+    /// Undo and Redo may take selected items away, put items back under new ids, and
+    /// take markers away or bring them back.
+    fn after_history_step(&mut self) {
+        let items = &self.items;
+        self.selection.retain(|id| items.get(*id).is_some());
+        self.text_editor = None;
+        // Only the local user's markers count, as in `renumber_markers`; other
+        // people's numbering runs on its own.
+        let local = self.items.author();
+        self.next_marker = self
+            .items
+            .iter_with_ids()
+            .filter(|(_, _, author)| *author == local)
+            .filter_map(|(_, item, _)| match item {
+                CanvasItem::Marker(marker) => Some(marker.number + 1),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(1);
+    }
+
+    /// Closes the gaps left by removed markers, so the rest still count up in
+    /// order, and steps the counter back so the next marker follows on. Only the
+    /// local user's markers are renumbered; other people's are theirs to edit.
+    fn renumber_markers(&mut self, removed: &[u32]) {
+        if removed.is_empty() || self.read_only() {
+            return;
+        }
+        let local = self.items.author();
+        let renumbered: Vec<_> = self
+            .items
+            .iter_with_ids()
+            .filter(|(_, _, author)| *author == local)
+            .filter_map(|(id, item, _)| {
+                let CanvasItem::Marker(marker) = item else {
+                    return None;
+                };
+                let gaps = removed.iter().filter(|n| **n < marker.number).count() as u32;
+                (gaps > 0).then(|| {
+                    let mut marker = marker.clone();
+                    marker.number -= gaps;
+                    (id, CanvasItem::Marker(marker))
+                })
+            })
+            .collect();
+        for (id, item) in renumbered {
+            self.items.execute(Command::Replace { id, item });
+        }
+        let below_next = removed.iter().filter(|n| **n < self.next_marker).count() as u32;
+        self.next_marker = (self.next_marker - below_next).max(1);
+    }
+
+    /// What Undo would take back, e.g. "Undo text “Step 2”"; `None` if nothing.
+    fn undo_description(&self) -> Option<String> {
+        if self.read_only() {
+            return None;
+        }
+        let step = self.items.undo_step()?;
+        // Undoing takes added items away and puts removed ones back.
+        match (
+            step.added.as_slice(),
+            step.removed.is_empty(),
+            step.edited.is_empty(),
+        ) {
+            ([id], true, true) => match self.items.get(*id) {
+                Some((item, _)) => Some(describe_change(item, false)),
+                None => Some(tr("Undo").to_string()),
+            },
+            ([_, ..], true, true) => Some(trf("Undo adding {0} items", &[&step.added.len()])),
+            ([], false, true) => Some(tr("Undo erase").to_string()),
+            _ => Some(tr("Undo edit").to_string()),
+        }
+    }
+
+    /// What Redo would bring back; `None` if nothing.
+    fn redo_description(&self) -> Option<String> {
+        if self.read_only() {
+            return None;
+        }
+        let step = self.items.redo_step()?;
+        // A redo step is the undone step turned around: what it removes was added.
+        match (
+            step.removed.as_slice(),
+            step.added.is_empty(),
+            step.edited.is_empty(),
+        ) {
+            ([removed], true, true) => Some(describe_change(&removed.item, true)),
+            ([_, ..], true, true) => Some(trf("Redo adding {0} items", &[&step.removed.len()])),
+            ([], false, true) => Some(tr("Redo erase").to_string()),
+            _ => Some(tr("Redo edit").to_string()),
+        }
+    }
+
+    /// The shape the current tool makes from a drag from `start` to `end`: the
+    /// ellipse inscribed in the dragged box, or a straight line. `None` while the
+    /// drag is too short to draw anything.
+    fn shape_between(&self, ctx: &egui::Context, start: Pos2, end: Pos2) -> Option<CanvasItem> {
+        let rgba = self.palette[self.active_color].to_array();
+        match self.tool {
+            Tool::Ellipse => {
+                let center = start + (end - start) / 2.0;
+                let radii = [(end.x - start.x).abs() / 2.0, (end.y - start.y).abs() / 2.0];
+                let ellipse = EllipseShape {
+                    center: [center.x, center.y],
+                    radii,
+                    rgba,
+                    thickness: self.thickness,
+                    rotation: 0.0,
+                    fill: self.shape_fill(),
+                };
+                (radii[0] >= 1.0 && radii[1] >= 1.0).then_some(CanvasItem::Ellipse(ellipse))
+            }
+            Tool::Line => {
+                let line = LineShape {
+                    start: [start.x, start.y],
+                    end: [end.x, end.y],
+                    rgba,
+                    thickness: self.thickness,
+                };
+                (start.distance(end) >= 1.0).then_some(CanvasItem::Line(line))
+            }
+            // Drawn from the tip of the tail to the middle of the bubble.
+            Tool::Callout => {
+                let mut text = self.text_box(end, self.text_to_place());
+                let [width, height] = render::text_size(ctx, &text);
+                text.pos = [end.x - width / 2.0, end.y - height / 2.0];
+                let tip = [start.x, start.y];
+                (start.distance(end) >= 1.0).then_some(CanvasItem::Callout(Callout { text, tip }))
+            }
+            _ => None,
+        }
+    }
+
+    /// The topmost shown item under `pos`.
+    fn item_at(&self, pos: Pos2) -> Option<ItemId> {
+        if self.hide_all {
+            return None;
+        }
+        self.items
+            .iter_with_ids()
+            .filter(|(id, item, author)| {
+                !self.hidden_authors.contains(*author)
+                    && item.hit(
+                        [pos.x, pos.y],
+                        SELECT_TOLERANCE,
+                        self.shape_cache.text_size(*id),
+                    )
+            })
+            .map(|(id, _, _)| id)
+            .last()
+    }
+
+    /// The text typed in the tool options, or a placeholder if there is none.
+    fn text_to_place(&self) -> String {
+        if self.text_draft.trim().is_empty() {
+            tr("Text").to_string()
+        } else {
+            self.text_draft.clone()
+        }
+    }
+
+    /// `pos` moved to the nearest grid intersection while the grid is on.
+    fn snap_to_grid(&self, pos: Pos2) -> Pos2 {
+        if !self.config.grid {
+            return pos;
+        }
+        let spacing = self.config.grid_spacing;
+        Pos2::new(
+            (pos.x / spacing).round() * spacing,
+            (pos.y / spacing).round() * spacing,
+        )
+    }
+
+    /// The inside color for new polygons and ellipses, if they are filled.
+    fn shape_fill(&self) -> Option<[u8; 4]> {
+        self.config
+            .shape_fill
+            .then_some(self.config.shape_fill_rgba)
+    }
+
+    /// A text box at `pos` in the current color, wrapping at the set width.
+    fn text_box(&self, pos: Pos2, text: String) -> TextBox {
+        TextBox {
+            pos: [pos.x, pos.y],
+            text,
+            rgba: self.palette[self.active_color].to_array(),
+            wrap_width: (self.config.text_wrap_width > 0.0).then_some(self.config.text_wrap_width),
+            background: self.config.text_background,
+        }
+    }
+
+    /// Opens the editor on the text box under `pos`, if there is one.
+    fn start_text_edit(&mut self, pos: Pos2) {
+        if self.read_only() {
+            return;
+        }
+        self.finish_text_edit(true);
+        let Some(id) = self.item_at(pos) else {
+            return;
+        };
+        if let Some(text) = self.items.get(id).and_then(|(item, _)| item.text_box()) {
+            self.text_editor = Some(TextEditor {
+                id,
+                text: text.text.clone(),
+                focused: false,
+            });
+        }
+    }
+
+    /// Closes the text editor, swapping in the edited text if `keep` is set. Text
+    /// edited down to nothing removes the box.
+    fn finish_text_edit(&mut self, keep: bool) {
+        let Some(editor) = self.text_editor.take() else {
+            return;
+        };
+        let Some((item, _)) = self.items.get(editor.id) else {
+            return;
+        };
+        let Some(text) = item.text_box() else {
+            return;
+        };
+        if !keep || editor.text == text.text || self.read_only() {
+            return;
+        }
+        let id = editor.id;
+        let emptied = editor.text.trim().is_empty();
+        if emptied {
+            self.items.execute(Command::Remove(id));
+        } else {
+            let mut item = item.clone();
+            if let Some(text) = item.text_box_mut() {
+                text.text = editor.text;
+            }
+            self.items.execute(Command::Replace { id, item });
+        }
+        if emptied || self.tool != Tool::Select {
+            self.selection.retain(|selected| *selected != id);
+        }
+    }
+
+    fn text_at(&self, pos: Pos2) -> bool {
+        self.item_at(pos)
+            .and_then(|id| self.items.get(id))
+            .is_some_and(|(item, _)| item.text_box().is_some())
+    }
+
+    /// Union of the bounds of the selected items.
+    fn selection_bounds(&self) -> Option<egui::Rect> {
+        self.selection
+            .iter()
+            .filter_map(|id| item_rect(self.items.get(*id)?.0, self.shape_cache.text_size(*id)))
+            .reduce(|a, b| a.union(b))
+    }
+
+    /// Edits the selected items in place, as one undo step. `edit` returns whether it
+    /// changed the item it was given.
+    fn edit_selection(&mut self, mut edit: impl FnMut(ItemId, &mut CanvasItem) -> bool) {
+        if self.read_only() {
+            return;
+        }
+        self.items.begin_step();
+        for id in self.selection.clone() {
+            let Some((item, _)) = self.items.get(id) else {
+                continue;
+            };
+            let mut item = item.clone();
+            if edit(id, &mut item) {
+                self.items.execute(Command::Replace { id, item });
+            }
+        }
+        self.items.end_step();
+    }
+
+    fn recolor_selection(&mut self) {
+        let rgba = self.palette[self.active_color].to_array();
+        self.edit_selection(|_, item| {
+            item.recolor(rgba);
+            true
+        });
+    }
+
+    /// Moves the selected items into line with each other.
+    fn align_selection(&mut self, alignment: Alignment) {
+        let (ids, bounds): (Vec<ItemId>, Vec<egui::Rect>) = self
+            .selection
+            .iter()
+            .filter_map(|id| {
+                let rect = item_rect(self.items.get(*id)?.0, self.shape_cache.text_size(*id))?;
+                Some((*id, rect))
+            })
+            .unzip();
+        if ids.len() < alignment.min_items() {
+            return;
+        }
+        let offsets: HashMap<ItemId, Vec2> = ids
+            .into_iter()
+            .zip(alignment.offsets(&bounds))
+            .filter(|(_, offset)| offset.length() >= 0.01)
+            .collect();
+        self.edit_selection(|id, item| match offsets.get(&id) {
+            Some(offset) => {
+                item.translate([offset.x, offset.y]);
+                true
+            }
+            None => false,
+        });
+    }
+
+    /// Adds a copy of each selected item, a little down and to the right, and
+    /// selects the copies.
+    fn duplicate_selection(&mut self) {
+        if self.read_only() {
+            return;
+        }
+        let copies: Vec<CanvasItem> = self
+            .selection
+            .iter()
+            .filter_map(|id| self.items.get(*id))
+            .map(|(item, _)| {
+                let mut copy = item.clone();
+                copy.translate([DUPLICATE_OFFSET, DUPLICATE_OFFSET]);
+                copy
+            })
+            .collect();
+        self.items.begin_step();
+        self.selection = copies
+            .into_iter()
+            .filter_map(|copy| self.push_item(copy))
+            .collect();
+        self.items.end_step();
+    }
+
+    /// Puts the selected items on the clipboard as board JSON, which pastes back as
+    /// items here or in another OpenPen.
+    fn copy_selection(&self, ctx: &egui::Context) {
+        if self.selection.is_empty() {
+            return;
+        }
+        let items = self
+            .selection
+            .iter()
+            .filter_map(|id| self.items.get(*id))
+            .map(|(item, _)| item);
+        if let Ok(json) = board::to_json(items) {
+            ctx.output_mut(|o| o.copied_text = json);
+        }
+    }
+
+    /// Adds copied items centred on `pos` and selects them.
+    fn paste_items(&mut self, items: Vec<CanvasItem>, pos: Pos2) {
+        if self.read_only() {
+            return;
+        }
+        let bounds = items
+            .iter()
+            .filter_map(|item| item_rect(item, None))
+            .reduce(|a, b| a.union(b));
+        let offset = bounds.map_or(Vec2::ZERO, |bounds| pos - bounds.center());
+        self.set_tool(Tool::Select);
+        self.items.begin_step();
+        self.selection = items
+            .into_iter()
+            .filter_map(|mut item| {
+                item.translate([offset.x, offset.y]);
+                self.push_item(item)
+            })
+            .collect();
+        self.items.end_step();
+    }
+
+    fn delete_selection(&mut self) {
+        if self.read_only() {
+            return;
+        }
+        self.items.begin_step();
+        for id in std::mem::take(&mut self.selection) {
+            self.items.execute(Command::Remove(id));
+        }
+        self.items.end_step();
+    }
+
+    /// The scale handles at the corners of the selection and the rotation grip above
+    /// it, while nothing is being dragged.
+    fn handles(&self) -> Vec<(Handle, Pos2)> {
+        let Some(bounds) = self
+            .selection_bounds()
+            .filter(|_| self.selection_drag.is_none())
+        else {
+            return Vec::new();
+        };
+        let bounds = bounds.expand(4.0);
+        let corners = [
+            bounds.left_top(),
+            bounds.right_top(),
+            bounds.right_bottom(),
+            bounds.left_bottom(),
+        ];
+        let mut handles: Vec<_> = corners
+            .into_iter()
+            .enumerate()
+            .map(|(idx, pos)| (Handle::Corner(idx), pos))
+            .collect();
+        handles.push((
+            Handle::Rotate,
+            bounds.center_top() - Vec2::Y * ROTATE_GRIP_OFFSET,
+        ));
+        if let [id] = self.selection[..] {
+            if let Some((CanvasItem::Callout(callout), _)) = self.items.get(id) {
+                // First, so it wins over a corner handle it sits next to.
+                handles.insert(0, (Handle::Tip, Pos2::new(callout.tip[0], callout.tip[1])));
+            }
+        }
+        handles
+    }
+
+    fn handle_at(&self, pos: Pos2) -> Option<Handle> {
+        self.handles()
+            .into_iter()
+            .find(|(_, at)| at.distance(pos) <= HANDLE_SIZE)
+            .map(|(handle, _)| handle)
+    }
+
+    /// Outlines the selected items and the rubber band, draws the handles, and shows
+    /// the selection changed as it would be while it is dragged.
+    fn draw_selection(&self, painter: &egui::Painter) {
+        let visuals = &painter.ctx().style().visuals;
+        let color = visuals.selection.stroke.color;
+        for id in &self.selection {
+            let Some((item, _)) = self.items.get(*id) else {
+                continue;
+            };
+            let mut changed;
+            let item = match self.selection_drag {
+                Some(drag) => {
+                    changed = item.clone();
+                    drag.apply(&mut changed, self.shape_cache.text_size(*id));
+                    painter.add(render::item_shape(&changed, painter, 0.0));
+                    &changed
+                }
+                None => item,
+            };
+            if let Some(rect) = item_rect(item, self.shape_cache.text_size(*id)) {
+                painter.rect_stroke(rect.expand(4.0), 2.0, Stroke::new(1.5, color));
+            }
+        }
+        let handles = self.handles();
+        if let Some((_, grip)) = handles.iter().find(|(handle, _)| *handle == Handle::Rotate) {
+            let top = grip.y + ROTATE_GRIP_OFFSET;
+            painter.line_segment([*grip, Pos2::new(grip.x, top)], Stroke::new(1.0, color));
+        }
+        for (handle, pos) in handles {
+            let fill = visuals.extreme_bg_color;
+            let stroke = Stroke::new(1.5, color);
+            match handle {
+                Handle::Corner(_) => {
+                    let square = egui::Rect::from_center_size(pos, Vec2::splat(HANDLE_SIZE));
+                    painter.rect(square, 0.0, fill, stroke);
+                }
+                Handle::Rotate => {
+                    painter.circle(pos, HANDLE_SIZE / 2.0, fill, stroke);
+                }
+                Handle::Tip => {
+                    painter.circle(pos, HANDLE_SIZE / 2.0, color, stroke);
+                }
+            }
+        }
+        if let Some((start, end)) = self.band_drag {
+            let band = egui::Rect::from_two_pos(start, end);
+            painter.rect(
+                band,
+                0.0,
+                color.gamma_multiply(0.15),
+                Stroke::new(1.0, color),
+            );
+        }
+    }
+
+    /// A dimension line from `start` to `end`, labelled with its length in physical
+    /// pixels, and in centimetres too once the screen DPI is set.
+    fn draw_measurement(&self, painter: &egui::Painter, start: Pos2, end: Pos2) {
+        let length = start.distance(end);
+        if length < 1.0 {
+            return;
+        }
+        let stroke = Stroke::new(2.0, self.palette[self.active_color]);
+        let normal = (end - start).normalized().rot90();
+        painter.line_segment([start, end], stroke);
+        for p in [start, end] {
+            painter.line_segment([p - normal * RULER_TICK, p + normal * RULER_TICK], stroke);
+        }
+
+        let pixels = length * painter.ctx().pixels_per_point();
+        let dpi = self.config.ruler_dpi;
+        let text = if dpi > 0.0 {
+            let cm = format!("{:.1}", pixels / dpi * 2.54);
+            trf("{0} px · {1} cm", &[&pixels.round(), &cm])
+        } else {
+            trf("{0} px", &[&pixels.round()])
+        };
+        let galley =
+            painter.layout_no_wrap(text, FontId::proportional(RULER_TEXT_SIZE), Color32::WHITE);
+        let anchor = start + (end - start) / 2.0 - normal * (RULER_TICK + RULER_TEXT_SIZE);
+        let rect = egui::Align2::CENTER_CENTER
+            .anchor_size(anchor, galley.size())
+            .expand(4.0);
+        painter.rect_filled(rect, 4.0, Color32::from_black_alpha(180));
+        painter.galley(rect.min + Vec2::splat(4.0), galley, Color32::WHITE);
+    }
+
+    fn clear(&mut self) {
+        if self.read_only() {
+            return;
+        }
+        self.items.execute(Command::Clear);
+        self.polygon_points.clear();
+    }
+
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        let typing = ctx.wants_keyboard_input();
+        let actions = ctx.input(|i| self.config.keymap.triggered(i, typing));
+        for (action, key) in actions {
+            if let Action::Hold(tool) = action {
+                if self.held_tool.is_none() && tool != self.tool && !self.read_only() {
+                    let before = self.hold_tool(tool);
+                    self.held_tool = Some((ctx.viewport_id(), key, before));
+                }
+            } else {
+                self.run_action(action, ctx);
+            }
+        }
+        // Only the window that saw the key go down sees it come back up.
+        if let Some((viewport, key, before)) = self.held_tool {
+            if viewport == ctx.viewport_id() && !ctx.input(|i| i.key_down(key)) {
+                self.held_tool = None;
+                self.release_tool(before);
+            }
+        }
+
+        let (copy, cut) = ctx.input(|i| {
+            (
+                i.events.iter().any(|e| matches!(e, egui::Event::Copy)),
+                i.events.iter().any(|e| matches!(e, egui::Event::Cut)),
+            )
+        });
+        // A focused text field handles copy and cut itself.
+        if (copy || cut) && !ctx.wants_keyboard_input() {
+            self.copy_selection(ctx);
+            if cut {
+                self.delete_selection();
+            }
+        }
+
+        let pasted = ctx.input(|i| {
+            i.events.iter().rev().find_map(|e| match e {
+                egui::Event::Paste(text) => Some(text.clone()),
+                _ => None,
+            })
+        });
+        if let Some(text) = pasted {
+            // A focused text field (e.g. the Text tool draft) consumes the paste itself.
+            if !ctx.wants_keyboard_input() && !text.trim().is_empty() {
+                let pos = ctx
+                    .input(|i| i.pointer.hover_pos())
+                    .unwrap_or_else(|| ctx.screen_rect().center());
+                match board::from_json(&text) {
+                    Ok(items) if !items.is_empty() => self.paste_items(items, pos),
+                    _ => {
+                        let text = self.text_box(pos, text.trim_end().to_string());
+                        self.push_item(CanvasItem::Text(text));
+                    }
+                }
+            }
+        }
+    }
+
+    fn run_action(&mut self, action: Action, ctx: &egui::Context) {
+        let now = ctx.input(|i| i.time);
+        match action {
+            Action::Pen => self.set_tool(Tool::Pen),
+            Action::Polygon => self.set_tool(Tool::Polygon),
+            Action::Ellipse => self.set_tool(Tool::Ellipse),
+            Action::Line => self.set_tool(Tool::Line),
+            Action::Marker => self.set_tool(Tool::Marker),
+            Action::Stamp => self.set_tool(Tool::Stamp),
+            Action::Ruler => self.set_tool(Tool::Ruler),
+            Action::Callout => self.set_tool(Tool::Callout),
+            Action::Text => self.set_tool(Tool::Text),
+            Action::Mouse => self.set_tool(Tool::Mouse),
+            Action::Select => self.set_tool(Tool::Select),
+            Action::DeleteSelection => self.delete_selection(),
+            Action::Duplicate => self.duplicate_selection(),
+            // A focused text field undoes its own typing.
+            Action::Undo | Action::Redo if ctx.wants_keyboard_input() => {}
+            Action::Undo => self.undo(),
+            Action::Redo => self.redo(),
+            Action::Eraser => self.set_tool(Tool::Eraser),
+            Action::LastTool => self.set_tool(self.previous_tool),
+            // Needs the key that is held; handled in `handle_shortcuts`.
+            Action::Hold(_) => {}
+            Action::ClosePolygon => {
+                if self.tool == Tool::Polygon && self.polygon_points.len() >= 3 {
+                    let poly = PolygonShape {
+                        points: self.polygon_points.iter().map(|p| [p.x, p.y]).collect(),
+                        rgba: self.palette[self.active_color].to_array(),
+                        thickness: self.thickness,
+                        fill: self.shape_fill(),
+                    };
+                    self.push_item(CanvasItem::Polygon(poly));
+                    self.polygon_points.clear();
+                }
+            }
+            Action::NextColor | Action::PreviousColor => {
+                let step = if action == Action::NextColor { 1 } else { -1 };
+                let len = self.palette.len() as isize;
+                self.active_color = (self.active_color as isize + step).rem_euclid(len) as usize;
+                self.toast(trf("Palette color {0}", &[&(self.active_color + 1)]), now);
+            }
+            Action::PickColor(idx) => {
+                if idx < self.palette.len() {
+                    self.active_color = idx;
+                    self.toast(trf("Palette color {0}", &[&(idx + 1)]), now);
+                }
+            }
+            Action::BrushSmaller => self.resize_brush(-1.0, now),
+            Action::BrushLarger => self.resize_brush(1.0, now),
+            Action::ShowToolbar => self.toolbar_last_used = now,
+            Action::UiScaleUp | Action::UiScaleDown | Action::UiScaleReset => {
+                self.config.ui_scale = match action {
+                    Action::UiScaleUp => self.config.ui_scale + UI_SCALE_STEP,
+                    Action::UiScaleDown => self.config.ui_scale - UI_SCALE_STEP,
+                    _ => 1.0,
+                }
+                .clamp(*UI_SCALE_RANGE.start(), *UI_SCALE_RANGE.end());
+                self.save_config();
+                let percent = (self.config.ui_scale * 100.0).round();
+                self.toast(trf("UI scale {0}%", &[&percent]), now);
+            }
+            Action::Stopwatch => {
+                self.stopwatch.toggle(now);
+                self.show_stopwatch = true;
+            }
+            Action::Lap => self.mark_lap(now),
+            Action::Break => self.toggle_break(now),
+            Action::RevealNext => self.reveal_next(),
+            Action::RevealPrevious => {
+                if let Some(curtain) = self.curtains.iter_mut().rev().find(|c| c.revealed) {
+                    curtain.revealed = false;
+                }
+            }
+            Action::HideAll => {
+                self.hide_all = !self.hide_all;
+                let notice = if self.hide_all {
+                    tr("Annotations hidden")
+                } else {
+                    tr("Annotations shown")
+                };
+                self.toast(notice.to_string(), now);
+            }
+            Action::Grid => {
+                self.config.grid = !self.config.grid;
+                self.save_config();
+            }
+            Action::Spotlight => self.spotlight = !self.spotlight,
+            Action::SpotlightSmaller | Action::SpotlightLarger if self.spotlight => {
+                let step = if action == Action::SpotlightLarger {
+                    SPOTLIGHT_STEP
+                } else {
+                    -SPOTLIGHT_STEP
+                };
+                self.spotlight_radius = (self.spotlight_radius + step).clamp(
+                    *SPOTLIGHT_RADIUS_RANGE.start(),
+                    *SPOTLIGHT_RADIUS_RANGE.end(),
+                );
+            }
+            Action::SpotlightSmaller | Action::SpotlightLarger => {}
+            Action::Help => self.show_help = !self.show_help,
+            Action::PerfHud => self.show_perf_hud = !self.show_perf_hud,
+            Action::Cancel => self.cancel(ctx),
+            Action::Quit => ctx.send_viewport_cmd(egui::ViewportCommand::Close),
+        }
+    }
+
+    /// Escape: drops the innermost thing in progress, and only once there is nothing
+    /// left gets the overlay out of the way.
+    fn cancel(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            // The focused text field gives up focus on Escape by itself.
+        } else if self.on_break.is_some() {
+            self.on_break = None;
+        } else if self.text_editor.is_some() {
+            self.finish_text_edit(false);
+        } else if !self.drawing.is_empty() {
+            self.drawing.clear();
+            self.live_stroke.clear();
+        } else if self.shape_drag.is_some() {
+            self.shape_drag = None;
+        } else if self.measurement.is_some() {
+            self.measurement = None;
+        } else if self.selection_drag.is_some() || self.band_drag.is_some() {
+            self.selection_drag = None;
+            self.band_drag = None;
+        } else if !self.selection.is_empty() {
+            self.selection.clear();
+        } else if !self.polygon_points.is_empty() {
+            self.polygon_points.clear();
+        } else if self.show_help {
+            self.show_help = false;
+        } else if self.config.escape_hides {
+            ctx.send_viewport_cmd_to(
+                egui::ViewportId::ROOT,
+                egui::ViewportCommand::Minimized(true),
+            );
+        }
+    }
+
+    /// An action's name followed by its first key, e.g. "Pen (1)".
+    fn shortcut_tip(&self, ctx: &egui::Context, action: Action) -> String {
+        self.with_shortcut(ctx, &action.description(), action)
+    }
+
+    /// `text` followed by the first key for `action`, if it has one.
+    fn with_shortcut(&self, ctx: &egui::Context, text: &str, action: Action) -> String {
+        match self.config.keymap.hint(action, ctx) {
+            Some(key) => format!("{text} ({key})"),
+            None => text.to_string(),
+        }
+    }
+
+    /// The F1 cheat sheet, listing every action with its current keys.
+    fn help_overlay(&mut self, ctx: &egui::Context) {
+        let actions = self.config.keymap.actions();
+        let mut open = true;
+        egui::Window::new(tr("Keyboard shortcuts"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+            .show(ctx, |ui| {
+                egui::Grid::new("shortcuts")
+                    .num_columns(2)
+                    .spacing([24.0, 6.0])
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (action, bindings) in actions {
+                            ui.label(action.description());
+                            let keys: Vec<String> =
+                                bindings.iter().map(|binding| binding.format(ctx)).collect();
+                            ui.label(RichText::new(keys.join("  ·  ")).monospace());
+                            ui.end_row();
+                        }
+                        ui.label(tr("Paste text as a text box"));
+                        ui.label(
+                            RichText::new(ctx.format_shortcut(&egui::KeyboardShortcut::new(
+                                egui::Modifiers::COMMAND,
+                                egui::Key::V,
+                            )))
+                            .monospace(),
+                        );
+                        ui.end_row();
+                        ui.label(tr("Resize pen or eraser"));
+                        ui.label(RichText::new(tr("Scroll wheel")).monospace());
+                        ui.end_row();
+                    });
+                ui.small(tr(
+                    "Keys can be remapped in the \"keymap\" list of the settings file.",
+                ));
+            });
+        self.show_help &= open;
+    }
+
+    fn toolbar(&mut self, ctx: &egui::Context) {
+        let area = egui::Area::new("bottom_toolbar".into());
+        let area = match self.config.toolbar {
+            ToolbarPlacement::Docked(edge) => {
+                let (align, offset) = edge.anchor(TOOLBAR_MARGIN);
+                area.anchor(align, offset)
+            }
+            ToolbarPlacement::Floating(pos) => area.fixed_pos(Pos2::new(pos[0], pos[1])),
+        };
+        self.toolbar_rect = area
+            .show(ctx, |ui| {
+                self.toolbar_contents(ui);
+            })
+            .response
+            .rect;
+    }
+
+    /// Moves the in-overlay toolbar along with the drag handle.
+    fn drag_toolbar(&mut self, delta: Vec2) {
+        let pos = self.toolbar_rect.min + delta;
+        self.toolbar_rect = self.toolbar_rect.translate(delta);
+        self.config.toolbar = ToolbarPlacement::Floating([pos.x, pos.y]);
+    }
+
+    /// Docks the toolbar if it was dropped close to a screen edge, then remembers where it went.
+    fn place_toolbar(&mut self, rect: egui::Rect, screen: egui::Rect) {
+        self.config.toolbar = match Edge::near(rect, screen, DOCK_DISTANCE) {
+            Some(edge) => ToolbarPlacement::Docked(edge),
+            None => ToolbarPlacement::Floating([rect.min.x, rect.min.y]),
+        };
+        // Recomputed from the placement the next time the toolbar has its own window.
+        self.toolbar_pos = None;
+        self.save_config();
+    }
+
+    fn save_config(&mut self) {
+        self.config_error = self.config.save().err().map(|e| e.to_string());
+    }
+
+    /// Hosts the toolbar in its own small window so the overlay can pass input through.
+    fn toolbar_viewport(&mut self, ctx: &egui::Context) {
+        let monitor = ctx
+            .input(|i| i.viewport().monitor_size)
+            .unwrap_or(Vec2::new(1920.0, 1080.0));
+        if self.toolbar_pos.is_none() {
+            self.toolbar_pos = Some(match self.config.toolbar {
+                ToolbarPlacement::Docked(edge) => {
+                    edge.position(monitor, self.toolbar_size, TOOLBAR_MARGIN)
+                }
+                ToolbarPlacement::Floating(pos) => Pos2::new(pos[0], pos[1]),
+            });
+        }
+
+        let mut builder = egui::ViewportBuilder::default()
+            .with_title("OpenPen toolbar")
+            .with_transparent(true)
+            .with_decorations(false)
+            .with_resizable(false)
+            .with_window_level(egui::WindowLevel::AlwaysOnTop)
+            .with_inner_size(self.toolbar_size);
+        if let Some(pos) = self.toolbar_pos {
+            builder = builder.with_position(pos);
+        }
+
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("toolbar_window"),
+            builder,
+            |ctx, _class| {
+                self.handle_shortcuts(ctx);
+                egui::Area::new("toolbar_window_contents".into())
+                    .fixed_pos(Pos2::ZERO)
+                    .show(ctx, |ui| {
+                        self.toolbar_contents(ui);
+                    });
+
+                // The OS moved the window while its handle was dragged; pick up where it
+                // ended and let it snap to an edge.
+                let moved_to = ctx.input(|i| {
+                    (!i.pointer.any_down())
+                        .then(|| i.viewport().outer_rect)
+                        .flatten()
+                });
+                if let Some(outer) = moved_to.filter(|_| self.toolbar_dragging) {
+                    self.toolbar_dragging = false;
+                    let screen = egui::Rect::from_min_size(Pos2::ZERO, monitor);
+                    self.place_toolbar(
+                        egui::Rect::from_min_size(outer.min, self.toolbar_size),
+                        screen,
+                    );
+                    self.toolbar_pos = match self.config.toolbar {
+                        ToolbarPlacement::Docked(_) => None,
+                        ToolbarPlacement::Floating(_) => Some(outer.min),
+                    };
+                }
+
+                let mut size = ctx.used_size().ceil();
+                if ctx.memory(|m| m.areas().visible_layer_ids().len() > 1) {
+                    // An open menu would otherwise be squeezed by the window edge.
+                    let headroom = TOOLBAR_MENU_HEADROOM * self.config.ui_scale;
+                    size.y += headroom;
+                    if self.config.toolbar_vertical() {
+                        size.x += headroom;
+                    }
+                }
+                if (size - self.toolbar_size).length() > 1.0 {
+                    if ctx.memory(|m| m.areas().visible_layer_ids().len() <= 1) {
+                        // Collapsing or expanding: re-dock around the new size.
+                        self.toolbar_pos = match self.config.toolbar {
+                            ToolbarPlacement::Docked(_) => None,
+                            ToolbarPlacement::Floating(_) => self.toolbar_pos,
+                        };
+                    }
+                    self.toolbar_size = size;
+                    ctx.request_repaint();
+                }
+            },
+        );
+    }
+
+    fn toolbar_contents(&mut self, ui: &mut egui::Ui) {
+        let now = ui.input(|i| i.time);
+        // Hovering the toolbar or one of its open menus keeps it expanded.
+        if !self.config.toolbar_autohide || self.toolbar_dragging || ui.ctx().is_pointer_over_area()
+        {
+            self.toolbar_last_used = now;
+        }
+        let idle = now - self.toolbar_last_used;
+        let collapsed = idle >= TOOLBAR_HIDE_SECONDS;
+        if self.config.toolbar_autohide && !collapsed {
+            ui.ctx()
+                .request_repaint_after(Duration::from_secs_f64(TOOLBAR_HIDE_SECONDS - idle));
+        }
+
+        egui::Frame::window(ui.style())
+            .rounding(egui::Rounding::same(14.0))
+            .show(ui, |ui| {
+                self.toolbar_handle(ui, collapsed);
+                if collapsed {
+                    return;
+                }
+
+                let vertical = self.config.toolbar_vertical();
+                let layout = match (vertical, self.config.left_handed) {
+                    (true, false) => egui::Layout::top_down(egui::Align::Min),
+                    (true, true) => egui::Layout::top_down(egui::Align::Max),
+                    (false, false) => egui::Layout::left_to_right(egui::Align::Center),
+                    (false, true) => egui::Layout::right_to_left(egui::Align::Center),
+                }
+                .with_main_wrap(!vertical);
+                ui.with_layout(layout, |ui| {
+                    self.tool_buttons(ui);
+                    self.toolbar_menus(ui);
+                });
+
+                if self.tool != Tool::Mouse {
+                    ui.separator();
+                    ui.with_layout(layout, |ui| self.tool_options(ui));
+                }
+
+                ui.with_layout(layout, |ui| self.history_buttons(ui));
+
+                if !vertical && !self.config.toolbar_compact {
+                    let help = self.shortcut_tip(ui.ctx(), Action::Help);
+                    ui.small(trf("Keyboard shortcuts: {0}", &[&help]));
+                }
+            });
+    }
+
+    /// A toolbar button's text: the icon alone in the compact toolbar, else the icon and `name`.
+    fn toolbar_label(&self, icon: &str, name: &str) -> String {
+        if self.config.toolbar_compact {
+            icon.to_string()
+        } else {
+            format!("{icon} {name}")
+        }
+    }
+
+    /// The handle the toolbar is dragged and docked by, which is all that is left of it
+    /// while it is collapsed.
+    fn toolbar_handle(&mut self, ui: &mut egui::Ui, collapsed: bool) {
+        let handle = if collapsed || self.config.toolbar_compact {
+            "⠿".to_string()
+        } else {
+            format!("⠿ {}", tr("Drag toolbar"))
+        };
+        let color = ui.visuals().widgets.inactive.fg_stroke.color;
+        let drag_bar = ui
+            .add(
+                egui::Label::new(RichText::new(handle).color(color))
+                    .sense(egui::Sense::click_and_drag()),
+            )
+            .on_hover_text(tr("Drop it near a screen edge to dock it there"));
+        let drag_bar = named(drag_bar, egui::WidgetType::Button, None, tr("Move toolbar"));
+        if self.idle_passthrough {
+            // The toolbar has a window of its own here, which the OS moves for us.
+            if drag_bar.drag_started() {
+                self.toolbar_dragging = true;
+                ui.ctx().send_viewport_cmd(egui::ViewportCommand::StartDrag);
+            }
+        } else {
+            if drag_bar.dragged() {
+                self.drag_toolbar(drag_bar.drag_delta());
+            }
+            if drag_bar.drag_stopped() {
+                self.place_toolbar(self.toolbar_rect, ui.ctx().screen_rect());
+            }
+        }
+    }
+
+    /// One button per tool, the pen's opening its colors, then the grid toggle.
+    fn tool_buttons(&mut self, ui: &mut egui::Ui) {
+        // Announces tool changes, including ones made with the keyboard.
+        let tool_status = ui.allocate_response(Vec2::ZERO, egui::Sense::hover());
+        let tool_name = trf("Tool: {0}", &[&tr(self.tool.name())]);
+        named(
+            tool_status.clone(),
+            egui::WidgetType::Label,
+            None,
+            &tool_name,
+        );
+        ui.ctx().accesskit_node_builder(tool_status.id, |node| {
+            node.set_live(egui::accesskit::Live::Polite);
+        });
+
+        for (tool, icon, action) in TOOL_BUTTONS {
+            let name = tr(tool.name());
+            let tip = self.shortcut_tip(ui.ctx(), action);
+            if tool == Tool::Pen {
+                let title = RichText::new(self.toolbar_label(icon, name))
+                    .color(self.palette[self.active_color]);
+                let left_handed = self.config.left_handed;
+                drop_down(ui, title, name, left_handed, |ui| self.pen_menu(ui)).on_hover_text(tip);
+                continue;
+            }
+            let selected = self.tool == tool;
+            let button = ui.selectable_label(selected, self.toolbar_label(icon, name));
+            if named(
+                button,
+                egui::WidgetType::SelectableLabel,
+                Some(selected),
+                name,
+            )
+            .on_hover_text(tip)
+            .clicked()
+            {
+                self.set_tool(tool);
+            }
+        }
+
+        let grid = ui.selectable_label(self.config.grid, self.toolbar_label("⊞", tr("Grid")));
+        if named(
+            grid,
+            egui::WidgetType::SelectableLabel,
+            Some(self.config.grid),
+            tr("Grid"),
+        )
+        .on_hover_text(self.shortcut_tip(ui.ctx(), Action::Grid))
+        .clicked()
+        {
+            self.config.grid = !self.config.grid;
+            self.save_config();
+        }
+    }
+
+    /// The presenter, timers, session, and settings menus.
+    fn toolbar_menus(&mut self, ui: &mut egui::Ui) {
+        let left_handed = self.config.left_handed;
+        let title = self.toolbar_label("🎯", tr("Presenter"));
+        drop_down(ui, title, tr("Presenter"), left_handed, |ui| {
+            self.presenter_menu(ui)
+        })
+        .on_hover_text(tr("Presenter aids"));
+        let title = self.toolbar_label("⏱", tr("Timers"));
+        drop_down(ui, title, tr("Timers"), left_handed, |ui| {
+            self.timers_menu(ui)
+        })
+        .on_hover_text(tr("Timers"));
+        let title = self.toolbar_label("👥", tr("Session"));
+        drop_down(ui, title, tr("Session"), left_handed, |ui| {
+            self.session_menu(ui)
+        })
+        .on_hover_text(tr("Session"));
+        let title = self.toolbar_label("⚙", tr("Settings"));
+        drop_down(ui, title, tr("Settings"), left_handed, |ui| {
+            self.settings_menu(ui)
+        })
+        .on_hover_text(tr("Settings"));
+    }
+
+    /// The pen's palette and stroke settings, shown when its button is clicked.
+    fn pen_menu(&mut self, ui: &mut egui::Ui) {
+        self.set_tool(Tool::Pen);
+        ui.horizontal_wrapped(|ui| {
+            for (idx, color) in self.palette.iter().copied().enumerate() {
+                let mut btn = egui::Button::new(" ")
+                    .fill(color)
+                    .min_size(Vec2::splat(22.0));
+                if self.active_color == idx {
+                    btn = btn.stroke(Stroke::new(2.0, ui.visuals().strong_text_color()));
+                }
+                let name = trf("Palette color {0}", &[&(idx + 1)]);
+                let selected = Some(self.active_color == idx);
+                if named(ui.add(btn), egui::WidgetType::Button, selected, &name).clicked() {
+                    self.active_color = idx;
+                    close_drop_down(ui);
+                }
+            }
+        });
+        let smoothing = ui.add(
+            egui::Slider::new(&mut self.config.pen_smoothing, 0.0..=1.0).text(tr("Smoothing")),
+        );
+        if smoothing.drag_stopped() || (smoothing.changed() && !smoothing.dragged()) {
+            self.save_config();
+        }
+        ui.checkbox(&mut self.predict_strokes, tr("Predict ahead"))
+            .on_hover_text(tr(
+                "Extend the line along the pen's motion to hide input lag",
+            ));
+    }
+
+    fn presenter_menu(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.cursor_highlight, tr("Cursor highlight"));
+        ui.checkbox(&mut self.click_ripples, tr("Click ripples"));
+        ui.horizontal(|ui| {
+            ui.label(tr("Color:"));
+            egui::color_picker::color_edit_button_srgba(
+                ui,
+                &mut self.highlight_color,
+                egui::color_picker::Alpha::OnlyBlend,
+            );
+        });
+        ui.add(egui::Slider::new(&mut self.highlight_radius, 10.0..=80.0).text(tr("Size")));
+        ui.checkbox(&mut self.pointer_trail, tr("Pointer trail"));
+        ui.add_enabled_ui(self.pointer_trail, |ui| {
+            ui.horizontal(|ui| {
+                egui::color_picker::color_edit_button_srgba(
+                    ui,
+                    &mut self.trail_color,
+                    egui::color_picker::Alpha::OnlyBlend,
+                );
+                ui.add(
+                    egui::Slider::new(&mut self.trail_length, 0.1..=1.5)
+                        .suffix(" s")
+                        .text(tr("Trail length")),
+                );
+            });
+        });
+        ui.checkbox(&mut self.show_keystrokes, tr("Show keystrokes"));
+        let global_keys = ui
+            .add_enabled(
+                self.show_keystrokes,
+                egui::Checkbox::new(
+                    &mut self.config.global_keystrokes,
+                    tr("Include keys typed into other apps"),
+                ),
+            )
+            .on_hover_text(tr("Reads the keyboard system-wide, so it also shows \
+                               passwords typed while it is on"));
+        if global_keys.changed() {
+            self.save_config();
+        }
+        ui.checkbox(&mut self.spotlight, tr("Spotlight"))
+            .on_hover_text(self.shortcut_tip(ui.ctx(), Action::Spotlight));
+        ui.add_enabled(
+            self.spotlight,
+            egui::Slider::new(&mut self.spotlight_radius, SPOTLIGHT_RADIUS_RANGE)
+                .text(tr("Spotlight size")),
+        );
+        ui.add_enabled(
+            self.spotlight,
+            egui::Checkbox::new(&mut self.spotlight_rect, tr("Rectangular spotlight")),
+        );
+        if let Some(err) = &self.global_input_error {
+            ui.colored_label(Color32::LIGHT_RED, err);
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui
+                .button(tr("Add curtain"))
+                .on_hover_text(tr("An opaque panel to cover part of the screen"))
+                .clicked()
+            {
+                let size = CURTAIN_DEFAULT_SIZE * self.config.ui_scale;
+                let offset = self.curtains.len() as f32 * 24.0;
+                let center = ui.ctx().screen_rect().center() + Vec2::splat(offset);
+                self.curtains.push(Curtain {
+                    rect: egui::Rect::from_center_size(center, size),
+                    revealed: false,
+                });
+            }
+            if ui
+                .add_enabled(
+                    !self.curtains.is_empty(),
+                    egui::Button::new(tr("Remove curtains")),
+                )
+                .clicked()
+            {
+                self.curtains.clear();
+            }
+        });
+        ui.horizontal(|ui| {
+            let covered = self.curtains.iter().any(|c| !c.revealed);
+            if ui
+                .add_enabled(covered, egui::Button::new(tr("Reveal next")))
+                .on_hover_text(self.shortcut_tip(ui.ctx(), Action::RevealNext))
+                .clicked()
+            {
+                self.reveal_next();
+            }
+            let revealed = self.curtains.iter().any(|c| c.revealed);
+            if ui
+                .add_enabled(revealed, egui::Button::new(tr("Cover all")))
+                .clicked()
+            {
+                self.curtains.iter_mut().for_each(|c| c.revealed = false);
+            }
+        });
+        ui.separator();
+        self.ticker_settings(ui);
+        ui.separator();
+        self.watermark_settings(ui);
+    }
+
+    fn settings_menu(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(
+            &mut self.idle_passthrough,
+            tr("Click-through overlay in Mouse mode"),
+        )
+        .on_hover_text(tr(
+            "Moves the toolbar into its own window and lets clicks reach the apps below \
+             while no drawing tool is active",
+        ));
+        let locale = self.config.locale;
+        egui::ComboBox::from_label(tr("Language"))
+            .selected_text(locale.name())
+            .show_ui(ui, |ui| {
+                for option in i18n::Locale::ALL {
+                    ui.selectable_value(&mut self.config.locale, option, option.name());
+                }
+            });
+        if self.config.locale != locale {
+            i18n::set_locale(self.config.locale);
+            self.save_config();
+        }
+        let theme = self.config.theme;
+        egui::ComboBox::from_label(tr("Theme"))
+            .selected_text(tr(theme.name()))
+            .show_ui(ui, |ui| {
+                for option in Theme::ALL {
+                    ui.selectable_value(&mut self.config.theme, option, tr(option.name()));
+                }
+            });
+        if self.config.theme != theme {
+            self.save_config();
+        }
+        let before = self.config.toolbar_orientation;
+        egui::ComboBox::from_label(tr("Toolbar layout"))
+            .selected_text(tr(before.name()))
+            .show_ui(ui, |ui| {
+                for orientation in [
+                    Orientation::Auto,
+                    Orientation::Horizontal,
+                    Orientation::Vertical,
+                ] {
+                    ui.selectable_value(
+                        &mut self.config.toolbar_orientation,
+                        orientation,
+                        tr(orientation.name()),
+                    );
+                }
+            })
+            .response
+            .on_hover_text(tr(
+                "Auto stacks the tools in a column while docked to the left or right edge",
+            ));
+        if self.config.toolbar_orientation != before {
+            self.save_config();
+        }
+        let scale = ui.add(
+            egui::Slider::new(&mut self.config.ui_scale, UI_SCALE_RANGE)
+                .step_by(UI_SCALE_STEP as f64)
+                .text(tr("UI scale")),
+        );
+        if scale.drag_stopped() || (scale.changed() && !scale.dragged()) {
+            self.save_config();
+        }
+        if ui
+            .checkbox(
+                &mut self.config.escape_hides,
+                tr("Escape minimizes the overlay"),
+            )
+            .on_hover_text(tr(
+                "Once there is no stroke or polygon left to cancel; Ctrl+Q quits",
+            ))
+            .changed()
+        {
+            self.save_config();
+        }
+        if ui
+            .checkbox(&mut self.config.show_status, tr("Status line"))
+            .on_hover_text(tr("Pointer coordinates and item and point counts"))
+            .changed()
+        {
+            self.save_config();
+        }
+        if ui
+            .checkbox(&mut self.config.left_handed, tr("Left-handed layout"))
+            .on_hover_text(tr("Mirror the toolbar and open its menus to the left"))
+            .changed()
+        {
+            self.save_config();
+        }
+        if ui
+            .checkbox(
+                &mut self.config.toolbar_compact,
+                tr("Compact toolbar (icons only)"),
+            )
+            .changed()
+        {
+            self.save_config();
+        }
+        if ui
+            .checkbox(&mut self.config.toolbar_autohide, tr("Auto-hide toolbar"))
+            .on_hover_text(tr("Collapse the toolbar to its handle when idle; \
+                               hover it or press T to bring it back"))
+            .changed()
+        {
+            self.save_config();
+        }
+        let simplification = ui
+            .add(
+                egui::Slider::new(&mut self.config.stroke_simplification, 0.0..=3.0)
+                    .text(tr("Stroke simplification (px)")),
+            )
+            .on_hover_text(tr(
+                "Drops pointer samples that deviate less than this from the simplified \
+                 stroke; 0 keeps every sample",
+            ));
+        if simplification.drag_stopped() || (simplification.changed() && !simplification.dragged())
+        {
+            self.save_config();
+        }
+        ui.horizontal(|ui| {
+            ui.label(tr("Undo steps:"));
+            let depth = ui
+                .add(egui::DragValue::new(&mut self.config.history_depth).clamp_range(1..=1000))
+                .on_hover_text(tr("How many steps are kept for Undo; the oldest go first"));
+            // Trimming drops steps for good, so a drag passing through a low value on its
+            // way elsewhere must not trim.
+            if depth.drag_stopped() || (depth.changed() && !depth.dragged()) {
+                self.items.set_history_depth(self.config.history_depth);
+                self.save_config();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label(tr("Grid spacing:"));
+            let spacing = ui.add(
+                egui::DragValue::new(&mut self.config.grid_spacing)
+                    .clamp_range(GRID_SPACING_RANGE)
+                    .suffix(" px"),
+            );
+            if spacing.drag_stopped() || (spacing.changed() && !spacing.dragged()) {
+                self.save_config();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label(tr("Soft limits:"));
+            let items = ui.add(
+                egui::DragValue::new(&mut self.config.item_budget)
+                    .clamp_range(100..=1_000_000)
+                    .suffix(tr(" items")),
+            );
+            let points = ui.add(
+                egui::DragValue::new(&mut self.config.point_budget)
+                    .clamp_range(1_000..=10_000_000)
+                    .speed(100)
+                    .suffix(tr(" points")),
+            );
+            if [items, points]
+                .iter()
+                .any(|limit| limit.drag_stopped() || (limit.changed() && !limit.dragged()))
+            {
+                self.save_config();
+            }
+        });
+        if ui
+            .checkbox(
+                &mut self.config.auto_simplify,
+                tr("Simplify your lines when over the point limit"),
+            )
+            .changed()
+        {
+            self.save_config();
+        }
+        let mut pad_enabled = self.companion.is_some();
+        if ui
+            .checkbox(&mut pad_enabled, tr("Phone/tablet drawing pad"))
+            .changed()
+        {
+            self.toggle_companion(ui.ctx(), pad_enabled);
+        }
+        if let Some(server) = &self.companion {
+            ui.label(trf(
+                "Open {0} on a device on this network",
+                &[&server.url()],
+            ));
+        }
+        if let Some(err) = &self.companion_error {
+            ui.colored_label(Color32::LIGHT_RED, err);
+        }
+        if let Some(err) = &self.journal_error {
+            ui.colored_label(Color32::LIGHT_RED, trf("Autosave: {0}", &[err]));
+        }
+        if let Some(err) = &self.config_error {
+            ui.colored_label(Color32::LIGHT_RED, trf("Settings file: {0}", &[err]));
+        }
+    }
+
+    /// Undo, redo, clear, and save.
+    fn history_buttons(&mut self, ui: &mut egui::Ui) {
+        let undo_text = self.undo_description();
+        let undo = ui
+            .add_enabled(
+                undo_text.is_some(),
+                egui::Button::new(self.toolbar_label("⟲", tr("Undo"))),
+            )
+            .on_hover_text(
+                undo_text
+                    .as_deref()
+                    .map_or(tr("Nothing to undo").to_string(), |text| {
+                        self.with_shortcut(ui.ctx(), text, Action::Undo)
+                    }),
+            )
+            .on_disabled_hover_text(tr("Nothing to undo"));
+        let undo_name = undo_text.as_deref().unwrap_or(tr("Undo"));
+        if named(undo, egui::WidgetType::Button, None, undo_name).clicked() {
+            self.undo();
+        }
+        let redo_text = self.redo_description();
+        let redo = ui
+            .add_enabled(
+                redo_text.is_some(),
+                egui::Button::new(self.toolbar_label("⟳", tr("Redo"))),
+            )
+            .on_hover_text(
+                redo_text
+                    .as_deref()
+                    .map_or(tr("Nothing to redo").to_string(), |text| {
+                        self.with_shortcut(ui.ctx(), text, Action::Redo)
+                    }),
+            )
+            .on_disabled_hover_text(tr("Nothing to redo"));
+        let redo_name = redo_text.as_deref().unwrap_or(tr("Redo"));
+        if named(redo, egui::WidgetType::Button, None, redo_name).clicked() {
+            self.redo();
+        }
+        let clear = ui
+            .button(self.toolbar_label("🗑", tr("Clear")))
+            .on_hover_text(tr("Clear"));
+        if named(clear, egui::WidgetType::Button, None, tr("Clear")).clicked() {
+            self.clear();
+        }
+        let save = ui
+            .add_enabled(
+                self.save_job.is_none(),
+                egui::Button::new(self.toolbar_label("💾", tr("Save"))),
+            )
+            .on_hover_text(tr("Save to openpen-board.json"));
+        if named(save, egui::WidgetType::Button, None, tr("Save")).clicked() {
+            self.save_board(ui.ctx());
+        }
+        if let Some(job) = &self.save_job {
+            ui.add(
+                egui::ProgressBar::new(job.progress())
+                    .desired_width(90.0)
+                    .show_percentage(),
+            );
+        }
+    }
+
+    /// Options for the active tool, shown in a strip of their own below the tool buttons.
+    fn tool_options(&mut self, ui: &mut egui::Ui) {
+        match self.tool {
+            Tool::Mouse => {}
+            Tool::Select => {
+                if self.selection.is_empty() {
+                    ui.label(tr(
+                        "Click an item to select it, or drag a box around several; \
+                         Shift adds to the selection",
+                    ));
+                } else {
+                    ui.label(trf("{0} selected", &[&self.selection.len()]));
+                    if ui
+                        .button(tr("Apply current color"))
+                        .on_hover_text(tr("Recolor the selected items"))
+                        .clicked()
+                    {
+                        self.recolor_selection();
+                    }
+                    let text_background = self
+                        .selection
+                        .iter()
+                        .filter_map(|id| self.items.get(*id))
+                        .find_map(|(item, _)| item.text_box())
+                        .map(|text| text.background);
+                    if let Some(current) = text_background {
+                        let left_handed = self.config.left_handed;
+                        drop_down(ui, tr("Background"), tr("Background"), left_handed, |ui| {
+                            self.selection_background_settings(ui, current);
+                        })
+                        .on_hover_text(tr("Background of the selected text boxes"));
+                    }
+                    if self.selection.len() >= 2 {
+                        let count = self.selection.len();
+                        let mut picked = None;
+                        drop_down(
+                            ui,
+                            tr("Align"),
+                            tr("Align"),
+                            self.config.left_handed,
+                            |ui| {
+                                for alignment in Alignment::ALL {
+                                    let button = ui.add_enabled(
+                                        count >= alignment.min_items(),
+                                        egui::Button::new(tr(alignment.label())),
+                                    );
+                                    if button.clicked() {
+                                        picked = Some(alignment);
+                                        close_drop_down(ui);
+                                    }
+                                }
+                            },
+                        );
+                        if let Some(alignment) = picked {
+                            self.align_selection(alignment);
+                        }
+                    }
+                    if ui
+                        .button(tr("Duplicate"))
+                        .on_hover_text(self.shortcut_tip(ui.ctx(), Action::Duplicate))
+                        .clicked()
+                    {
+                        self.duplicate_selection();
+                    }
+                    if ui
+                        .button(tr("Delete"))
+                        .on_hover_text(self.shortcut_tip(ui.ctx(), Action::DeleteSelection))
+                        .clicked()
+                    {
+                        self.delete_selection();
+                    }
+                }
+            }
+            Tool::Pen => {
+                ui.add(egui::Slider::new(&mut self.thickness, 1.0..=24.0).text(tr("Thickness")));
+            }
+            Tool::Polygon => {
+                ui.add(egui::Slider::new(&mut self.thickness, 1.0..=24.0).text(tr("Outline")));
+                self.fill_settings(ui);
+                ui.label(trf(
+                    "{0} points · Enter closes",
+                    &[&self.polygon_points.len()],
+                ));
+            }
+            Tool::Ellipse => {
+                ui.add(egui::Slider::new(&mut self.thickness, 1.0..=24.0).text(tr("Outline")));
+                self.fill_settings(ui);
+            }
+            Tool::Line => {
+                ui.add(egui::Slider::new(&mut self.thickness, 1.0..=24.0).text(tr("Thickness")));
+            }
+            Tool::Marker => {
+                ui.label(trf("Next: {0}", &[&self.next_marker]));
+                if ui
+                    .add_enabled(self.next_marker > 1, egui::Button::new(tr("Restart at 1")))
+                    .clicked()
+                {
+                    self.next_marker = 1;
+                }
+            }
+            Tool::Stamp => {
+                for kind in StampKind::ALL {
+                    let (icon, name) = stamp_label(kind);
+                    let response = named(
+                        ui.selectable_label(self.stamp_kind == kind, icon),
+                        egui::WidgetType::SelectableLabel,
+                        Some(self.stamp_kind == kind),
+                        tr(name),
+                    );
+                    if response.on_hover_text(tr(name)).clicked() {
+                        self.stamp_kind = kind;
+                    }
+                }
+                ui.add(egui::Slider::new(&mut self.stamp_size, STAMP_SIZE_RANGE).text(tr("Size")));
+            }
+            Tool::Ruler => {
+                ui.label(tr("Screen DPI:"));
+                let dpi = ui
+                    .add(
+                        egui::DragValue::new(&mut self.config.ruler_dpi)
+                            .clamp_range(0.0..=1000.0)
+                            .speed(1.0),
+                    )
+                    .on_hover_text(tr(
+                        "Set to show distances in centimetres as well; 0 for pixels only",
+                    ));
+                if dpi.drag_stopped() || (dpi.changed() && !dpi.dragged()) {
+                    self.save_config();
+                }
+            }
+            Tool::Text | Tool::Callout => {
+                if self.tool == Tool::Callout {
+                    ui.label(tr("Drag from what it points at to where the bubble goes"));
+                }
+                ui.label(tr("Text:"));
+                ui.text_edit_singleline(&mut self.text_draft);
+                ui.label(tr("Wrap at:"));
+                let wrap = ui
+                    .add(
+                        egui::DragValue::new(&mut self.config.text_wrap_width)
+                            .clamp_range(0.0..=4000.0)
+                            .speed(4.0)
+                            .suffix(" px"),
+                    )
+                    .on_hover_text(tr(
+                        "Width new text boxes wrap their lines at; 0 breaks lines only at newlines",
+                    ));
+                if wrap.drag_stopped() || (wrap.changed() && !wrap.dragged()) {
+                    self.save_config();
+                }
+                let left_handed = self.config.left_handed;
+                drop_down(ui, tr("Background"), tr("Background"), left_handed, |ui| {
+                    self.text_background_settings(ui);
+                });
+            }
+            Tool::Eraser => {
+                ui.add(
+                    egui::Slider::new(&mut self.eraser_size, 8.0..=80.0).text(tr("Eraser size")),
+                );
+                if ui
+                    .checkbox(&mut self.config.partial_erase, tr("Split strokes"))
+                    .on_hover_text(tr("Erase only the part of a pen stroke under the eraser \
+                                       instead of the whole stroke"))
+                    .changed()
+                {
+                    self.save_config();
+                }
+                if ui
+                    .checkbox(
+                        &mut self.config.secondary_button_erases,
+                        tr("Stylus eraser button"),
+                    )
+                    .on_hover_text(tr("Erase while the stylus eraser end, barrel button, \
+                                       or right mouse button is held down"))
+                    .changed()
+                {
+                    self.save_config();
+                }
+            }
+        }
+        if matches!(
+            self.tool,
+            Tool::Pen | Tool::Polygon | Tool::Ellipse | Tool::Line | Tool::Text | Tool::Callout
+        ) {
+            let mut fades = self.fade_after.contains_key(&self.tool);
+            if ui
+                .checkbox(&mut fades, tr("Fade out after"))
+                .on_hover_text(tr(
+                    "Items drawn with this tool remove themselves after a while",
+                ))
+                .changed()
+            {
+                if fades {
+                    self.fade_after.insert(self.tool, DEFAULT_FADE_AFTER);
+                } else {
+                    self.fade_after.remove(&self.tool);
+                }
+            }
+            if let Some(seconds) = self.fade_after.get_mut(&self.tool) {
+                ui.add(
+                    egui::DragValue::new(seconds)
+                        .clamp_range(1.0..=600.0)
+                        .suffix(" s"),
+                );
+            }
+        }
+    }
+
+    fn timers_menu(&mut self, ui: &mut egui::Ui) {
+        let now = ui.input(|i| i.time);
+        ui.label(RichText::new(tr("Countdown")).strong());
+        let (mut minutes, mut seconds) = (
+            self.config.countdown_seconds / 60,
+            self.config.countdown_seconds % 60,
+        );
+        let changed = ui
+            .horizontal(|ui| {
+                let minutes = ui.add(
+                    egui::DragValue::new(&mut minutes)
+                        .clamp_range(0..=599)
+                        .suffix(" min"),
+                );
+                let seconds = ui.add(
+                    egui::DragValue::new(&mut seconds)
+                        .clamp_range(0..=59)
+                        .suffix(" s"),
+                );
+                minutes.changed() || seconds.changed()
+            })
+            .inner;
+        if changed {
+            self.config.countdown_seconds = (minutes * 60 + seconds).max(1);
+            self.countdown
+                .set_duration(self.config.countdown_seconds as f64);
+            self.save_config();
+        }
+        ui.horizontal(|ui| {
+            if self.countdown.is_running() {
+                if ui.button(tr("Pause")).clicked() {
+                    self.countdown.pause(now);
+                }
+            } else if ui.button(tr("Start")).clicked() {
+                self.start_countdown(now);
+            }
+            if ui.button(tr("Reset")).clicked() {
+                self.countdown.reset();
+            }
+        });
+        ui.checkbox(&mut self.show_countdown, tr("Show countdown"));
+
+        ui.separator();
+        ui.label(RichText::new(tr("Stopwatch")).strong());
+        ui.horizontal(|ui| {
+            let toggle = if self.stopwatch.is_running() {
+                tr("Stop")
+            } else {
+                tr("Start")
+            };
+            if ui
+                .button(toggle)
+                .on_hover_text(self.shortcut_tip(ui.ctx(), Action::Stopwatch))
+                .clicked()
+            {
+                self.stopwatch.toggle(now);
+                self.show_stopwatch = true;
+            }
+            if ui
+                .add_enabled(self.stopwatch.is_running(), egui::Button::new(tr("Lap")))
+                .on_hover_text(self.shortcut_tip(ui.ctx(), Action::Lap))
+                .clicked()
+            {
+                self.stopwatch.lap(now);
+            }
+            if ui.button(tr("Reset")).clicked() {
+                self.stopwatch.reset();
+            }
+        });
+        ui.checkbox(&mut self.show_stopwatch, tr("Show stopwatch"));
+
+        ui.separator();
+        ui.label(RichText::new(tr("Break")).strong());
+        let minutes = ui.add(
+            egui::DragValue::new(&mut self.config.break_minutes)
+                .clamp_range(1..=120)
+                .suffix(" min"),
+        );
+        let message = ui.add(
+            egui::TextEdit::singleline(&mut self.config.break_message)
+                .hint_text(tr("We'll be right back")),
+        );
+        if (minutes.changed() && !minutes.dragged())
+            || minutes.drag_stopped()
+            || message.lost_focus()
+        {
+            self.save_config();
+        }
+        if ui
+            .button(tr("Start break"))
+            .on_hover_text(self.shortcut_tip(ui.ctx(), Action::Break))
+            .clicked()
+        {
+            self.toggle_break(now);
+            close_drop_down(ui);
+        }
+
+        ui.separator();
+        let before = (
+            self.config.show_clock,
+            self.config.clock_corner,
+            self.config.clock_24h,
+            self.config.clock_seconds,
+        );
+        ui.checkbox(
+            &mut self.config.show_clock,
+            RichText::new(tr("Clock")).strong(),
+        );
+        ui.add_enabled_ui(self.config.show_clock, |ui| {
+            egui::ComboBox::from_label(tr("Corner"))
+                .selected_text(tr(self.config.clock_corner.name()))
+                .show_ui(ui, |ui| {
+                    for corner in Corner::ALL {
+                        ui.selectable_value(
+                            &mut self.config.clock_corner,
+                            corner,
+                            tr(corner.name()),
+                        );
+                    }
+                });
+            ui.checkbox(&mut self.config.clock_24h, tr("24-hour time"));
+            ui.checkbox(&mut self.config.clock_seconds, tr("Show seconds"));
+            let opacity = ui.add(
+                egui::Slider::new(&mut self.config.clock_opacity, 0.2..=1.0).text(tr("Opacity")),
+            );
+            if opacity.drag_stopped() || (opacity.changed() && !opacity.dragged()) {
+                self.save_config();
+            }
+        });
+        let after = (
+            self.config.show_clock,
+            self.config.clock_corner,
+            self.config.clock_24h,
+            self.config.clock_seconds,
+        );
+        if after != before {
+            self.save_config();
+        }
+    }
+
+    fn session_menu(&mut self, ui: &mut egui::Ui) {
+        if let Some(session) = &self.session {
+            ui.label(session.label());
+            if !session.is_viewer() {
+                ui.label(trf(
+                    "Participants: {0} · Viewers: {1}",
+                    &[
+                        &session.peer_count(collab::Role::Participant),
+                        &session.peer_count(collab::Role::Viewer),
+                    ],
+                ));
+            }
+            if session.is_host() {
+                let mut locked = session.is_locked();
+                if ui
+                    .checkbox(&mut locked, tr("🔒 Lock canvas to presenter"))
+                    .on_hover_text(tr("Temporarily revoke everyone else's drawing rights"))
+                    .changed()
+                {
+                    session.set_locked(locked);
+                }
+            } else if session.is_locked() && !session.is_viewer() {
+                ui.label(tr("🔒 The presenter has locked the canvas"));
+            }
+            for (text, link) in [
+                ("Participant link: {0}", session.join_link()),
+                ("Viewer link: {0}", session.viewer_link()),
+            ] {
+                let Some(link) = link else { continue };
+                ui.horizontal(|ui| {
+                    ui.label(trf(text, &[&link]));
+                    if ui.small_button(tr("Copy")).clicked() {
+                        ui.output_mut(|o| o.copied_text = link.to_string());
+                    }
+                });
+            }
+            if ui.button(tr("Leave session")).clicked() {
+                self.session = None;
+                self.remote_cursors.clear();
+                close_drop_down(ui);
+            }
+        } else {
+            ui.horizontal(|ui| {
+                ui.label(tr("Name:"));
+                ui.add(egui::TextEdit::singleline(&mut self.display_name).desired_width(140.0));
+            });
+            ui.checkbox(
+                &mut self.broadcast,
+                tr("Allow read-only viewers (broadcast)"),
+            );
+            let joining = self.pending_join.is_some();
+            if ui
+                .add_enabled(!joining, egui::Button::new(tr("Host session")))
+                .clicked()
+            {
+                match collab::Session::host(collab::DEFAULT_PORT, self.broadcast, ui.ctx().clone())
+                {
+                    Ok(session) => self.start_session(session),
+                    Err(e) => self.session_error = Some(trf("Hosting failed: {0}", &[&e])),
+                }
+            }
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.session_addr)
+                        .hint_text(tr("participant or viewer link"))
+                        .desired_width(140.0),
+                );
+                if joining {
+                    ui.spinner();
+                } else if ui.button(tr("Join")).clicked() {
+                    self.session_error = None;
+                    self.pending_join =
+                        Some(collab::Session::join(&self.session_addr, ui.ctx().clone()));
+                }
+            });
+        }
+        if let Some(err) = &self.session_error {
+            ui.colored_label(Color32::LIGHT_RED, err);
+        }
+
+        let authors = self.items.authors();
+        if authors.len() > 1 || self.session.is_some() {
+            ui.separator();
+            ui.label(tr("Annotations by author"));
+            for (author, count) in authors {
+                ui.horizontal(|ui| {
+                    let mut visible = !self.hidden_authors.contains(&author);
+                    let name = if author.is_empty() {
+                        tr("(unknown)")
+                    } else {
+                        &author
+                    };
+                    if ui
+                        .checkbox(&mut visible, format!("{name} ({count})"))
+                        .changed()
+                    {
+                        if visible {
+                            self.hidden_authors.remove(&author);
+                        } else {
+                            self.hidden_authors.insert(author.clone());
+                        }
+                    }
+                    if ui
+                        .add_enabled(
+                            self.export_job.is_none(),
+                            egui::Button::new(tr("Export")).small(),
+                        )
+                        .clicked()
+                    {
+                        self.export_author(ui.ctx(), &author);
+                    }
+                    if !self.read_only() && ui.small_button(tr("Delete")).clicked() {
+                        self.items.remove_where(|_, _, a| a == author);
+                        self.hidden_authors.remove(&author);
+                    }
+                });
+            }
+            if let Some(job) = &self.export_job {
+                ui.add(egui::ProgressBar::new(job.progress()).show_percentage());
+            }
+        }
+    }
+
+    /// Writes one author's items as JSON into the working directory.
+    fn export_author(&mut self, ctx: &egui::Context, author: &str) {
+        let items: Vec<CanvasItem> = self
+            .items
+            .iter_authored()
+            .filter(|(_, a)| *a == author)
+            .map(|(item, _)| item.clone())
+            .collect();
+        let slug: String = author
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect();
+        let path = format!("openpen-{slug}.json");
+        self.export_job = Some(jobs::write_board(
+            ctx,
+            path,
+            items,
+            "Exported {0} items to {1}",
+            "Export failed: {0}",
+        ));
+    }
+
+    /// Saves every visible item to `openpen-board.json`, which `kalam render` can rasterize.
+    fn save_board(&mut self, ctx: &egui::Context) {
+        let items: Vec<CanvasItem> = self
+            .items
+            .iter_authored()
+            .filter(|(_, author)| !self.hidden_authors.contains(*author))
+            .map(|(item, _)| item.clone())
+            .collect();
+        self.save_job = Some(jobs::write_board(
+            ctx,
+            "openpen-board.json".to_string(),
+            items,
+            "Saved {0} items to {1}",
+            "Saving failed: {0}",
+        ));
+    }
+
+    /// Warns once whenever the board grows past the item or point budget and, if
+    /// enabled and points are what is over, simplifies the local user's lines to
+    /// claw points back. Other authors' items are left to their owners.
+    fn enforce_budget(&mut self, now: f64) {
+        self.points.refresh(&self.items);
+        let points = self.points.total();
+        let (item_budget, point_budget) = (self.config.item_budget, self.config.point_budget);
+        if self.items.len() <= item_budget && points <= point_budget {
+            self.over_budget = false;
+            return;
+        }
+        if self.over_budget {
+            return;
+        }
+        self.over_budget = true;
+
+        let mut message = trf(
+            "Board is over budget: {0} items, {1} points",
+            &[&self.items.len(), &points],
+        );
+        if self.config.auto_simplify && points > point_budget && !self.read_only() {
+            let local = self.items.author();
+            let replacements: Vec<_> = self
+                .items
+                .iter_with_ids()
+                .filter(|(_, _, author)| *author == local)
+                .filter_map(|(id, item, _)| {
+                    let simplified = item.simplified(BUDGET_SIMPLIFY_EPSILON)?;
+                    Some((id, simplified))
+                })
+                .collect();
+            self.items.begin_step();
+            for (id, item) in replacements {
+                self.items.execute(Command::Replace { id, item });
+            }
+            self.items.end_step();
+            self.points.refresh(&self.items);
+            let remaining = self.points.total();
+            message += &trf(" (simplified down to {0} points)", &[&remaining]);
+        }
+        self.budget_warning = Some((message, now));
+    }
+
+    /// Grows (positive `steps`) or shrinks the pen or eraser, whichever is active.
+    fn resize_brush(&mut self, steps: f32, now: f64) {
+        match self.tool {
+            Tool::Pen => self.thickness = (self.thickness + steps).clamp(1.0, 24.0),
+            Tool::Eraser => self.eraser_size = (self.eraser_size + steps * 4.0).clamp(8.0, 80.0),
+            Tool::Stamp => {
+                self.stamp_size = (self.stamp_size + steps * 4.0)
+                    .clamp(*STAMP_SIZE_RANGE.start(), *STAMP_SIZE_RANGE.end());
+            }
+            _ => return,
+        }
+        self.brush_resized_at = Some(now);
+    }
+
+    /// Briefly shows the new brush size next to the cursor after it changes.
+    fn brush_size_indicator(&mut self, ctx: &egui::Context) {
+        let now = ctx.input(|i| i.time);
+        let Some(since) = self.brush_resized_at else {
+            return;
+        };
+        let remaining = BRUSH_INDICATOR_SECONDS - (now - since);
+        let label = match self.tool {
+            Tool::Pen => trf("Thickness {0} px", &[&self.thickness.round()]),
+            Tool::Eraser => trf("Eraser {0} px", &[&self.eraser_size.round()]),
+            Tool::Stamp => trf("Stamp {0} px", &[&self.stamp_size.round()]),
+            _ => String::new(),
+        };
+        if remaining <= 0.0 || label.is_empty() {
+            self.brush_resized_at = None;
+            return;
+        }
+        ctx.request_repaint_after(Duration::from_secs_f64(remaining));
+
+        let pos = ctx
+            .input(|i| i.pointer.hover_pos())
+            .unwrap_or_else(|| ctx.screen_rect().center());
+        egui::Area::new("brush_size".into())
+            .fixed_pos(pos + Vec2::new(20.0, 20.0))
+            .order(egui::Order::Foreground)
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(label);
+                });
+            });
+    }
+
+    /// Shows `message` briefly in the top-right corner, so actions taken with the
+    /// keyboard or from another device get visible confirmation.
+    fn toast(&mut self, message: String, now: f64) {
+        if self.toasts.len() == MAX_TOASTS {
+            self.toasts.remove(0);
+        }
+        self.toasts.push((message, now));
+    }
+
+    /// Whether new polygons and ellipses are filled, and with what.
+    fn fill_settings(&mut self, ui: &mut egui::Ui) {
+        let mut changed = ui
+            .checkbox(&mut self.config.shape_fill, tr("Fill"))
+            .changed();
+        let mut color = color_from_rgba(self.config.shape_fill_rgba);
+        ui.add_enabled_ui(self.config.shape_fill, |ui| {
+            if egui::color_picker::color_edit_button_srgba(
+                ui,
+                &mut color,
+                egui::color_picker::Alpha::OnlyBlend,
+            )
+            .on_hover_text(tr("Fill color"))
+            .changed()
+            {
+                self.config.shape_fill_rgba = color.to_array();
+                changed = true;
+            }
+        });
+        if changed {
+            self.save_config();
+        }
+    }
+
+    /// Color and shape of the backdrop behind new text boxes.
+    fn text_background_settings(&mut self, ui: &mut egui::Ui) {
+        let (_, finished) = text_background_editor(ui, &mut self.config.text_background);
+        if finished {
+            self.save_config();
+        }
+    }
+
+    /// Color and shape of the backdrop behind the selected text boxes and callouts,
+    /// starting from the first of them. A change becomes one undo step once the
+    /// pointer is let go, however long the drag that made it.
+    fn selection_background_settings(&mut self, ui: &mut egui::Ui, current: TextBackground) {
+        let mut style = self.selection_background.unwrap_or(current);
+        if text_background_editor(ui, &mut style).0 {
+            self.selection_background = Some(style);
+        }
+    }
+
+    fn apply_selection_background(&mut self, ctx: &egui::Context) {
+        if ctx.input(|i| i.pointer.any_down()) {
+            return;
+        }
+        let Some(style) = self.selection_background.take() else {
+            return;
+        };
+        self.edit_selection(|_, item| match item.text_box_mut() {
+            Some(text) if text.background != style => {
+                text.background = style;
+                true
+            }
+            _ => false,
+        });
+    }
+
+    /// The editor for a placed text box, laid over the box. Enter keeps the changes,
+    /// Shift+Enter starts a new line, and Escape drops them.
+    fn text_editor_area(&mut self, ctx: &egui::Context) {
+        let Some(editor) = &self.text_editor else {
+            return;
+        };
+        if !matches!(self.tool, Tool::Select | Tool::Text | Tool::Callout) {
+            self.finish_text_edit(true);
+            return;
+        }
+        let Some(text) = self
+            .items
+            .get(editor.id)
+            .and_then(|(item, _)| item.text_box())
+        else {
+            self.text_editor = None;
+            return;
+        };
+        let wrap_width = text.wrap_width;
+        let pos = Pos2::new(text.pos[0], text.pos[1]);
+        let color = color_from_rgba(text.rgba);
+        let style = text.background;
+        let Some(editor) = &mut self.text_editor else {
+            return;
+        };
+        let mut done = None;
+        egui::Area::new(egui::Id::new("text_editor"))
+            .fixed_pos(pos)
+            .show(ctx, |ui| {
+                egui::Frame::none()
+                    .fill(color_from_rgba(style.rgba()))
+                    .rounding(style.corner_radius)
+                    .inner_margin(egui::Margin::symmetric(style.padding[0], style.padding[1]))
+                    .show(ui, |ui| {
+                        let width = wrap_width.unwrap_or_else(|| {
+                            let galley = ui.painter().layout_no_wrap(
+                                editor.text.clone(),
+                                egui::FontId::proportional(18.0),
+                                color,
+                            );
+                            galley.size().x.max(120.0) + 20.0
+                        });
+                        let response = ui.add(
+                            egui::TextEdit::multiline(&mut editor.text)
+                                .font(egui::FontId::proportional(18.0))
+                                .text_color(color)
+                                .frame(false)
+                                .desired_rows(1)
+                                .desired_width(width)
+                                .return_key(egui::KeyboardShortcut::new(
+                                    egui::Modifiers::SHIFT,
+                                    egui::Key::Enter,
+                                )),
+                        );
+                        if !editor.focused {
+                            response.request_focus();
+                            editor.focused = true;
+                        } else if response.lost_focus()
+                            && ui.input(|i| i.key_pressed(egui::Key::Escape))
+                        {
+                            done = Some(false);
+                        } else if response.has_focus()
+                            && ui.input(|i| i.key_pressed(egui::Key::Enter) && !i.modifiers.shift)
+                        {
+                            response.surrender_focus();
+                            done = Some(true);
+                        }
+                    });
+            });
+        if let Some(keep) = done {
+            self.finish_text_edit(keep);
+        }
+    }
+
+    fn show_toasts(&mut self, ctx: &egui::Context) {
+        let now = ctx.input(|i| i.time);
+        self.toasts.retain(|(_, since)| now - since < TOAST_SECONDS);
+        let Some((_, oldest)) = self.toasts.first() else {
+            return;
+        };
+        ctx.request_repaint_after(Duration::from_secs_f64(TOAST_SECONDS - (now - oldest)));
+
+        egui::Area::new("toasts".into())
+            .anchor(egui::Align2::RIGHT_TOP, Vec2::new(-18.0, 18.0))
+            .order(egui::Order::Foreground)
+            .interactable(false)
+            .show(ctx, |ui| {
+                ui.with_layout(egui::Layout::top_down(egui::Align::Max), |ui| {
+                    for (message, _) in &self.toasts {
+                        egui::Frame::popup(ui.style()).show(ui, |ui| {
+                            ui.label(message);
+                        });
+                    }
+                });
+            });
+    }
+
+    fn budget_warning_banner(&mut self, ctx: &egui::Context) {
+        let now = ctx.input(|i| i.time);
+        let Some((message, since)) = &self.budget_warning else {
+            return;
+        };
+        let remaining = BUDGET_WARNING_SECONDS - (now - since);
+        if remaining <= 0.0 {
+            self.budget_warning = None;
+            return;
+        }
+        ctx.request_repaint_after(Duration::from_secs_f64(remaining));
+
+        egui::Area::new("budget_warning".into())
+            .anchor(egui::Align2::CENTER_TOP, Vec2::new(0.0, 18.0))
+            .order(egui::Order::Foreground)
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.colored_label(Color32::from_rgb(255, 190, 80), format!("⚠ {message}"));
+                });
+            });
+    }
+
+    /// Appends this frame's document changes to the autosave journal.
+    fn write_journal(&mut self, ctx: &egui::Context) {
+        let events: Vec<Event> = self.journal_events.try_iter().collect();
+        if let Some(journal) = &mut self.journal {
+            if let Err(e) = journal.record(&events, &self.items) {
+                self.journal_error = Some(e.to_string());
+            }
+            // Come back to sync the last changes even if nothing else happens.
+            if !journal.is_synced() {
+                ctx.request_repaint_after(journal::SYNC_INTERVAL);
+            }
+        }
+    }
+
+    fn poll_jobs(&mut self, now: f64) {
+        if let Some(status) = self.save_job.as_ref().and_then(jobs::Job::poll) {
+            self.toast(status, now);
+            self.save_job = None;
+        }
+        if let Some(status) = self.export_job.as_ref().and_then(jobs::Job::poll) {
+            self.toast(status, now);
+            self.export_job = None;
+        }
+    }
+
+    fn start_session(&mut self, session: collab::Session) {
+        self.session_error = None;
+        // Offer everything we have, including edits made while disconnected; the
+        // host merges it and relays it on.
+        self.items.take_outbox();
+        if session.is_viewer() {
+            self.tool = Tool::Mouse;
+            self.polygon_points.clear();
+        } else {
+            if let Some(participant) = session.participant() {
+                self.active_color = self.participant_color_index(participant);
+            }
+            session.send(&collab::WireMessage::Ops(self.items.state()));
+        }
+        self.session = Some(session);
+    }
+
+    fn sync_session(&mut self, ctx: &egui::Context) {
+        if let Some(result) = self
+            .pending_join
+            .as_ref()
+            .and_then(collab::PendingJoin::poll)
+        {
+            self.pending_join = None;
+            match result {
+                Ok(session) => self.start_session(session),
+                Err(e) => self.session_error = Some(trf("Joining failed: {0}", &[&e])),
+            }
+        }
+        let outbox = self.items.take_outbox();
+        let Some(session) = &mut self.session else {
+            return;
+        };
+        if !outbox.is_empty() {
+            session.send(&collab::WireMessage::Ops(outbox));
+        }
+
+        let (pointer, now) = ctx.input(|i| (i.pointer.hover_pos(), i.time));
+        if let Some(participant) = session.participant().filter(|_| !session.is_viewer()) {
+            let pos = pointer.map(|p| [p.x, p.y]);
+            let moved = pos != self.last_cursor_sent.1;
+            // Throttle while moving and re-send now and then so peers don't expire us.
+            if (moved && now - self.last_cursor_sent.0 > CURSOR_SEND_INTERVAL)
+                || now - self.last_cursor_sent.0 > CURSOR_HEARTBEAT
+            {
+                session.send(&collab::WireMessage::Cursor {
+                    participant,
+                    name: self.display_name.clone(),
+                    pos,
+                });
+                self.last_cursor_sent = (now, pos);
+            }
+        }
+
+        let mut disconnected = None;
+        let mut assigned = None;
+        let mut peer_notices = Vec::new();
+        for event in session.poll() {
+            match event {
+                collab::SessionEvent::Received(collab::WireMessage::Ops(ops)) => {
+                    for op in ops {
+                        self.items.apply(op);
+                    }
+                }
+                collab::SessionEvent::Received(collab::WireMessage::Cursor {
+                    participant,
+                    name,
+                    pos,
+                }) => match pos {
+                    Some(pos) => {
+                        self.remote_cursors.insert(
+                            participant,
+                            RemoteCursor {
+                                name,
+                                pos: Pos2::new(pos[0], pos[1]),
+                                seen: now,
+                            },
+                        );
+                    }
+                    None => {
+                        self.remote_cursors.remove(&participant);
+                    }
+                },
+                collab::SessionEvent::Received(
+                    collab::WireMessage::Welcome { .. } | collab::WireMessage::Lock { .. },
+                ) => {}
+                collab::SessionEvent::Rejected { participant, ops } => {
+                    let replies = self.items.reject(ops);
+                    if !replies.is_empty() {
+                        session.send_to(participant, &collab::WireMessage::Ops(replies));
+                    }
+                    let outbox = self.items.take_outbox();
+                    if !outbox.is_empty() {
+                        session.send(&collab::WireMessage::Ops(outbox));
+                    }
+                }
+                collab::SessionEvent::Assigned(participant) => assigned = Some(participant),
+                collab::SessionEvent::PeerJoined => {
+                    session.send(&collab::WireMessage::Ops(self.items.state()));
+                    peer_notices.push(tr("Someone joined the session"));
+                }
+                collab::SessionEvent::PeerLeft => {
+                    peer_notices.push(tr("Someone left the session"));
+                }
+                collab::SessionEvent::Disconnected(reason) => disconnected = Some(reason),
+            }
+        }
+        self.remote_cursors
+            .retain(|_, cursor| now - cursor.seen < CURSOR_HEARTBEAT * 3.0);
+        // Wake up for heartbeats and to expire cursors of peers that went quiet.
+        ctx.request_repaint_after(Duration::from_secs_f64(CURSOR_HEARTBEAT));
+
+        if let Some(participant) = assigned {
+            self.active_color = self.participant_color_index(participant);
+        }
+        if let Some(reason) = disconnected {
+            self.session = None;
+            self.remote_cursors.clear();
+            self.session_error = Some(trf("Session ended: {0}", &[&reason]));
+            self.toast(trf("Session ended: {0}", &[&reason]), now);
+        }
+        for notice in peer_notices {
+            self.toast(notice.to_string(), now);
+        }
+    }
+
+    /// Participants get palette colors in join order, so everyone agrees on who is which color.
+    fn participant_color_index(&self, participant: usize) -> usize {
+        participant % self.palette.len()
+    }
+
+    fn draw_remote_cursors(&self, ctx: &egui::Context) {
+        if self.remote_cursors.is_empty() {
+            return;
+        }
+        let painter = ctx.layer_painter(egui::LayerId::new(
+            egui::Order::Foreground,
+            egui::Id::new("remote_cursors"),
+        ));
+        for (participant, cursor) in &self.remote_cursors {
+            let color = self.palette[self.participant_color_index(*participant)];
+            painter.circle(cursor.pos, 6.0, color, Stroke::new(2.0, Color32::BLACK));
+            let label = painter.layout_no_wrap(
+                cursor.name.clone(),
+                FontId::proportional(14.0),
+                Color32::BLACK,
+            );
+            let rect = egui::Rect::from_min_size(
+                cursor.pos + Vec2::new(10.0, 8.0),
+                label.size() + Vec2::new(10.0, 4.0),
+            );
+            painter.rect_filled(rect, 4.0, color);
+            painter.galley(rect.min + Vec2::new(5.0, 2.0), label, Color32::BLACK);
+        }
+    }
+
+    fn toggle_companion(&mut self, ctx: &egui::Context, enabled: bool) {
+        self.companion_error = None;
+        self.companion = None;
+        if enabled {
+            match companion::CompanionServer::start(companion::DEFAULT_PORT, ctx.clone()) {
+                Ok(server) => self.companion = Some(server),
+                Err(e) => self.companion_error = Some(trf("Drawing pad failed: {0}", &[&e])),
+            }
+        }
+    }
+
+    /// Maps strokes from the companion page (normalized coordinates) onto the overlay,
+    /// and runs the timer and break commands sent to it.
+    fn receive_remote_input(&mut self, ctx: &egui::Context) {
+        let Some(server) = &self.companion else {
+            return;
+        };
+        let (strokes, commands) = server.poll();
+        let now = ctx.input(|i| i.time);
+        for command in commands {
+            self.run_remote_command(command, now);
+        }
+        let rect = ctx.screen_rect();
+        for stroke in strokes {
+            if stroke.points.len() < 2 {
+                continue;
+            }
+            let points = stroke
+                .points
+                .iter()
+                .map(|p| {
+                    let pos = rect.lerp_inside(Vec2::new(p[0], p[1]));
+                    [pos.x, pos.y]
+                })
+                .collect();
+            let stroke = self.stroke_from_points(points, &[]);
+            self.push_item(CanvasItem::Stroke(stroke));
+        }
+    }
+
+    fn erase_near(&mut self, center: Pos2) {
+        if self.hide_all {
+            return;
+        }
+        let hidden = &self.hidden_authors;
+        let cache = &self.shape_cache;
+        let partial = self.config.partial_erase;
+        let local = self.items.author().to_string();
+        let mut erased_markers = Vec::new();
+        let mut split = Vec::new();
+        self.items.remove_where(|id, item, author| {
+            // Hidden and offscreen items are out of the eraser's reach. The cached
+            // bounds rule out most items before their points are looked at.
+            if hidden.contains(author) || !cache.may_hit(id, center, self.eraser_size) {
+                return false;
+            }
+            if let (true, CanvasItem::Stroke(path)) = (partial, item) {
+                if let Some(pieces) = path.erase_circle([center.x, center.y], self.eraser_size) {
+                    split.push((id, pieces));
+                }
+                return false;
+            }
+            let erase = item.is_near([center.x, center.y], self.eraser_size, cache.text_size(id));
+            if let (true, CanvasItem::Marker(marker)) = (erase, item) {
+                if author == local {
+                    erased_markers.push(marker.number);
+                }
+            }
+            erase
+        });
+        for (id, pieces) in split {
+            let pieces = pieces.into_iter().map(CanvasItem::Stroke).collect();
+            self.items.execute(Command::Split { id, pieces });
+        }
+        self.renumber_markers(&erased_markers);
+    }
+
+    /// Lifts the first curtain, in the order they were added, that still covers something.
+    fn reveal_next(&mut self) {
+        if let Some(curtain) = self.curtains.iter_mut().find(|c| !c.revealed) {
+            curtain.revealed = true;
+        }
+    }
+
+    /// Opaque panels over the board. They are not board items, so undo and clear leave
+    /// them alone; drag one to slide it, its corner to resize it, or × to remove it.
+    /// With several, each shows its place in the reveal order.
+    fn curtains(&mut self, ctx: &egui::Context) {
+        let screen = ctx.screen_rect();
+        let numbered = self.curtains.len() > 1;
+        let mut removed = None;
+        for (idx, curtain) in self.curtains.iter_mut().enumerate() {
+            if curtain.revealed {
+                continue;
+            }
+            let rect = &mut curtain.rect;
+            egui::Area::new(egui::Id::new("curtain").with(idx))
+                .order(egui::Order::Middle)
+                .movable(false)
+                .current_pos(rect.min)
+                .constrain(false)
+                .show(ctx, |ui| {
+                    let (body, response) = ui.allocate_exact_size(rect.size(), egui::Sense::drag());
+                    ui.painter().rect(
+                        body,
+                        4.0,
+                        Color32::from_gray(CURTAIN_GRAY),
+                        Stroke::new(1.0, Color32::from_gray(CURTAIN_GRAY + 40)),
+                    );
+                    if numbered {
+                        ui.painter().text(
+                            body.min + Vec2::splat(CURTAIN_GRIP / 2.0),
+                            egui::Align2::LEFT_TOP,
+                            (idx + 1).to_string(),
+                            FontId::proportional(CURTAIN_GRIP),
+                            Color32::from_gray(CURTAIN_GRAY + 80),
+                        );
+                    }
+                    if response.dragged() {
+                        *rect = rect.translate(response.drag_delta());
+                    }
+                    if response.hovered() || response.dragged() {
+                        ui.ctx().set_cursor_icon(egui::CursorIcon::Grab);
+                    }
+
+                    let grip =
+                        egui::Rect::from_min_max(body.max - Vec2::splat(CURTAIN_GRIP), body.max);
+                    let resize = ui.interact(grip, response.id.with("resize"), egui::Sense::drag());
+                    ui.painter().text(
+                        grip.center(),
+                        egui::Align2::CENTER_CENTER,
+                        "◢",
+                        FontId::proportional(CURTAIN_GRIP),
+                        Color32::from_gray(CURTAIN_GRAY + 80),
+                    );
+                    if resize.dragged() {
+                        rect.max += resize.drag_delta();
+                        rect.max = rect.max.max(rect.min + Vec2::splat(CURTAIN_GRIP * 2.0));
+                    }
+                    if resize.hovered() || resize.dragged() {
+                        ui.ctx().set_cursor_icon(egui::CursorIcon::ResizeNwSe);
+                    }
+
+                    let close = egui::Rect::from_min_size(
+                        Pos2::new(body.max.x - CURTAIN_GRIP, body.min.y),
+                        Vec2::splat(CURTAIN_GRIP),
+                    );
+                    let close = ui.put(close, egui::Button::new("×").frame(false));
+                    if named(close, egui::WidgetType::Button, None, tr("Remove curtain"))
+                        .on_hover_text(tr("Remove curtain"))
+                        .clicked()
+                    {
+                        removed = Some(idx);
+                    }
+                });
+            // Keep at least a corner on screen so a curtain can always be dragged back.
+            let visible = screen.shrink(CURTAIN_GRIP);
+            *rect = rect.translate(Vec2::new(
+                (visible.min.x - rect.max.x).max(0.0) + (visible.max.x - rect.min.x).min(0.0),
+                (visible.min.y - rect.max.y).max(0.0) + (visible.max.y - rect.min.y).min(0.0),
+            ));
+        }
+        if let Some(idx) = removed {
+            self.curtains.remove(idx);
+        }
+    }
+
+    fn ticker_settings(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.show_ticker, RichText::new(tr("Ticker")).strong());
+        let before = (self.config.ticker_edge, self.config.ticker_rgba);
+        let text = ui.add(
+            egui::TextEdit::multiline(&mut self.config.ticker_text)
+                .desired_rows(2)
+                .hint_text(tr("Announcement to scroll across the screen")),
+        );
+        let speed = ui
+            .add(egui::Slider::new(&mut self.config.ticker_speed, 30.0..=400.0).text(tr("Speed")));
+        ui.horizontal(|ui| {
+            for edge in [Edge::Top, Edge::Bottom] {
+                let name = if edge == Edge::Top {
+                    tr("Top")
+                } else {
+                    tr("Bottom")
+                };
+                ui.selectable_value(&mut self.config.ticker_edge, edge, name);
+            }
+            let mut color = Color32::from_rgba_unmultiplied(
+                self.config.ticker_rgba[0],
+                self.config.ticker_rgba[1],
+                self.config.ticker_rgba[2],
+                self.config.ticker_rgba[3],
+            );
+            if egui::color_picker::color_edit_button_srgba(
+                ui,
+                &mut color,
+                egui::color_picker::Alpha::OnlyBlend,
+            )
+            .changed()
+            {
+                self.config.ticker_rgba = color.to_array();
+            }
+        });
+        let after = (self.config.ticker_edge, self.config.ticker_rgba);
+        if text.lost_focus()
+            || speed.drag_stopped()
+            || (speed.changed() && !speed.dragged())
+            || after != before
+        {
+            self.save_config();
+        }
+    }
+
+    fn watermark_settings(&mut self, ui: &mut egui::Ui) {
+        ui.label(RichText::new(tr("Watermark")).strong());
+        let corner = self.config.watermark_corner;
+        let text = ui.add(
+            egui::TextEdit::singleline(&mut self.config.watermark)
+                .hint_text(tr("Attribution shown over everything")),
+        );
+        ui.add_enabled_ui(!self.config.watermark.trim().is_empty(), |ui| {
+            egui::ComboBox::from_id_source("watermark_corner")
+                .selected_text(tr(corner.name()))
+                .show_ui(ui, |ui| {
+                    for option in Corner::ALL {
+                        ui.selectable_value(
+                            &mut self.config.watermark_corner,
+                            option,
+                            tr(option.name()),
+                        );
+                    }
+                });
+            let opacity = ui.add(
+                egui::Slider::new(&mut self.config.watermark_opacity, 0.1..=1.0)
+                    .text(tr("Opacity")),
+            );
+            if opacity.drag_stopped() || (opacity.changed() && !opacity.dragged()) {
+                self.save_config();
+            }
+        });
+        if text.lost_focus() || self.config.watermark_corner != corner {
+            self.save_config();
+        }
+    }
+
+    /// Drawn above the board and every control, so it shows up in screenshots and
+    /// recordings of the screen.
+    fn draw_watermark(&self, ctx: &egui::Context) {
+        let text = self.config.watermark.trim();
+        if text.is_empty() {
+            return;
+        }
+        let painter = ctx.layer_painter(egui::LayerId::new(
+            egui::Order::Tooltip,
+            egui::Id::new("watermark"),
+        ));
+        let (align, offset) = self.config.watermark_corner.anchor(18.0);
+        painter.text(
+            align.pos_in_rect(&ctx.screen_rect()) + offset,
+            align,
+            text,
+            FontId::proportional(WATERMARK_TEXT_SIZE * self.config.ui_scale),
+            Color32::WHITE.gamma_multiply(self.config.watermark_opacity),
+        );
+    }
+
+    /// A strip along the top or bottom edge with the ticker text scrolling right to left.
+    /// It sits above the board but below the toolbar and other controls.
+    fn draw_ticker(&self, ctx: &egui::Context) {
+        let text = self
+            .config
+            .ticker_text
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        if text.is_empty() {
+            return;
+        }
+        let painter = ctx.layer_painter(egui::LayerId::new(
+            egui::Order::Background,
+            egui::Id::new("ticker"),
+        ));
+        let [r, g, b, a] = self.config.ticker_rgba;
+        let galley = painter.layout_no_wrap(
+            text,
+            FontId::proportional(TICKER_TEXT_SIZE * self.config.ui_scale),
+            Color32::from_rgba_unmultiplied(r, g, b, a),
+        );
+        let screen = ctx.screen_rect();
+        let height = galley.size().y + 2.0 * TICKER_PADDING;
+        let strip = if self.config.ticker_edge == Edge::Top {
+            egui::Rect::from_min_size(screen.min, Vec2::new(screen.width(), height))
+        } else {
+            egui::Rect::from_min_max(Pos2::new(screen.min.x, screen.max.y - height), screen.max)
+        };
+        painter.rect_filled(
+            strip,
+            0.0,
+            Color32::from_black_alpha(TICKER_BACKGROUND_ALPHA),
+        );
+
+        // Enters at the right edge and wraps around once it has left on the left.
+        let travel = screen.width() + galley.size().x;
+        let offset = (ctx.input(|i| i.time) as f32 * self.config.ticker_speed) % travel;
+        let pos = Pos2::new(strip.max.x - offset, strip.min.y + TICKER_PADDING);
+        painter
+            .with_clip_rect(strip)
+            .galley(pos, galley, Color32::WHITE);
+        ctx.request_repaint();
+    }
+
+    /// Watches the pointer through the OS while click-through mode keeps it from the
+    /// overlay and an aid needs to follow it, and the keyboard while the keystroke
+    /// display is set to show keys typed into other apps.
+    fn watch_global_input(&mut self, ctx: &egui::Context) {
+        let pointer = self.passthrough_active
+            && (self.cursor_highlight
+                || self.click_ripples
+                || self.pointer_trail
+                || self.spotlight);
+        let keys = self.show_keystrokes && self.config.global_keystrokes;
+        if !pointer && !keys {
+            self.global_input = None;
+        } else if self.global_input.is_none() && self.global_input_error.is_none() {
+            match GlobalInput::start(ctx.clone()) {
+                Ok(input) => self.global_input = Some(input),
+                Err(e) => self.global_input_error = Some(e),
+            }
+        }
+        self.global_events.clear();
+        if let Some(input) = &mut self.global_input {
+            input.set_keys(keys);
+            self.global_events = input.poll();
+        }
+    }
+
+    fn draw_presenter_aids(&mut self, ctx: &egui::Context) {
+        let (mut pointer, now, mut pressed) =
+            ctx.input(|i| (i.pointer.latest_pos(), i.time, i.pointer.any_pressed()));
+        // Every move since the last frame, so quick flicks leave a smooth trail.
+        let mut moves: Vec<Pos2> = ctx.input(|i| {
+            i.events
+                .iter()
+                .filter_map(|event| match event {
+                    egui::Event::PointerMoved(pos) => Some(*pos),
+                    _ => None,
+                })
+                .collect()
+        });
+        if let (true, Some(input)) = (self.passthrough_active, &self.global_input) {
+            pressed = self
+                .global_events
+                .iter()
+                .any(|event| matches!(event, GlobalEvent::Pressed(_)));
+            moves = self
+                .global_events
+                .iter()
+                .filter_map(|event| match *event {
+                    GlobalEvent::Moved(pos) => Some(GlobalInput::to_overlay(ctx, pos)),
+                    _ => None,
+                })
+                .collect();
+            pointer = input.pointer().map(|pos| GlobalInput::to_overlay(ctx, pos));
+        }
+
+        if self.click_ripples && pressed {
+            if let Some(pos) = pointer {
+                self.ripples.push((pos, now));
+            }
+        }
+        self.ripples
+            .retain(|(_, start)| now - start < RIPPLE_SECONDS);
+        if !self.ripples.is_empty() {
+            ctx.request_repaint();
+        }
+
+        if self.pointer_trail {
+            self.trail.extend(moves.into_iter().map(|pos| (pos, now)));
+        }
+        let trail_length = self.trail_length as f64;
+        self.trail.retain(|(_, at)| now - at < trail_length);
+        if !self.trail.is_empty() {
+            ctx.request_repaint();
+        }
+
+        // Drawn on the foreground layer so the aids stay visible above the toolbar too.
+        let painter = ctx.layer_painter(egui::LayerId::new(
+            egui::Order::Foreground,
+            egui::Id::new("presenter_aids"),
+        ));
+
+        if self.cursor_highlight {
+            if let Some(pos) = pointer {
+                painter.circle(
+                    pos,
+                    self.highlight_radius,
+                    self.highlight_color.gamma_multiply(0.25),
+                    Stroke::new(3.0, self.highlight_color),
+                );
+            }
+        }
+
+        if self.spotlight {
+            if let Some(pos) = pointer {
+                let dim = Color32::from_black_alpha(SPOTLIGHT_DIM_ALPHA);
+                let screen = ctx.screen_rect();
+                if self.spotlight_rect {
+                    let half = Vec2::new(SPOTLIGHT_RECT_ASPECT, 1.0) * self.spotlight_radius;
+                    let lit = egui::Rect::from_center_size(pos, half * 2.0);
+                    // Bands above and below span the screen; the sides fill the gap between.
+                    for band in [
+                        egui::Rect::from_x_y_ranges(screen.x_range(), screen.top()..=lit.top()),
+                        egui::Rect::from_x_y_ranges(
+                            screen.x_range(),
+                            lit.bottom()..=screen.bottom(),
+                        ),
+                        egui::Rect::from_x_y_ranges(screen.left()..=lit.left(), lit.y_range()),
+                        egui::Rect::from_x_y_ranges(lit.right()..=screen.right(), lit.y_range()),
+                    ] {
+                        if band.is_positive() {
+                            painter.rect_filled(band, 0.0, dim);
+                        }
+                    }
+                } else {
+                    // A ring wide enough to reach every corner leaves only the circle undimmed.
+                    let reach = screen.size().length() * 2.0;
+                    painter.circle_stroke(
+                        pos,
+                        self.spotlight_radius + reach / 2.0,
+                        Stroke::new(reach, dim),
+                    );
+                }
+            }
+        }
 
-    eframe::run_native(
-        "OpenPen",
-        native_options,
-        Box::new(|cc| {
-            cc.egui_ctx.set_visuals(egui::Visuals::dark());
-            Box::<OpenPenApp>::default()
-        }),
-    )
-}
+        // Segments thin out and fade towards the oldest end, like a comet's tail.
+        for pair in self.trail.windows(2) {
+            let [(from, _), (to, at)] = [pair[0], pair[1]];
+            let fresh = 1.0 - ((now - at) / trail_length) as f32;
+            painter.line_segment(
+                [from, to],
+                Stroke::new(TRAIL_WIDTH * fresh, self.trail_color.gamma_multiply(fresh)),
+            );
+        }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Tool {
-    Mouse,
-    Pen,
-    Polygon,
-    Text,
-    Eraser,
-}
+        for (pos, start) in &self.ripples {
+            let t = ((now - start) / RIPPLE_SECONDS) as f32;
+            painter.circle_stroke(
+                *pos,
+                self.highlight_radius * (0.3 + 1.2 * t),
+                Stroke::new(3.0, self.highlight_color.gamma_multiply(1.0 - t)),
+            );
+        }
+    }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct StrokePath {
-    points: Vec<[f32; 2]>,
-    rgba: [u8; 4],
-    thickness: f32,
-}
+    /// Pointer position, item and point counts, and the size of the selection, along
+    /// the bottom edge.
+    fn status_line(&self, ctx: &egui::Context) {
+        let points = self.points.total();
+        let pointer = ctx
+            .input(|i| i.pointer.hover_pos())
+            .map_or("–".to_string(), |pos| {
+                format!("{:.0}, {:.0}", pos.x, pos.y)
+            });
+        let mut status = trf(
+            "{0} · {1} items · {2} points · UI {3}%",
+            &[
+                &pointer,
+                &self.items.len(),
+                &points,
+                &(self.config.ui_scale * 100.0).round(),
+            ],
+        );
+        if let Some(bounds) = self.selection_bounds() {
+            let (width, height) = (bounds.width().round(), bounds.height().round());
+            status += &trf(
+                " · {0} selected, {1} × {2}",
+                &[&self.selection.len(), &width, &height],
+            );
+        }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct PolygonShape {
-    points: Vec<[f32; 2]>,
-    rgba: [u8; 4],
-    thickness: f32,
-}
+        egui::Area::new("status_line".into())
+            .anchor(egui::Align2::LEFT_BOTTOM, Vec2::new(12.0, -12.0))
+            .order(egui::Order::Foreground)
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(RichText::new(status).monospace());
+                });
+            });
+    }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct TextBox {
-    pos: [f32; 2],
-    text: String,
-    rgba: [u8; 4],
-}
+    /// Debug readout for slowdown reports; `cpu_usage` is the previous frame's CPU time.
+    fn perf_hud(&self, ctx: &egui::Context, cpu_usage: Option<f32>) {
+        let points = self.points.total();
+        let text_bytes: usize = self
+            .items
+            .iter()
+            .map(|item| match item {
+                CanvasItem::Text(t) => t.text.len(),
+                CanvasItem::Callout(callout) => callout.text.text.len(),
+                _ => 0,
+            })
+            .sum();
+        let cache = self.shape_cache.stats();
+        let item_bytes = self.items.len() * std::mem::size_of::<CanvasItem>()
+            + points * std::mem::size_of::<[f32; 2]>()
+            + text_bytes;
+        let lines = [
+            format!(
+                "frame   {}",
+                cpu_usage.map_or("–".to_string(), |s| format!("{:.2} ms", s * 1000.0))
+            ),
+            format!("items   {}", self.items.len()),
+            format!("points  {points}"),
+            format!(
+                "tess    {:.2} ms ({} cached, LOD {})",
+                cache.tessellation_time.as_secs_f64() * 1000.0,
+                cache.meshes,
+                cache.level_of_detail
+            ),
+            format!(
+                "memory  ~{:.1} MiB",
+                (item_bytes + cache.bytes) as f64 / (1024.0 * 1024.0)
+            ),
+        ];
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-enum CanvasItem {
-    Stroke(StrokePath),
-    Polygon(PolygonShape),
-    Text(TextBox),
-}
+        egui::Area::new("perf_hud".into())
+            .anchor(egui::Align2::LEFT_TOP, Vec2::new(12.0, 12.0))
+            .order(egui::Order::Foreground)
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    for line in lines {
+                        ui.label(RichText::new(line).monospace());
+                    }
+                });
+            });
+    }
 
-impl CanvasItem {
-    fn draw(&self, painter: &egui::Painter) {
-        match self {
-            CanvasItem::Stroke(path) => {
-                let points: Vec<Pos2> = path.points.iter().map(|p| Pos2::new(p[0], p[1])).collect();
-                if points.len() > 1 {
-                    painter.add(Shape::line(
-                        points,
-                        Stroke::new(path.thickness, color_from_rgba(path.rgba)),
-                    ));
+    fn run_remote_command(&mut self, command: companion::RemoteCommand, now: f64) {
+        use companion::RemoteCommand;
+        match command {
+            RemoteCommand::CountdownStart => self.start_countdown(now),
+            RemoteCommand::CountdownPause => self.countdown.pause(now),
+            RemoteCommand::CountdownReset => self.countdown.reset(),
+            RemoteCommand::StopwatchStart | RemoteCommand::StopwatchStop => {
+                if self.stopwatch.is_running() != (command == RemoteCommand::StopwatchStart) {
+                    self.stopwatch.toggle(now);
                 }
+                self.show_stopwatch = true;
             }
-            CanvasItem::Polygon(poly) => {
-                let points: Vec<Pos2> = poly.points.iter().map(|p| Pos2::new(p[0], p[1])).collect();
-                if points.len() > 2 {
-                    painter.add(Shape::closed_line(
-                        points,
-                        Stroke::new(poly.thickness, color_from_rgba(poly.rgba)),
-                    ));
+            RemoteCommand::StopwatchLap => self.mark_lap(now),
+            RemoteCommand::StopwatchReset => self.stopwatch.reset(),
+            RemoteCommand::BreakStart | RemoteCommand::BreakEnd => {
+                if self.on_break.is_some() != (command == RemoteCommand::BreakStart) {
+                    self.toggle_break(now);
                 }
             }
-            CanvasItem::Text(t) => {
-                let pos = Pos2::new(t.pos[0], t.pos[1]);
-                painter.rect_filled(
-                    egui::Rect::from_min_size(
-                        pos,
-                        Vec2::new((t.text.len() as f32 * 9.0) + 14.0, 30.0),
-                    ),
-                    6.0,
-                    Color32::from_rgba_premultiplied(10, 10, 10, 140),
-                );
-                painter.text(
-                    pos + Vec2::new(7.0, 15.0),
-                    Align2::LEFT_CENTER,
-                    &t.text,
-                    FontId::proportional(18.0),
-                    color_from_rgba(t.rgba),
-                );
-            }
         }
     }
-}
-
-fn color_from_rgba(rgba: [u8; 4]) -> Color32 {
-    Color32::from_rgba_premultiplied(rgba[0], rgba[1], rgba[2], rgba[3])
-}
 
-struct OpenPenApp {
-    palette: Vec<Color32>,
-    active_color: usize,
-    thickness: f32,
-    tool: Tool,
-    drawing: Vec<Pos2>,
-    polygon_points: Vec<Pos2>,
-    items: Vec<CanvasItem>,
-    redo_stack: Vec<CanvasItem>,
-    eraser_size: f32,
-    text_draft: String,
-}
+    /// Starts the countdown, from the top if it has run out, and shows it.
+    fn start_countdown(&mut self, now: f64) {
+        if self.countdown.remaining(now) == 0.0 {
+            self.countdown.reset();
+        }
+        self.countdown.start(now);
+        self.show_countdown = true;
+    }
 
-impl OpenPenApp {
-    fn set_tool(&mut self, tool: Tool) {
-        self.tool = tool;
+    fn mark_lap(&mut self, now: f64) {
+        self.stopwatch.lap(now);
+        if let Some(lap) = self.stopwatch.laps().last() {
+            let count = self.stopwatch.laps().len();
+            self.toast(
+                trf("Lap {0}: {1}", &[&count, &timers::format_elapsed(*lap)]),
+                now,
+            );
+        }
     }
 
-    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
-        if ctx.input(|i| i.key_pressed(egui::Key::Num1) || i.key_pressed(egui::Key::F1)) {
-            self.set_tool(Tool::Pen);
+    /// Hides the board behind a dimmed screen with a countdown, or brings it back. The
+    /// board, tool and anything half-finished are left as they were.
+    fn toggle_break(&mut self, now: f64) {
+        if self.on_break.take().is_none() {
+            let mut countdown = timers::Countdown::new(self.config.break_minutes as f64 * 60.0);
+            countdown.start(now);
+            self.on_break = Some(countdown);
+            // The pen is lifted with the board hidden; a drag in progress is lost.
+            self.drawing.clear();
+            self.live_stroke.clear();
         }
-        if ctx.input(|i| i.key_pressed(egui::Key::Num2) || i.key_pressed(egui::Key::F2)) {
-            self.set_tool(Tool::Polygon);
+    }
+
+    fn break_screen(&mut self, ctx: &egui::Context) {
+        let Some(countdown) = &self.on_break else {
+            return;
+        };
+        let remaining = countdown.remaining(ctx.input(|i| i.time));
+        if remaining == 0.0 {
+            self.on_break = None;
+            self.toast(tr("The break is over").to_string(), ctx.input(|i| i.time));
+            return;
         }
-        if ctx.input(|i| i.key_pressed(egui::Key::Num3) || i.key_pressed(egui::Key::F3)) {
-            self.set_tool(Tool::Text);
+        let until_tick = remaining - (remaining.ceil() - 1.0);
+        ctx.request_repaint_after(Duration::from_secs_f64(until_tick.max(0.01)));
+
+        let message = if self.config.break_message.trim().is_empty() {
+            tr("We'll be right back").to_string()
+        } else {
+            self.config.break_message.clone()
+        };
+        let scale = self.config.ui_scale;
+        let mut end = false;
+        egui::CentralPanel::default()
+            .frame(egui::Frame::none().fill(Color32::from_black_alpha(BREAK_DIM_ALPHA)))
+            .show(ctx, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.add_space(ui.available_height() / 3.0);
+                    ui.label(
+                        RichText::new(message)
+                            .size(BREAK_MESSAGE_SIZE * scale)
+                            .color(Color32::WHITE),
+                    );
+                    ui.label(
+                        RichText::new(timers::format_duration(remaining))
+                            .monospace()
+                            .size(BREAK_COUNTDOWN_SIZE * scale)
+                            .color(Color32::WHITE),
+                    );
+                    ui.add_space(ui.spacing().item_spacing.y * 4.0);
+                    end = ui
+                        .button(tr("End break"))
+                        .on_hover_text(self.shortcut_tip(ctx, Action::Break))
+                        .clicked();
+                });
+            });
+        if end {
+            self.on_break = None;
         }
-        if ctx.input(|i| i.key_pressed(egui::Key::Num4) || i.key_pressed(egui::Key::F4)) {
-            self.set_tool(Tool::Mouse);
+    }
+
+    /// Large digits that can be dragged anywhere; they turn amber near the end and red
+    /// once time is up.
+    fn countdown_display(&mut self, ctx: &egui::Context) {
+        if !self.show_countdown {
+            return;
         }
-        if ctx.input(|i| i.key_pressed(egui::Key::Num5) || i.key_pressed(egui::Key::F5)) {
-            self.set_tool(Tool::Eraser);
+        let now = ctx.input(|i| i.time);
+        let remaining = self.countdown.remaining(now);
+        if self.countdown.is_running() {
+            if remaining == 0.0 {
+                self.countdown.pause(now);
+            } else {
+                // Wake up when the displayed second changes.
+                let until_tick = remaining - (remaining.ceil() - 1.0);
+                ctx.request_repaint_after(Duration::from_secs_f64(until_tick.max(0.01)));
+            }
         }
+        let warn_from = COUNTDOWN_WARNING_SECONDS.min(self.countdown.duration() * 0.2);
+        let color = if remaining == 0.0 {
+            Color32::from_rgb(255, 90, 80)
+        } else if remaining <= warn_from {
+            Color32::from_rgb(255, 190, 80)
+        } else {
+            ctx.style().visuals.strong_text_color()
+        };
 
-        if ctx.input(|i| i.key_pressed(egui::Key::Enter))
-            && self.tool == Tool::Polygon
-            && self.polygon_points.len() >= 3
-        {
-            let poly = PolygonShape {
-                points: self.polygon_points.iter().map(|p| [p.x, p.y]).collect(),
-                rgba: self.palette[self.active_color].to_array(),
-                thickness: self.thickness,
-            };
-            self.items.push(CanvasItem::Polygon(poly));
-            self.redo_stack.clear();
-            self.polygon_points.clear();
+        egui::Area::new("countdown".into())
+            .pivot(egui::Align2::CENTER_TOP)
+            .default_pos(ctx.screen_rect().center_top() + Vec2::new(0.0, 80.0))
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(
+                        RichText::new(timers::format_duration(remaining))
+                            .monospace()
+                            .size(COUNTDOWN_TEXT_SIZE * self.config.ui_scale)
+                            .color(color),
+                    );
+                });
+            });
+    }
+
+    /// Elapsed time with the most recent laps below it; can be dragged anywhere.
+    fn stopwatch_display(&mut self, ctx: &egui::Context) {
+        if !self.show_stopwatch {
+            return;
+        }
+        let now = ctx.input(|i| i.time);
+        if self.stopwatch.is_running() {
+            ctx.request_repaint_after(Duration::from_millis(100));
         }
+
+        egui::Area::new("stopwatch".into())
+            .pivot(egui::Align2::CENTER_TOP)
+            .default_pos(ctx.screen_rect().center_top() + Vec2::new(0.0, 200.0))
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(
+                        RichText::new(timers::format_elapsed(self.stopwatch.elapsed(now)))
+                            .monospace()
+                            .size(STOPWATCH_TEXT_SIZE * self.config.ui_scale)
+                            .color(ui.visuals().strong_text_color()),
+                    );
+                    let laps = self.stopwatch.laps();
+                    let first_shown = laps.len().saturating_sub(MAX_LAPS_SHOWN);
+                    for (idx, lap) in laps.iter().enumerate().skip(first_shown).rev() {
+                        let previous = if idx == 0 { 0.0 } else { laps[idx - 1] };
+                        ui.monospace(trf(
+                            "Lap {0}: {1} (+{2})",
+                            &[
+                                &(idx + 1),
+                                &timers::format_elapsed(*lap),
+                                &timers::format_elapsed(lap - previous),
+                            ],
+                        ));
+                    }
+                });
+            });
     }
 
-    fn toolbar(&mut self, ctx: &egui::Context) {
-        egui::Area::new("bottom_toolbar".into())
-            .anchor(egui::Align2::CENTER_BOTTOM, Vec2::new(0.0, -18.0))
+    fn clock(&self, ctx: &egui::Context) {
+        let now = chrono::Local::now();
+        let format = match (self.config.clock_24h, self.config.clock_seconds) {
+            (true, false) => "%H:%M",
+            (true, true) => "%H:%M:%S",
+            (false, false) => "%-I:%M %p",
+            (false, true) => "%-I:%M:%S %p",
+        };
+        // Wake up when the displayed time changes.
+        let into_second =
+            Duration::from_nanos(now.timestamp_subsec_nanos().min(999_999_999).into());
+        let until_tick = if self.config.clock_seconds {
+            Duration::from_secs(1) - into_second
+        } else {
+            Duration::from_secs(60 - now.second() as u64) - into_second
+        };
+        ctx.request_repaint_after(until_tick);
+
+        let (align, offset) = self.config.clock_corner.anchor(18.0);
+        egui::Area::new("clock".into())
+            .anchor(align, offset)
+            .interactable(false)
             .show(ctx, |ui| {
-                egui::Frame::window(ui.style())
-                    .rounding(egui::Rounding::same(14.0))
-                    .show(ui, |ui| {
-                        let drag_bar = ui.add(
-                            egui::Label::new(
-                                RichText::new("⠿ Drag toolbar / window").color(Color32::LIGHT_GRAY),
-                            )
-                            .sense(egui::Sense::click_and_drag()),
-                        );
-                        if drag_bar.dragged() {
-                            ui.ctx().send_viewport_cmd(egui::ViewportCommand::StartDrag);
-                        }
+                ui.set_opacity(self.config.clock_opacity);
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(
+                        RichText::new(now.format(format).to_string())
+                            .monospace()
+                            .size(CLOCK_TEXT_SIZE * self.config.ui_scale)
+                            .color(ui.visuals().strong_text_color()),
+                    );
+                });
+            });
+    }
 
-                        ui.horizontal_wrapped(|ui| {
-                            if ui.selectable_label(self.tool == Tool::Mouse, "🖱 Mouse").clicked() {
-                                self.set_tool(Tool::Mouse);
-                            }
-                            ui.menu_button(
-                                RichText::new("✏ Pen").color(self.palette[self.active_color]),
-                                |ui| {
-                                    self.set_tool(Tool::Pen);
-                                    ui.horizontal_wrapped(|ui| {
-                                        for (idx, color) in self.palette.iter().copied().enumerate() {
-                                            let mut btn =
-                                                egui::Button::new(" ").fill(color).min_size(Vec2::splat(22.0));
-                                            if self.active_color == idx {
-                                                btn = btn.stroke(Stroke::new(2.0, Color32::WHITE));
-                                            }
-                                            if ui.add(btn).clicked() {
-                                                self.active_color = idx;
-                                                ui.close_menu();
-                                            }
-                                        }
-                                    });
-                                    ui.add(
-                                        egui::Slider::new(&mut self.thickness, 1.0..=24.0)
-                                            .text("Thickness"),
-                                    );
-                                },
-                            );
-                            if ui
-                                .selectable_label(self.tool == Tool::Polygon, "⬠ Polygon")
-                                .clicked()
-                            {
-                                self.set_tool(Tool::Polygon);
-                            }
-                            if ui.selectable_label(self.tool == Tool::Text, "🔤 Text").clicked() {
-                                self.set_tool(Tool::Text);
-                            }
-                            if ui
-                                .selectable_label(self.tool == Tool::Eraser, "🧽 Eraser")
-                                .clicked()
-                            {
-                                self.set_tool(Tool::Eraser);
-                            }
-                        });
+    fn keystroke_display(&mut self, ctx: &egui::Context) {
+        let now = ctx.input(|i| i.time);
+        if !self.show_keystrokes {
+            self.keystrokes.clear();
+            return;
+        }
 
-                        if self.tool == Tool::Text {
-                            ui.horizontal(|ui| {
-                                ui.label("Text:");
-                                ui.text_edit_singleline(&mut self.text_draft);
-                            });
-                        }
+        // Keys typed into the overlay reach the system watcher too, so with it running
+        // the overlay's own events would show them twice.
+        let global = self.config.global_keystrokes && self.global_input.is_some();
+        let pressed: Vec<String> = if global {
+            self.global_events
+                .iter()
+                .filter_map(|event| match event {
+                    GlobalEvent::Key(shortcut) => Some(ctx.format_shortcut(shortcut)),
+                    _ => None,
+                })
+                .collect()
+        } else {
+            ctx.input(|i| {
+                i.events
+                    .iter()
+                    .filter_map(|e| match e {
+                        egui::Event::Key {
+                            key,
+                            pressed: true,
+                            repeat: false,
+                            modifiers,
+                            ..
+                        } => Some(
+                            ctx.format_shortcut(&egui::KeyboardShortcut::new(*modifiers, *key)),
+                        ),
+                        _ => None,
+                    })
+                    .collect()
+            })
+        };
+        self.keystrokes
+            .extend(pressed.into_iter().map(|k| (k, now)));
+        self.keystrokes
+            .retain(|(_, at)| now - at < KEYSTROKE_SECONDS);
+        if self.keystrokes.len() > MAX_KEYSTROKES {
+            self.keystrokes
+                .drain(..self.keystrokes.len() - MAX_KEYSTROKES);
+        }
+        let Some((_, oldest)) = self.keystrokes.first() else {
+            return;
+        };
+        ctx.request_repaint_after(Duration::from_secs_f64(
+            (KEYSTROKE_SECONDS - (now - oldest)).max(0.0),
+        ));
 
+        egui::Area::new("keystrokes".into())
+            .anchor(egui::Align2::RIGHT_BOTTOM, Vec2::new(-18.0, -18.0))
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::window(ui.style())
+                    .rounding(egui::Rounding::same(10.0))
+                    .show(ui, |ui| {
                         ui.horizontal(|ui| {
-                            if ui.button("Undo").clicked() {
-                                if let Some(item) = self.items.pop() {
-                                    self.redo_stack.push(item);
-                                }
-                            }
-                            if ui.button("Redo").clicked() {
-                                if let Some(item) = self.redo_stack.pop() {
-                                    self.items.push(item);
-                                }
-                            }
-                            if ui.button("Clear").clicked() {
-                                self.items.clear();
-                                self.redo_stack.clear();
-                                self.polygon_points.clear();
-                            }
-                            if self.tool == Tool::Eraser {
-                                ui.add(
-                                    egui::Slider::new(&mut self.eraser_size, 8.0..=80.0)
-                                        .text("Eraser size"),
-                                );
+                            for (key, _) in &self.keystrokes {
+                                ui.label(RichText::new(key).size(22.0).strong());
                             }
                         });
-
-                        ui.small("Hotkeys: 1 Pen · 2 Polygon · 3 Text · 4 Mouse · 5 Eraser · Enter closes polygon");
                     });
             });
     }
 
-    fn erase_near(&mut self, center: Pos2) {
-        self.items.retain(|item| match item {
-            CanvasItem::Stroke(path) => !path
-                .points
-                .iter()
-                .any(|p| Pos2::new(p[0], p[1]).distance(center) <= self.eraser_size),
-            CanvasItem::Polygon(poly) => !poly
-                .points
-                .iter()
-                .any(|p| Pos2::new(p[0], p[1]).distance(center) <= self.eraser_size),
-            CanvasItem::Text(t) => {
-                Pos2::new(t.pos[0], t.pos[1]).distance(center) > self.eraser_size
-            }
-        });
-    }
-
     fn draw_canvas(&mut self, ctx: &egui::Context) {
         let mut commit_stroke = None;
 
@@ -282,42 +4224,197 @@ impl OpenPenApp {
                 let response = ui.allocate_rect(rect, egui::Sense::click_and_drag());
                 let painter = ui.painter_at(rect);
 
-                for item in &self.items {
-                    item.draw(&painter);
+                // Tablet drivers report the eraser end and the barrel button as the
+                // secondary button; if enabled, it erases for as long as it stays down.
+                // A mouse right-click looks the same, hence the setting.
+                let (eraser_down, eraser_pressed) = ui.input(|i| {
+                    (
+                        i.pointer.button_down(egui::PointerButton::Secondary),
+                        i.pointer.button_pressed(egui::PointerButton::Secondary),
+                    )
+                });
+                if eraser_pressed
+                    && self.config.secondary_button_erases
+                    && response.hovered()
+                    && self.stylus_eraser.is_none()
+                    && self.held_tool.is_none()
+                    && self.tool != Tool::Eraser
+                    && !self.read_only()
+                {
+                    self.stylus_eraser = Some(self.hold_tool(Tool::Eraser));
+                }
+                if !eraser_down {
+                    if let Some(before) = self.stylus_eraser.take() {
+                        self.release_tool(before);
+                    }
+                }
+
+                let hidden = &self.hidden_authors;
+                let hide_all = self.hide_all;
+                let now = unix_time();
+                if self.config.grid {
+                    render::paint_grid(&painter, rect, self.config.grid_spacing);
                 }
 
+                // The item being moved is drawn at its new place by `draw_selection`.
+                let moving: &[ItemId] = match self.selection_drag {
+                    Some(_) => &self.selection,
+                    None => &[],
+                };
+                // So is the text box being edited, by its editor.
+                let editing = self.text_editor.as_ref().map(|editor| editor.id);
+                self.shape_cache.paint(
+                    &painter,
+                    self.items
+                        .iter_with_ids()
+                        .filter(|(id, _, author)| {
+                            !hide_all
+                                && !hidden.contains(*author)
+                                && !moving.contains(id)
+                                && editing != Some(*id)
+                        })
+                        .map(|(id, item, _)| {
+                            let opacity = self.items.expires(id).map_or(1.0, |expires| {
+                                ((expires - now) / FADE_OUT_SECONDS).clamp(0.0, 1.0) as f32
+                            });
+                            (id, item, opacity)
+                        }),
+                );
+
                 if self.tool == Tool::Pen {
+                    // Pens report pressure through touch events, interleaved with the
+                    // pointer moves they also produce.
+                    let events = ui.input(|i| i.events.clone());
+                    let mut moves = Vec::new();
+                    for event in &events {
+                        match event {
+                            egui::Event::Touch { phase, force, .. } => {
+                                self.pen_force = match phase {
+                                    egui::TouchPhase::End | egui::TouchPhase::Cancel => None,
+                                    _ => force.or(self.pen_force),
+                                };
+                            }
+                            egui::Event::PointerMoved(pos) => moves.push((*pos, self.pen_force)),
+                            _ => {}
+                        }
+                    }
+                    self.drawing_pressure.truncate(self.drawing.len());
+                    let straight = ui.input(|i| i.modifiers.shift);
                     if response.drag_started() {
                         self.drawing.clear();
+                        self.drawing_pressure.clear();
+                        self.live_stroke.clear();
+                        // The drag only registers after a few points of movement; start
+                        // the stroke where the pen actually went down.
+                        if let Some(origin) = ui.input(|i| i.pointer.press_origin()) {
+                            self.drawing.push(origin);
+                            self.drawing_pressure
+                                .push(moves.first().and_then(|(_, force)| *force));
+                        }
                     }
-                    if response.dragged() {
-                        if let Some(pos) = response.interact_pointer_pos() {
-                            self.drawing.push(pos);
+                    // An empty stroke mid-drag was cancelled; it stays empty until the next one.
+                    if response.dragged() && !self.drawing.is_empty() {
+                        // Every move event since the last frame, so fast flicks keep
+                        // their shape instead of turning into straight segments.
+                        if moves.is_empty() {
+                            moves.extend(
+                                response
+                                    .interact_pointer_pos()
+                                    .map(|pos| (pos, self.pen_force)),
+                            );
+                        }
+                        for (pos, force) in moves {
+                            if self.drawing.last() != Some(&pos) {
+                                self.drawing.push(pos);
+                                self.drawing_pressure.push(force);
+                            }
+                        }
+                        // Shift straightens the stroke into one segment from where it
+                        // started, at a multiple of 45°. Letting go carries on freehand.
+                        if straight && self.drawing.len() > 1 {
+                            let (start, end) =
+                                (self.drawing[0], self.drawing[self.drawing.len() - 1]);
+                            let end = geometry::snap_angle(
+                                [start.x, start.y],
+                                [end.x, end.y],
+                                std::f32::consts::FRAC_PI_4,
+                            );
+                            self.drawing = vec![start, Pos2::new(end[0], end[1])];
+                            let last = self.drawing_pressure.last().copied().flatten();
+                            self.drawing_pressure.truncate(1);
+                            self.drawing_pressure.push(last);
                         }
                     }
+                    let widths = self.drawing_widths();
                     if response.drag_stopped() && self.drawing.len() > 1 {
-                        commit_stroke = Some(StrokePath {
-                            points: self.drawing.iter().map(|p| [p.x, p.y]).collect(),
-                            rgba: self.palette[self.active_color].to_array(),
-                            thickness: self.thickness,
-                        });
-                    }
-                    if self.drawing.len() > 1 {
-                        painter.add(Shape::line(
-                            self.drawing.clone(),
-                            Stroke::new(self.thickness, self.palette[self.active_color]),
+                        commit_stroke = Some(self.stroke_from_points(
+                            self.drawing.iter().map(|p| [p.x, p.y]).collect(),
+                            &widths,
                         ));
                     }
+                    let color = self.palette[self.active_color];
+                    let live_widths = if widths.is_empty() {
+                        vec![self.thickness; self.drawing.len()]
+                    } else {
+                        widths
+                    };
+                    self.live_stroke.paint(
+                        &painter,
+                        &self.drawing,
+                        &live_widths,
+                        self.config.pen_smoothing,
+                        color,
+                    );
+                    if self.predict_strokes && response.dragged() && !straight {
+                        // Extrapolated tail only; the next frame's real samples replace it.
+                        if let Some(&last) = self.drawing.last() {
+                            let lead = ui.input(|i| i.pointer.velocity()) * PREDICTION_SECONDS;
+                            if lead.length() > 0.5 {
+                                let lead = lead.normalized() * lead.length().min(MAX_PREDICTION);
+                                let width = live_widths.last().copied().unwrap_or(self.thickness);
+                                painter.add(Shape::mesh(render::stroke_mesh(
+                                    &[last, last + lead],
+                                    &[width; 2],
+                                    color,
+                                )));
+                                // Keep going until the velocity estimate settles on a paused pen.
+                                ui.ctx().request_repaint();
+                            }
+                        }
+                    }
                 } else {
                     self.drawing.clear();
+                    self.live_stroke.clear();
                 }
 
                 if self.tool == Tool::Polygon {
+                    // With Ctrl held the next side turns in 15° steps from the last point.
+                    let angled = ui.input(|i| i.modifiers.command);
+                    let place = |app: &Self, pos: Pos2| match app.polygon_points.last() {
+                        Some(last) if angled => {
+                            let [x, y] = geometry::snap_angle(
+                                [last.x, last.y],
+                                [pos.x, pos.y],
+                                POLYGON_ANGLE_STEP.to_radians(),
+                            );
+                            Pos2::new(x, y)
+                        }
+                        _ => app.snap_to_grid(pos),
+                    };
                     if response.clicked() {
                         if let Some(pos) = response.interact_pointer_pos() {
+                            let pos = place(self, pos);
                             self.polygon_points.push(pos);
                         }
                     }
+                    if let (Some(&last), Some(pos), true) =
+                        (self.polygon_points.last(), response.hover_pos(), angled)
+                    {
+                        painter.line_segment(
+                            [last, place(self, pos)],
+                            Stroke::new(1.0, self.palette[self.active_color].gamma_multiply(0.6)),
+                        );
+                    }
                     if self.polygon_points.len() > 1 {
                         painter.add(Shape::line(
                             self.polygon_points.clone(),
@@ -329,22 +4426,262 @@ impl OpenPenApp {
                     }
                 }
 
-                if self.tool == Tool::Text && response.clicked() {
+                if self.tool == Tool::Select {
+                    let items = &self.items;
+                    self.selection.retain(|id| items.get(*id).is_some());
+                    let shift = ui.input(|i| i.modifiers.shift);
+                    if response.clicked() {
+                        let hit = response
+                            .interact_pointer_pos()
+                            .and_then(|pos| self.item_at(pos));
+                        match hit {
+                            Some(id) if shift => {
+                                if let Some(idx) = self.selection.iter().position(|s| *s == id) {
+                                    self.selection.remove(idx);
+                                } else {
+                                    self.selection.push(id);
+                                }
+                            }
+                            Some(id) => self.selection = vec![id],
+                            None if shift => {}
+                            None => self.selection.clear(),
+                        }
+                    }
+                    if response.drag_started() {
+                        if let Some(origin) = ui.input(|i| i.pointer.press_origin()) {
+                            // Dragging a handle scales or turns the selection, dragging an
+                            // item moves it, taking the item into it first, and dragging
+                            // empty space pulls out a rubber band.
+                            let pivot = self.selection_bounds().map(|bounds| bounds.center());
+                            let (start, end) = (origin, origin);
+                            match (self.handle_at(origin), pivot) {
+                                (Some(Handle::Tip), _) => {
+                                    self.selection_drag = Some(SelectionDrag::Tip { start, end });
+                                }
+                                (Some(Handle::Rotate), Some(pivot)) => {
+                                    self.selection_drag =
+                                        Some(SelectionDrag::Rotate { pivot, start, end });
+                                }
+                                (Some(Handle::Corner(_)), Some(pivot)) => {
+                                    self.selection_drag =
+                                        Some(SelectionDrag::Scale { pivot, start, end });
+                                }
+                                _ => match self.item_at(origin) {
+                                    Some(id) => {
+                                        if !self.selection.contains(&id) {
+                                            if !shift {
+                                                self.selection.clear();
+                                            }
+                                            self.selection.push(id);
+                                        }
+                                        self.selection_drag =
+                                            Some(SelectionDrag::Move { start, end });
+                                    }
+                                    None => {
+                                        if !shift {
+                                            self.selection.clear();
+                                        }
+                                        self.band_drag = Some((origin, origin));
+                                    }
+                                },
+                            }
+                        }
+                    }
+                    // Like a pen stroke, a drag cancelled with Escape stays cancelled.
                     if let Some(pos) = response.interact_pointer_pos() {
-                        let text = if self.text_draft.trim().is_empty() {
-                            "Text".to_string()
+                        if let Some(drag) = &mut self.selection_drag {
+                            drag.set_end(pos);
+                        }
+                        if let Some((_, end)) = &mut self.band_drag {
+                            *end = pos;
+                        }
+                    }
+                    if response.drag_stopped() {
+                        if let Some(drag) = self.selection_drag.take() {
+                            if !drag.is_empty() {
+                                let text_sizes: HashMap<ItemId, [f32; 2]> = self
+                                    .selection
+                                    .iter()
+                                    .filter_map(|id| Some((*id, self.shape_cache.text_size(*id)?)))
+                                    .collect();
+                                self.edit_selection(|id, item| {
+                                    drag.apply(item, text_sizes.get(&id).copied());
+                                    true
+                                });
+                            }
+                        }
+                        if let Some((start, end)) = self.band_drag.take() {
+                            let band = egui::Rect::from_two_pos(start, end);
+                            let enclosed: Vec<ItemId> = self
+                                .items
+                                .iter_with_ids()
+                                .filter(|(id, item, author)| {
+                                    !self.hide_all
+                                        && !self.hidden_authors.contains(*author)
+                                        && !self.selection.contains(id)
+                                        && item.bounds(self.shape_cache.text_size(*id)).is_some_and(
+                                            |[min, max]| {
+                                                band.contains(Pos2::new(min[0], min[1]))
+                                                    && band.contains(Pos2::new(max[0], max[1]))
+                                            },
+                                        )
+                                })
+                                .map(|(id, _, _)| id)
+                                .collect();
+                            self.selection.extend(enclosed);
+                        }
+                    }
+                    self.draw_selection(&painter);
+                } else {
+                    self.selection.clear();
+                    self.selection_drag = None;
+                    self.band_drag = None;
+                }
+
+                if self.tool != Tool::Ruler {
+                    self.measurement = None;
+                }
+                if matches!(
+                    self.tool,
+                    Tool::Ellipse | Tool::Line | Tool::Ruler | Tool::Callout
+                ) {
+                    // Measurements go where the pointer is; everything else lands on the grid.
+                    let snap = |app: &Self, pos| match app.tool {
+                        Tool::Ruler => pos,
+                        _ => app.snap_to_grid(pos),
+                    };
+                    if response.drag_started() {
+                        self.shape_drag = ui
+                            .input(|i| i.pointer.press_origin())
+                            .map(|origin| (snap(self, origin), snap(self, origin)));
+                    }
+                    // Like a pen stroke, a drag cancelled with Escape stays cancelled.
+                    let pos = response.interact_pointer_pos().map(|pos| snap(self, pos));
+                    if let (Some((_, end)), Some(pos)) = (&mut self.shape_drag, pos) {
+                        *end = pos;
+                    }
+                    if let Some((start, end)) = self.shape_drag {
+                        if self.tool == Tool::Ruler {
+                            // Stays up after the drag, until the next one or a tool change.
+                            self.measurement = Some((start, end));
+                            if response.drag_stopped() {
+                                self.shape_drag = None;
+                            }
                         } else {
-                            self.text_draft.clone()
+                            let shape = self.shape_between(ui.ctx(), start, end);
+                            if response.drag_stopped() {
+                                self.shape_drag = None;
+                                if let Some(item) = shape {
+                                    self.push_item(item);
+                                }
+                            } else if let Some(item) = shape {
+                                painter.add(render::item_shape(&item, &painter, 0.0));
+                            }
+                        }
+                    }
+                    if let Some((start, end)) = self.measurement {
+                        self.draw_measurement(&painter, start, end);
+                    }
+                } else {
+                    self.shape_drag = None;
+                }
+
+                if self.tool == Tool::Marker && response.clicked() && !self.read_only() {
+                    if let Some(pos) = response.interact_pointer_pos() {
+                        let marker = Marker {
+                            pos: [pos.x, pos.y],
+                            number: self.next_marker,
+                            rgba: self.palette[self.active_color].to_array(),
                         };
-                        self.items.push(CanvasItem::Text(TextBox {
+                        self.next_marker += 1;
+                        self.push_item(CanvasItem::Marker(marker));
+                    }
+                }
+
+                if self.tool == Tool::Stamp && response.clicked() {
+                    if let Some(pos) = response.interact_pointer_pos() {
+                        let stamp = Stamp {
                             pos: [pos.x, pos.y],
-                            text,
+                            kind: self.stamp_kind,
+                            size: self.stamp_size,
                             rgba: self.palette[self.active_color].to_array(),
-                        }));
-                        self.redo_stack.clear();
+                        };
+                        self.push_item(CanvasItem::Stamp(stamp));
+                    }
+                }
+
+                if matches!(self.tool, Tool::Select | Tool::Text | Tool::Callout)
+                    && response.double_clicked()
+                {
+                    if let Some(pos) = response.interact_pointer_pos() {
+                        self.start_text_edit(pos);
+                    }
+                } else if self.text_editor.is_some()
+                    && (response.clicked() || response.drag_started())
+                {
+                    // Clicking away from the editor is done with it; the click does
+                    // nothing else.
+                    self.finish_text_edit(true);
+                } else if self.tool == Tool::Text && response.clicked() {
+                    // Clicking a text box is the first half of a double-click to edit it.
+                    let pos = response
+                        .interact_pointer_pos()
+                        .filter(|pos| !self.text_at(*pos));
+                    if let Some(pos) = pos {
+                        let pos = self.snap_to_grid(pos);
+                        let text = self.text_box(pos, self.text_to_place());
+                        self.push_item(CanvasItem::Text(text));
+                    }
+                }
+
+                if response.hovered() {
+                    self.brush_scroll += ui.input(|i| i.raw_scroll_delta.y);
+                    let notches = (self.brush_scroll / SCROLL_NOTCH).trunc();
+                    if notches != 0.0 {
+                        self.brush_scroll -= notches * SCROLL_NOTCH;
+                        self.resize_brush(notches, ui.input(|i| i.time));
+                    }
+                }
+
+                if response.hovered() || response.dragged() {
+                    let handle = ui
+                        .input(|i| i.pointer.hover_pos())
+                        .filter(|_| self.tool == Tool::Select)
+                        .and_then(|pos| self.handle_at(pos));
+                    ui.ctx().set_cursor_icon(match handle {
+                        Some(Handle::Corner(idx)) if idx % 2 == 0 => egui::CursorIcon::ResizeNwSe,
+                        Some(Handle::Corner(_)) => egui::CursorIcon::ResizeNeSw,
+                        Some(Handle::Rotate) => egui::CursorIcon::Grab,
+                        Some(Handle::Tip) => egui::CursorIcon::Move,
+                        None => self.tool.cursor(),
+                    });
+                    if self.tool == Tool::Pen {
+                        if let Some(pos) = ui.input(|i| i.pointer.hover_pos()) {
+                            let color = self.palette[self.active_color];
+                            if !response.dragged() {
+                                // The dab a stroke started here would leave.
+                                painter.circle(
+                                    pos,
+                                    self.thickness / 2.0,
+                                    color,
+                                    Stroke::new(1.0, Color32::from_black_alpha(160)),
+                                );
+                            }
+                            paint_nib(&painter, pos, color);
+                        }
                     }
                 }
 
+                // A whole eraser drag is one undo step, however the drag ends.
+                let erasing = self.tool == Tool::Eraser && response.dragged();
+                if erasing != self.erasing {
+                    if erasing {
+                        self.items.begin_step();
+                    } else {
+                        self.items.end_step();
+                    }
+                    self.erasing = erasing;
+                }
                 if self.tool == Tool::Eraser {
                     if response.dragged() {
                         if let Some(pos) = response.interact_pointer_pos() {
@@ -362,15 +4699,63 @@ impl OpenPenApp {
             });
 
         if let Some(stroke) = commit_stroke {
-            self.items.push(CanvasItem::Stroke(stroke));
-            self.redo_stack.clear();
+            self.push_item(CanvasItem::Stroke(stroke));
             self.drawing.clear();
         }
     }
 }
 
+/// Opens the first journal in the data directory that no running instance holds and
+/// locks it for this one. A journal left behind by a crash is free again, so the next
+/// start recovers its items.
+fn open_journal() -> io::Result<(Journal, Vec<Op>, File)> {
+    let dir = config::data_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory found"))?
+        .join("journals");
+    std::fs::create_dir_all(&dir)?;
+    for slot in 0..JOURNAL_SLOTS {
+        let lock = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(dir.join(format!("journal-{slot}.lock")))?;
+        match lock.try_lock() {
+            Ok(()) => {
+                let (journal, recovered) =
+                    Journal::open(dir.join(format!("journal-{slot}.jsonl")))?;
+                return Ok((journal, recovered, lock));
+            }
+            Err(TryLockError::WouldBlock) => continue,
+            Err(TryLockError::Error(e)) => return Err(e),
+        }
+    }
+    Err(io::Error::other("too many OpenPen windows are open"))
+}
+
 impl Default for OpenPenApp {
     fn default() -> Self {
+        let config = Config::load();
+        i18n::set_locale(config.locale);
+        let mut items = Document::default();
+        items.set_history_depth(config.history_depth);
+        // Anything left in the journal means the run that owned it did not exit cleanly.
+        let (journal, journal_lock, journal_error) = match open_journal() {
+            Ok((mut journal, recovered, lock)) => {
+                for op in recovered {
+                    items.apply(op);
+                }
+                let compacted = journal.compact(&items);
+                (
+                    Some(journal),
+                    Some(lock),
+                    compacted.err().map(|e| e.to_string()),
+                )
+            }
+            Err(e) => (None, None, Some(e.to_string())),
+        };
+        let shape_cache = render::ShapeCache::new(items.subscribe());
+        let journal_events = items.subscribe();
+        let points = PointTally::new(&mut items);
         Self {
             palette: vec![
                 Color32::from_rgb(255, 77, 77),
@@ -383,26 +4768,171 @@ impl Default for OpenPenApp {
             active_color: 0,
             thickness: 4.0,
             tool: Tool::Pen,
+            previous_tool: Tool::Mouse,
+            held_tool: None,
+            stylus_eraser: None,
+            erasing: false,
             drawing: Vec::new(),
+            drawing_pressure: Vec::new(),
+            pen_force: None,
             polygon_points: Vec::new(),
-            items: Vec::new(),
-            redo_stack: Vec::new(),
+            shape_drag: None,
+            next_marker: 1,
+            stamp_kind: StampKind::Check,
+            stamp_size: 40.0,
+            measurement: None,
+            selection: Vec::new(),
+            selection_background: None,
+            selection_drag: None,
+            text_editor: None,
+            band_drag: None,
+            items,
+            shape_cache,
+            journal,
+            journal_lock,
+            journal_events,
+            journal_error,
+            live_stroke: render::LiveStroke::default(),
             eraser_size: 24.0,
-            text_draft: "Text".to_string(),
+            brush_scroll: 0.0,
+            brush_resized_at: None,
+            predict_strokes: false,
+            text_draft: tr("Text").to_string(),
+            cursor_highlight: false,
+            click_ripples: false,
+            highlight_color: Color32::from_rgb(255, 230, 90),
+            highlight_radius: 28.0,
+            ripples: Vec::new(),
+            pointer_trail: false,
+            trail_length: 0.4,
+            trail_color: Color32::from_rgb(255, 120, 60),
+            trail: Vec::new(),
+            fade_after: HashMap::new(),
+            show_keystrokes: false,
+            show_perf_hud: false,
+            show_help: false,
+            countdown: timers::Countdown::new(config.countdown_seconds as f64),
+            show_countdown: false,
+            stopwatch: timers::Stopwatch::default(),
+            show_stopwatch: false,
+            on_break: None,
+            show_ticker: false,
+            spotlight: false,
+            spotlight_radius: 160.0,
+            spotlight_rect: false,
+            curtains: Vec::new(),
+            hide_all: false,
+            points,
+            over_budget: false,
+            budget_warning: None,
+            keystrokes: Vec::new(),
+            idle_passthrough: false,
+            passthrough_active: false,
+            global_input: None,
+            global_input_error: None,
+            global_events: Vec::new(),
+            toolbar_pos: None,
+            toolbar_size: Vec2::new(760.0, 120.0),
+            toolbar_rect: egui::Rect::NOTHING,
+            toolbar_dragging: false,
+            toolbar_last_used: 0.0,
+            config,
+            applied_style: None,
+            config_error: None,
+            companion: None,
+            companion_error: None,
+            session: None,
+            session_addr: String::new(),
+            broadcast: false,
+            display_name: std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_else(|_| "Guest".to_string()),
+            remote_cursors: HashMap::new(),
+            last_cursor_sent: (0.0, None),
+            hidden_authors: HashSet::new(),
+            toasts: Vec::new(),
+            save_job: None,
+            export_job: None,
+            session_error: None,
+            pending_join: None,
         }
     }
 }
 
 impl eframe::App for OpenPenApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
-            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
-            return;
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        self.items.set_author(&self.display_name);
+        self.handle_shortcuts(ctx);
+        let style = (self.config.ui_scale, self.config.theme);
+        if self.applied_style != Some(style) {
+            ctx.set_style(scaled_style(style.0, style.1));
+            self.applied_style = Some(style);
+        }
+        self.receive_remote_input(ctx);
+        self.sync_session(ctx);
+        self.apply_selection_background(ctx);
+        self.poll_jobs(ctx.input(|i| i.time));
+        self.write_journal(ctx);
+        self.enforce_budget(ctx.input(|i| i.time));
+        self.expire_items(ctx);
+        if self.read_only() && self.tool != Tool::Mouse {
+            self.tool = Tool::Mouse;
+            self.drawing.clear();
+            self.polygon_points.clear();
         }
 
-        self.handle_shortcuts(ctx);
-        self.draw_canvas(ctx);
-        self.toolbar(ctx);
-        ctx.request_repaint();
+        let passthrough =
+            self.idle_passthrough && self.tool == Tool::Mouse && self.on_break.is_none();
+        if passthrough != self.passthrough_active {
+            ctx.send_viewport_cmd(egui::ViewportCommand::MousePassthrough(passthrough));
+            self.passthrough_active = passthrough;
+        }
+
+        if self.on_break.is_some() {
+            self.break_screen(ctx);
+        } else {
+            self.draw_canvas(ctx);
+            self.text_editor_area(ctx);
+            self.curtains(ctx);
+            if self.idle_passthrough {
+                self.toolbar_viewport(ctx);
+            } else {
+                self.toolbar(ctx);
+            }
+        }
+        self.watch_global_input(ctx);
+        self.keystroke_display(ctx);
+        self.countdown_display(ctx);
+        self.stopwatch_display(ctx);
+        if self.config.show_clock {
+            self.clock(ctx);
+        }
+        self.draw_remote_cursors(ctx);
+        self.draw_presenter_aids(ctx);
+        if self.show_ticker {
+            self.draw_ticker(ctx);
+        }
+        self.brush_size_indicator(ctx);
+        self.budget_warning_banner(ctx);
+        self.show_toasts(ctx);
+        if self.config.show_status {
+            self.status_line(ctx);
+        }
+        if self.show_help {
+            self.help_overlay(ctx);
+        }
+        if self.show_perf_hud {
+            self.perf_hud(ctx, frame.info().cpu_usage);
+        }
+        self.draw_watermark(ctx);
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        // A clean exit starts the next run with an empty board, as before. Only our
+        // own journal goes; the lock is released after it is gone.
+        if let Some(journal) = self.journal.take() {
+            let _ = std::fs::remove_file(journal.path());
+        }
+        self.journal_lock = None;
     }
 }
@@ -0,0 +1,647 @@
+//! Tessellation caches for committed items and the stroke being drawn.
+//!
+//! Items only change through the document, so each item is tessellated once and the
+//! mesh is reused every frame. Entries are evicted as the document reports edits and
+//! removals, and the whole cache is rebuilt when the scale factor changes since
+//! feathering and glyph positions depend on it.
+//! The mesh bounds double as each item's bounding box for culling, and the size a
+//! text box was laid out at is kept for hit tests. Items fading out keep a faded
+//! copy of their mesh that is only remade at each of a fixed number of fade steps.
+//!
+//! Boards with a very large number of points switch to a coarser level of detail,
+//! drawing lines from simplified outlines until the board shrinks again.
+//!
+//! Stamps of the same kind, size, and color share one mesh, tessellated around the
+//! origin and shifted into place for each instance. Shared meshes no painted stamp
+//! used are dropped after each paint.
+
+use std::collections::{HashMap, HashSet};
+use std::f32::consts::{PI, TAU};
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use eframe::egui::{
+    self, epaint::Tessellator, text::LayoutJob, Align2, Color32, FontId, Galley, Mesh, Pos2, Rect,
+    Shape, Stroke, TextFormat, Vec2,
+};
+
+use kalam_core::crdt::ItemId;
+use kalam_core::document::Event;
+use kalam_core::{geometry, markup, CanvasItem, Marker, Stamp, StampKind, TextBox};
+
+use crate::color_from_rgba;
+
+/// Total points up to which everything is drawn at full detail.
+const FULL_DETAIL_POINTS: usize = 50_000;
+/// Extra simplification tolerance, in points, per level of detail.
+const LOD_EPSILON_STEP: f32 = 0.75;
+const MAX_LOD: u32 = 3;
+const MARKER_FONT_SIZE: f32 = 16.0;
+const TEXT_SIZE: f32 = 18.0;
+/// How far bold runs are drawn over again to thicken them.
+const BOLD_OFFSET: f32 = 0.8;
+/// Opacity levels a fading item steps through.
+const FADE_STEPS: u32 = 64;
+const CODE_BACKGROUND: Color32 = Color32::from_rgba_premultiplied(40, 40, 40, 40);
+
+struct CachedItem {
+    /// Empty for stamps, which draw their shared mesh instead.
+    mesh: Mesh,
+    /// While the item fades out: the fade step its faded copy was made for, and the copy.
+    faded: Option<(u32, Mesh)>,
+    bounds: Rect,
+    /// Backdrop size of the item's text box as laid out, if it has one.
+    text_size: Option<[f32; 2]>,
+}
+
+/// Kind, size bits, and color of a stamp; stamps that agree look the same.
+type StampKey = (StampKind, u32, [u8; 4]);
+
+pub(crate) struct ShapeCache {
+    items: HashMap<ItemId, CachedItem>,
+    stamps: HashMap<StampKey, Mesh>,
+    events: Receiver<Event>,
+    pixels_per_point: f32,
+    visible_rect: Option<Rect>,
+    level_of_detail: u32,
+    tessellation_time: Duration,
+}
+
+/// Figures for the performance HUD.
+pub(crate) struct CacheStats {
+    pub meshes: usize,
+    pub bytes: usize,
+    /// Time spent tessellating uncached items during the last paint.
+    pub tessellation_time: Duration,
+    pub level_of_detail: u32,
+}
+
+impl ShapeCache {
+    pub(crate) fn new(events: Receiver<Event>) -> Self {
+        Self {
+            items: HashMap::new(),
+            stamps: HashMap::new(),
+            events,
+            pixels_per_point: 0.0,
+            visible_rect: None,
+            level_of_detail: 0,
+            tessellation_time: Duration::ZERO,
+        }
+    }
+
+    pub(crate) fn stats(&self) -> CacheStats {
+        let bytes = self
+            .items
+            .values()
+            .map(|cached| &cached.mesh)
+            .chain(self.stamps.values())
+            .map(|mesh| {
+                mesh.vertices.len() * std::mem::size_of::<egui::epaint::Vertex>()
+                    + mesh.indices.len() * std::mem::size_of::<u32>()
+            })
+            .sum();
+        CacheStats {
+            meshes: self.items.len() + self.stamps.len(),
+            bytes,
+            tessellation_time: self.tessellation_time,
+            level_of_detail: self.level_of_detail,
+        }
+    }
+
+    /// Paints `items` in order at the given opacity, tessellating only the ones not
+    /// cached yet and skipping those entirely outside the painter's clip rect.
+    pub(crate) fn paint<'a>(
+        &mut self,
+        painter: &egui::Painter,
+        items: impl Iterator<Item = (ItemId, &'a CanvasItem, f32)>,
+    ) {
+        let ctx = painter.ctx();
+        let pixels_per_point = ctx.pixels_per_point();
+        let items: Vec<_> = items.collect();
+        let total_points: usize = items.iter().map(|(_, item, _)| item.point_count()).sum();
+        let level_of_detail = level_of_detail(total_points);
+        if pixels_per_point != self.pixels_per_point || level_of_detail != self.level_of_detail {
+            self.items.clear();
+            self.stamps.clear();
+            self.pixels_per_point = pixels_per_point;
+            self.level_of_detail = level_of_detail;
+        }
+        let epsilon = level_of_detail as f32 * LOD_EPSILON_STEP;
+        let visible_rect = painter.clip_rect();
+        self.visible_rect = Some(visible_rect);
+
+        for event in self.events.try_iter() {
+            match event {
+                Event::Added(_) => {}
+                Event::Removed(id) | Event::Updated(id) => {
+                    self.items.remove(&id);
+                }
+                Event::Cleared => self.items.clear(),
+            }
+        }
+
+        let mut tessellator = None;
+        let mut tessellation_time = Duration::ZERO;
+        let mut tessellate = |item: &CanvasItem| {
+            let tessellator = tessellator.get_or_insert_with(|| new_tessellator(ctx));
+            let started = Instant::now();
+            let mut mesh = Mesh::default();
+            tessellator.tessellate_shape(item_shape(item, painter, epsilon), &mut mesh);
+            tessellation_time += started.elapsed();
+            mesh
+        };
+        let mut used_stamps = HashSet::new();
+        for (id, item, opacity) in items {
+            let (cached, stamp) = match item {
+                CanvasItem::Stamp(stamp) => {
+                    let key = (stamp.kind, stamp.size.to_bits(), stamp.rgba);
+                    used_stamps.insert(key);
+                    let shared = self.stamps.entry(key).or_insert_with(|| {
+                        let template = Stamp {
+                            pos: [0.0, 0.0],
+                            ..stamp.clone()
+                        };
+                        tessellate(&CanvasItem::Stamp(template))
+                    });
+                    let offset = Vec2::new(stamp.pos[0], stamp.pos[1]);
+                    let cached = self.items.entry(id).or_insert_with(|| CachedItem {
+                        mesh: Mesh::default(),
+                        faded: None,
+                        bounds: shared.calc_bounds().translate(offset),
+                        text_size: None,
+                    });
+                    (cached, Some((&*shared, offset)))
+                }
+                _ => {
+                    let cached = self.items.entry(id).or_insert_with(|| {
+                        let mesh = tessellate(item);
+                        let bounds = mesh.calc_bounds();
+                        let text_size = item.text_box().map(|t| text_size(ctx, t));
+                        CachedItem {
+                            mesh,
+                            faded: None,
+                            bounds,
+                            text_size,
+                        }
+                    });
+                    (cached, None)
+                }
+            };
+            if !cached.bounds.intersects(visible_rect) {
+                continue;
+            }
+            let placed = |shared: &Mesh, offset: Vec2| {
+                let mut mesh = shared.clone();
+                mesh.translate(offset);
+                mesh
+            };
+            // egui takes meshes by value, so each one is copied once a frame; a fading
+            // item's faded copy is only remade when it reaches the next fade step.
+            let step = (opacity.clamp(0.0, 1.0) * FADE_STEPS as f32).round() as u32;
+            let mesh = if step < FADE_STEPS {
+                if cached
+                    .faded
+                    .as_ref()
+                    .is_some_and(|(made_for, _)| *made_for != step)
+                {
+                    cached.faded = None;
+                }
+                let (_, faded) = cached.faded.get_or_insert_with(|| {
+                    let mut faded = match stamp {
+                        Some((shared, offset)) => placed(shared, offset),
+                        None => cached.mesh.clone(),
+                    };
+                    let opacity = step as f32 / FADE_STEPS as f32;
+                    for vertex in &mut faded.vertices {
+                        vertex.color = vertex.color.gamma_multiply(opacity);
+                    }
+                    (step, faded)
+                });
+                faded.clone()
+            } else {
+                match stamp {
+                    Some((shared, offset)) => placed(shared, offset),
+                    None => cached.mesh.clone(),
+                }
+            };
+            if !mesh.is_empty() {
+                painter.add(Shape::mesh(mesh));
+            }
+        }
+        self.stamps.retain(|key, _| used_stamps.contains(key));
+        self.tessellation_time = tessellation_time;
+    }
+
+    /// Backdrop size of the item's text box as it was laid out when last painted;
+    /// `None` for items without text and those the cache has not seen yet.
+    pub(crate) fn text_size(&self, id: ItemId) -> Option<[f32; 2]> {
+        self.items.get(&id)?.text_size
+    }
+
+    /// Broad phase for hit tests: whether the item was on screen when last painted
+    /// and its bounds, grown by `radius`, contain `pos`. Items the cache has not seen
+    /// yet always pass, leaving them to the exact test.
+    pub(crate) fn may_hit(&self, id: ItemId, pos: Pos2, radius: f32) -> bool {
+        match (self.items.get(&id), self.visible_rect) {
+            (Some(cached), Some(visible_rect)) => {
+                cached.bounds.intersects(visible_rect) && cached.bounds.expand(radius).contains(pos)
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Preview of the stroke being drawn. The points are split into the chunks
+/// [`geometry::smooth_polyline_piecewise`] smooths the finished stroke in, and each
+/// full chunk is smoothed and meshed once, so each frame only rebuilds the tail
+/// after the last full chunk and the preview matches the stroke it becomes.
+#[derive(Default)]
+pub(crate) struct LiveStroke {
+    chunks: Vec<Mesh>,
+    chunked_points: usize,
+}
+
+impl LiveStroke {
+    pub(crate) fn clear(&mut self) {
+        self.chunks.clear();
+        self.chunked_points = 0;
+    }
+
+    /// Draws the stroke through `points`, `widths` wide at each of them.
+    pub(crate) fn paint(
+        &mut self,
+        painter: &egui::Painter,
+        points: &[Pos2],
+        widths: &[f32],
+        smoothing: f32,
+        color: Color32,
+    ) {
+        if points.len() < self.chunked_points {
+            self.clear();
+        }
+        let points = &points[..points.len().min(widths.len())];
+        let line = |points: &[Pos2], widths: &[f32]| {
+            let raw: Vec<[f32; 2]> = points.iter().map(|p| [p.x, p.y]).collect();
+            let smoothed: Vec<Pos2> = geometry::smooth_polyline(&raw, smoothing)
+                .into_iter()
+                .map(|p| Pos2::new(p[0], p[1]))
+                .collect();
+            stroke_mesh(
+                &smoothed,
+                &geometry::smooth_values(widths, smoothing),
+                color,
+            )
+        };
+
+        // Neighbouring chunks share their boundary point.
+        while points.len() > self.chunked_points + geometry::SMOOTHING_CHUNK {
+            let range = self.chunked_points..=self.chunked_points + geometry::SMOOTHING_CHUNK;
+            self.chunks
+                .push(line(&points[range.clone()], &widths[range.clone()]));
+            self.chunked_points = *range.end();
+        }
+
+        for mesh in &self.chunks {
+            painter.add(Shape::mesh(mesh.clone()));
+        }
+        let tail = self.chunked_points.min(points.len())..points.len();
+        if tail.len() > 1 {
+            painter.add(Shape::mesh(line(&points[tail.clone()], &widths[tail])));
+        }
+    }
+}
+
+/// Faint lines every `spacing` points across `rect`, lined up with the screen origin
+/// so they meet where snapped points land.
+pub(crate) fn paint_grid(painter: &egui::Painter, rect: Rect, spacing: f32) {
+    let stroke = Stroke::new(1.0, Color32::from_rgba_unmultiplied(128, 128, 128, 70));
+    let mut x = (rect.left() / spacing).ceil() * spacing;
+    while x <= rect.right() {
+        painter.vline(x, rect.y_range(), stroke);
+        x += spacing;
+    }
+    let mut y = (rect.top() / spacing).ceil() * spacing;
+    while y <= rect.bottom() {
+        painter.hline(rect.x_range(), y, stroke);
+        y += spacing;
+    }
+}
+
+/// Builds the shapes for an item; the painter is only used to lay out text.
+/// A positive `epsilon` draws lines from a simplified outline.
+pub(crate) fn item_shape(item: &CanvasItem, painter: &egui::Painter, epsilon: f32) -> Shape {
+    let outline = |points: &[[f32; 2]]| -> Vec<Pos2> {
+        geometry::simplify_polyline(points, epsilon)
+            .into_iter()
+            .map(|p| Pos2::new(p[0], p[1]))
+            .collect()
+    };
+    match item {
+        CanvasItem::Stroke(path) => {
+            let keep = geometry::simplify_indices(&path.points, epsilon);
+            if keep.len() > 1 {
+                let widths = path.point_widths();
+                let points: Vec<Pos2> = keep
+                    .iter()
+                    .map(|idx| Pos2::new(path.points[*idx][0], path.points[*idx][1]))
+                    .collect();
+                let widths: Vec<f32> = keep.iter().map(|idx| widths[*idx]).collect();
+                Shape::mesh(stroke_mesh(&points, &widths, color_from_rgba(path.rgba)))
+            } else {
+                Shape::Noop
+            }
+        }
+        CanvasItem::Polygon(poly) => {
+            let points = outline(&poly.points);
+            if points.len() > 2 {
+                let stroke = Stroke::new(poly.thickness, color_from_rgba(poly.rgba));
+                match poly.fill {
+                    // Polygons may be concave or cross themselves, which a convex
+                    // fill would paint over.
+                    Some(fill) => {
+                        let mut mesh = Mesh::default();
+                        let corners: Vec<[f32; 2]> = points.iter().map(|p| [p.x, p.y]).collect();
+                        for triangle in geometry::fill_triangles(&corners) {
+                            let base = mesh.vertices.len() as u32;
+                            for p in triangle {
+                                mesh.colored_vertex(Pos2::new(p[0], p[1]), color_from_rgba(fill));
+                            }
+                            mesh.add_triangle(base, base + 1, base + 2);
+                        }
+                        Shape::Vec(vec![Shape::mesh(mesh), Shape::closed_line(points, stroke)])
+                    }
+                    None => Shape::closed_line(points, stroke),
+                }
+            } else {
+                Shape::Noop
+            }
+        }
+        CanvasItem::Ellipse(ellipse) => {
+            let stroke = Stroke::new(ellipse.thickness, color_from_rgba(ellipse.rgba));
+            let fill = ellipse.fill.map_or(Color32::TRANSPARENT, color_from_rgba);
+            Shape::convex_polygon(outline(&ellipse.outline()), fill, stroke)
+        }
+        CanvasItem::Line(line) => {
+            let points = outline(&[line.start, line.end]);
+            let widths = vec![line.thickness; points.len()];
+            Shape::mesh(stroke_mesh(&points, &widths, color_from_rgba(line.rgba)))
+        }
+        CanvasItem::Marker(marker) => {
+            let center = Pos2::new(marker.pos[0], marker.pos[1]);
+            let color = color_from_rgba(marker.label_rgba());
+            let galley = painter.layout_no_wrap(
+                marker.number.to_string(),
+                FontId::proportional(MARKER_FONT_SIZE),
+                color,
+            );
+            let label_rect = Align2::CENTER_CENTER.anchor_size(center, galley.size());
+            Shape::Vec(vec![
+                Shape::circle_filled(center, Marker::RADIUS, color_from_rgba(marker.rgba)),
+                Shape::galley(label_rect.min, galley, color),
+            ])
+        }
+        CanvasItem::Stamp(stamp) => {
+            let color = color_from_rgba(stamp.rgba);
+            let lines = stamp.lines().into_iter().map(|(points, closed)| {
+                let points: Vec<Pos2> = points.iter().map(|p| Pos2::new(p[0], p[1])).collect();
+                if closed {
+                    Shape::closed_line(points, Stroke::new(stamp.thickness(), color))
+                } else {
+                    let widths = vec![stamp.thickness(); points.len()];
+                    Shape::mesh(stroke_mesh(&points, &widths, color))
+                }
+            });
+            Shape::Vec(lines.collect())
+        }
+        CanvasItem::Callout(callout) => {
+            let bubble = item_shape(&CanvasItem::Text(callout.text.clone()), painter, epsilon);
+            match callout.tail(text_size(painter.ctx(), &callout.text)) {
+                Some(tail) => {
+                    let points = tail.iter().map(|p| Pos2::new(p[0], p[1])).collect();
+                    let fill = color_from_rgba(callout.text.background.rgba());
+                    Shape::Vec(vec![
+                        Shape::convex_polygon(points, fill, Stroke::NONE),
+                        bubble,
+                    ])
+                }
+                None => bubble,
+            }
+        }
+        CanvasItem::Text(t) => {
+            let pos = Pos2::new(t.pos[0], t.pos[1]);
+            let color = color_from_rgba(t.rgba);
+            let galley = painter.ctx().fonts(|f| text_galley(f, t, color, false));
+            let style = t.background;
+            let size = galley.size() + 2.0 * Vec2::from(style.padding);
+            let background = Shape::rect_filled(
+                egui::Rect::from_min_size(pos, size),
+                style.corner_radius,
+                color_from_rgba(style.rgba()),
+            );
+            let anchor = pos + Vec2::new(style.padding[0], size.y / 2.0);
+            let text_rect = Align2::LEFT_CENTER.anchor_size(anchor, galley.size());
+            let mut shapes = vec![background, Shape::galley(text_rect.min, galley, color)];
+            if markup::parse(&t.text).iter().any(|span| span.style.bold) {
+                let bold = painter.ctx().fonts(|f| text_galley(f, t, color, true));
+                let pos = text_rect.min + Vec2::new(BOLD_OFFSET, 0.0);
+                shapes.push(Shape::galley(pos, bold, color));
+            }
+            Shape::Vec(shapes)
+        }
+    }
+}
+
+/// Lays out a text box's styled text. With `bold_only`, everything but the bold runs
+/// is laid out invisible, for drawing those runs a second time just to the right:
+/// the bundled font has no bold face.
+fn text_galley(
+    fonts: &egui::epaint::Fonts,
+    t: &TextBox,
+    color: Color32,
+    bold_only: bool,
+) -> Arc<Galley> {
+    let mut job = LayoutJob::default();
+    job.wrap.max_width = t.wrap_width.unwrap_or(f32::INFINITY);
+    for span in markup::parse(&t.text) {
+        let visible = !bold_only || span.style.bold;
+        let font_id = if span.style.code {
+            FontId::monospace(TEXT_SIZE - 1.0)
+        } else {
+            FontId::proportional(TEXT_SIZE)
+        };
+        let format = TextFormat {
+            font_id,
+            color: if visible { color } else { Color32::TRANSPARENT },
+            italics: span.style.italic,
+            background: if span.style.code && !bold_only {
+                CODE_BACKGROUND
+            } else {
+                Color32::TRANSPARENT
+            },
+            ..Default::default()
+        };
+        job.append(&span.text, 0.0, format);
+    }
+    fonts.layout_job(job)
+}
+
+/// Size of the backdrop `t` needs, from its laid-out text.
+pub(crate) fn text_size(ctx: &egui::Context, t: &TextBox) -> [f32; 2] {
+    let galley = ctx.fonts(|f| text_galley(f, t, Color32::WHITE, false));
+    let size = galley.size() + 2.0 * Vec2::from(t.background.padding);
+    [size.x, size.y]
+}
+
+/// Inner joins are clamped to this many half-widths so hairpin turns don't spike.
+const MITER_LIMIT: f32 = 4.0;
+
+/// Builds a filled mesh for a pen stroke with round caps and round joins.
+/// `widths` holds the full width at each point, so the line may vary in thickness.
+///
+/// Unlike `Shape::line`, consecutive segments share their inner join vertex and the
+/// outer side of a join is filled by a single fan, so translucent strokes don't
+/// darken at every corner. The mesh has no feathering; edges are left to multisampling.
+pub(crate) fn stroke_mesh(points: &[Pos2], widths: &[f32], color: Color32) -> Mesh {
+    let mut mesh = Mesh::default();
+    let mut samples: Vec<(Pos2, f32)> = Vec::with_capacity(points.len());
+    for (&pos, &width) in points.iter().zip(widths) {
+        if samples.last().is_none_or(|&(last, _)| last != pos) {
+            samples.push((pos, width * 0.5));
+        }
+    }
+    match samples.len() {
+        0 => return mesh,
+        1 => {
+            let (center, radius) = samples[0];
+            add_fan(&mut mesh, center, radius, Vec2::X, TAU, color);
+            return mesh;
+        }
+        _ => {}
+    }
+
+    let direction = |i: usize| (samples[i + 1].0 - samples[i].0).normalized();
+    let (first, first_radius) = samples[0];
+    let first_dir = direction(0);
+    add_fan(
+        &mut mesh,
+        first,
+        first_radius,
+        first_dir.rot90(),
+        PI * sweep_sign(first_dir.rot90(), -first_dir),
+        color,
+    );
+
+    let mut start = [
+        add_vertex(&mut mesh, first + first_dir.rot90() * first_radius, color),
+        add_vertex(&mut mesh, first - first_dir.rot90() * first_radius, color),
+    ];
+    for i in 0..samples.len() - 1 {
+        let (end, radius) = samples[i + 1];
+        let normal = direction(i).rot90();
+        if i + 2 == samples.len() {
+            let end_quad = [
+                add_vertex(&mut mesh, end + normal * radius, color),
+                add_vertex(&mut mesh, end - normal * radius, color),
+            ];
+            add_quad(&mut mesh, start, end_quad);
+            add_fan(
+                &mut mesh,
+                end,
+                radius,
+                normal,
+                PI * sweep_sign(normal, direction(i)),
+                color,
+            );
+            break;
+        }
+
+        let next_normal = direction(i + 1).rot90();
+        // The outer side is the one facing away from where the stroke turns.
+        let outer = if normal.dot(direction(i + 1)) < 0.0 {
+            1.0
+        } else {
+            -1.0
+        };
+        // On a full reversal the bisector is zero and the inner corner collapses onto the point.
+        let bisector = (normal + next_normal).normalized();
+        let cos = bisector.dot(normal).max(1.0 / MITER_LIMIT);
+        let inner = add_vertex(&mut mesh, end - outer * bisector * (radius / cos), color);
+        let outer_in = add_vertex(&mut mesh, end + outer * normal * radius, color);
+        let outer_out = add_vertex(&mut mesh, end + outer * next_normal * radius, color);
+        let (end_quad, next_start) = if outer > 0.0 {
+            ([outer_in, inner], [outer_out, inner])
+        } else {
+            ([inner, outer_in], [inner, outer_out])
+        };
+        add_quad(&mut mesh, start, end_quad);
+        let from = outer * normal;
+        let to = outer * next_normal;
+        let angle = (from.x * to.y - from.y * to.x).atan2(from.dot(to));
+        add_fan(&mut mesh, end, radius, from, angle, color);
+        start = next_start;
+    }
+    mesh
+}
+
+fn sweep_sign(from: Vec2, through: Vec2) -> f32 {
+    if from.x * through.y - from.y * through.x >= 0.0 {
+        1.0
+    } else {
+        -1.0
+    }
+}
+
+fn add_vertex(mesh: &mut Mesh, pos: Pos2, color: Color32) -> u32 {
+    let idx = mesh.vertices.len() as u32;
+    mesh.colored_vertex(pos, color);
+    idx
+}
+
+fn add_quad(mesh: &mut Mesh, start: [u32; 2], end: [u32; 2]) {
+    mesh.add_triangle(start[0], start[1], end[0]);
+    mesh.add_triangle(start[1], end[1], end[0]);
+}
+
+/// Fills the circular sector starting at `center + from * radius` and turning by `sweep` radians.
+fn add_fan(mesh: &mut Mesh, center: Pos2, radius: f32, from: Vec2, sweep: f32, color: Color32) {
+    let steps = ((sweep.abs() / PI) * (radius.sqrt() * 3.0).clamp(4.0, 24.0)).ceil() as usize;
+    if steps == 0 || radius <= 0.0 {
+        return;
+    }
+    let hub = add_vertex(mesh, center, color);
+    let start_angle = from.angle();
+    let mut prev = add_vertex(mesh, center + from * radius, color);
+    for step in 1..=steps {
+        let angle = start_angle + sweep * step as f32 / steps as f32;
+        let next = add_vertex(mesh, center + Vec2::angled(angle) * radius, color);
+        mesh.add_triangle(hub, prev, next);
+        prev = next;
+    }
+}
+
+fn new_tessellator(ctx: &egui::Context) -> Tessellator {
+    let (font_tex_size, prepared_discs) = ctx.fonts(|fonts| {
+        (
+            fonts.font_image_size(),
+            fonts.texture_atlas().lock().prepared_discs(),
+        )
+    });
+    Tessellator::new(
+        ctx.pixels_per_point(),
+        ctx.tessellation_options(|options| *options),
+        font_tex_size,
+        prepared_discs,
+    )
+}
+
+/// 0 is full detail; each doubling of the point count beyond the budget adds a level.
+fn level_of_detail(total_points: usize) -> u32 {
+    let mut level = 0;
+    let mut budget = FULL_DETAIL_POINTS;
+    while total_points > budget && level < MAX_LOD {
+        level += 1;
+        budget *= 2;
+    }
+    level
+}
@@ -0,0 +1,121 @@
+//! Timers shown over the board. Times are seconds on egui's input clock, so they
+//! keep running while the overlay is not repainting.
+
+/// Counts down from a set duration; it can be paused and resumed.
+pub struct Countdown {
+    duration: f64,
+    /// Time left when it was last paused or reset.
+    left: f64,
+    /// When it was last started, while it is running.
+    started: Option<f64>,
+}
+
+impl Countdown {
+    pub fn new(duration: f64) -> Self {
+        Self {
+            duration,
+            left: duration,
+            started: None,
+        }
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.duration
+    }
+
+    /// Changes the duration and resets to it.
+    pub fn set_duration(&mut self, duration: f64) {
+        *self = Self::new(duration);
+    }
+
+    pub fn remaining(&self, now: f64) -> f64 {
+        let elapsed = self.started.map_or(0.0, |started| now - started);
+        (self.left - elapsed).max(0.0)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.started.is_some()
+    }
+
+    pub fn start(&mut self, now: f64) {
+        if self.started.is_none() && self.left > 0.0 {
+            self.started = Some(now);
+        }
+    }
+
+    pub fn pause(&mut self, now: f64) {
+        self.left = self.remaining(now);
+        self.started = None;
+    }
+
+    pub fn reset(&mut self) {
+        self.left = self.duration;
+        self.started = None;
+    }
+}
+
+/// Counts up while running, and keeps lap marks.
+#[derive(Default)]
+pub struct Stopwatch {
+    /// Time counted up to when it was last stopped.
+    counted: f64,
+    started: Option<f64>,
+    /// Elapsed time at each lap mark.
+    laps: Vec<f64>,
+}
+
+impl Stopwatch {
+    pub fn elapsed(&self, now: f64) -> f64 {
+        self.counted + self.started.map_or(0.0, |started| now - started)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.started.is_some()
+    }
+
+    pub fn toggle(&mut self, now: f64) {
+        match self.started.take() {
+            Some(started) => self.counted += now - started,
+            None => self.started = Some(now),
+        }
+    }
+
+    /// Marks a lap; does nothing while stopped.
+    pub fn lap(&mut self, now: f64) {
+        if self.is_running() {
+            self.laps.push(self.elapsed(now));
+        }
+    }
+
+    pub fn laps(&self) -> &[f64] {
+        &self.laps
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// `m:ss`, or `h:mm:ss` from an hour up, rounding partial seconds up so a countdown
+/// only shows `0:00` once it has run out.
+pub fn format_duration(seconds: f64) -> String {
+    let total = seconds.max(0.0).ceil() as u64;
+    let (hours, minutes, seconds) = (total / 3600, total / 60 % 60, total % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
+
+/// `m:ss.t`, or `h:mm:ss.t` from an hour up, for a running stopwatch.
+pub fn format_elapsed(seconds: f64) -> String {
+    let tenths = (seconds.max(0.0) * 10.0).floor() as u64;
+    let whole = tenths / 10;
+    let (hours, minutes, seconds) = (whole / 3600, whole / 60 % 60, whole % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}.{}", tenths % 10)
+    } else {
+        format!("{minutes}:{seconds:02}.{}", tenths % 10)
+    }
+}